@@ -0,0 +1,16 @@
+//! Feeds arbitrary input to the custom engine's parser. Compiling may succeed or
+//! fail, but it must never panic; when it succeeds, searching must not either.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use regexer::CustomRegex;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(pattern) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(regex) = CustomRegex::new(pattern) {
+        regex.find_ranges(pattern);
+    }
+});
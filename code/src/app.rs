@@ -1,26 +1,71 @@
 use color_eyre::Result;
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
-    layout::{Constraint, Layout, Position},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Position, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, List, ListItem, Paragraph},
+    widgets::{BarChart, Block, List, ListItem, ListState, Paragraph, Wrap},
     DefaultTerminal, Frame,
 };
-use std::fs;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::builder::{Regexer, RegexerBuilder};
+use crate::custom_regex::ast_tree;
+use crate::engines::{
+    apply_pattern, compiles, format_matches, is_implemented, match_ranges, match_set_split,
+    merge_ranges, replace_matches, validate, EngineChoice, MatchOutcome, Replaced, SearchLimits,
+};
+
+/// How many rows PageUp/PageDown move the expression selection.
+const PAGE_SIZE: isize = 10;
+
+/// How many edits Ctrl+Z can undo; older ones are forgotten.
+const UNDO_LIMIT: usize = 100;
 
-use crate::engines::{apply_pattern, EngineChoice};
+/// Inputs larger than this aren't matched on every keystroke; Enter still
+/// matches them in the background.
+const PREVIEW_MAX_BYTES: usize = 1 << 20;
 
+/// `PREVIEW_MAX_BYTES` for the custom and custommeta engines, which run the
+/// DFA from every start and so take quadratic time on inputs like a long run
+/// of `a`s under `a*`.
+const CUSTOM_PREVIEW_MAX_BYTES: usize = 4 << 10;
+
+/// How long the event loop waits for input before redrawing anyway.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the spinner turns while a background match runs.
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExpressionEntry {
     pattern: String,
     text: String,
     matches: String,
+    ranges: Vec<Range<usize>>,
 }
 
 pub enum InputMode {
     Normal,
     EditingPattern,
     EditingText,
+    EditingReplacement,
 }
 
 pub struct App {
@@ -31,6 +76,197 @@ pub struct App {
     pub character_index: usize,
     pub file: Option<String>,
     pub engine_choice: EngineChoice,
+    pub list_state: ListState,
+    /// The most recently entered text, used as the live preview's subject.
+    pub last_text: String,
+    /// Live match result for the pattern being edited, if any.
+    pub preview: Option<Preview>,
+    /// Where the expression history is saved on exit (`--history FILE`).
+    pub history_file: Option<String>,
+    /// The last pattern compiled by `add_expression`, keyed by pattern and
+    /// engine, so entering more text doesn't rebuild it.
+    compiled: Option<(String, EngineChoice, Arc<Regexer>)>,
+    /// Whether the pattern compiles, keyed like `compiled`, so drawing a frame
+    /// doesn't recompile it.
+    validity: Option<(String, EngineChoice, bool)>,
+    /// The `-f` file's contents, keyed by path, so previews don't reread it on
+    /// every keystroke.
+    file_cache: Option<(String, Arc<str>)>,
+    /// Opened by the first copy, see `copy_to_clipboard`.
+    clipboard: Option<arboard::Clipboard>,
+    /// Whether the full-screen key binding overlay (`?`) is showing.
+    pub show_help: bool,
+    /// Whether the custom engine's parse tree pane (`a`) is showing.
+    pub show_ast: bool,
+    /// Whether the per-expression match count chart (`h`) is showing.
+    pub show_histogram: bool,
+    /// Custom vs builtin matches shown in place of the expressions list (`v`).
+    pub diff_view: Option<DiffView>,
+    /// The input file browser (`o`), shown instead of the main layout.
+    pub file_picker: Option<FilePicker>,
+    /// A problem to show in the status bar, such as an unreadable `-f` file.
+    pub message: Option<String>,
+    /// A match running on a background thread; Esc cancels it. New submissions
+    /// are rejected until it finishes.
+    pub pending: Option<PendingMatch>,
+    /// The last replacement string entered with `r`.
+    pub replacement: String,
+    /// The text with the replacement being edited applied, or why it can't be.
+    pub replace_preview: Option<Result<Replaced, String>>,
+    /// Where applying a replacement writes the result (`-o FILE`).
+    pub output_file: Option<String>,
+    /// `input` and `character_index` before each edit, newest last (Ctrl+Z).
+    undo_stack: VecDeque<(String, usize)>,
+    /// States undone since the last edit, newest last (Ctrl+Y).
+    redo_stack: Vec<(String, usize)>,
+    /// Submitted patterns, oldest first, recalled with Up / Down while editing.
+    pub pattern_history: Vec<String>,
+    /// The entry of `pattern_history` being shown, or `None` for the new pattern.
+    pub history_index: Option<usize>,
+    /// The new pattern as typed before browsing the history.
+    history_draft: String,
+}
+
+/// The live preview of the pattern being edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    /// `None` when the input is too large to match on every keystroke.
+    pub outcome: Option<MatchOutcome>,
+    /// The outcome as the preview pane shows it.
+    pub summary: String,
+}
+
+/// An expression whose matches are still being computed off the render thread.
+/// The worker sends the ranges back over `receiver`, and stops searching once
+/// `cancel` is set, which is how Esc cancels.
+pub struct PendingMatch {
+    pattern: String,
+    text: String,
+    receiver: Receiver<Vec<Range<usize>>>,
+    cancel: Arc<AtomicBool>,
+    started: Instant,
+}
+
+/// One row of the file browser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Lists `dir` for the file browser: `..` first when there is a parent, then
+/// directories, then files, each sorted by name. Entries that can't be read are
+/// skipped.
+pub fn list_directory(dir: &Path) -> io::Result<Vec<PickerEntry>> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let is_dir = file_type.is_dir();
+        let picker_entry = PickerEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path: entry.path(),
+            is_dir,
+        };
+        if is_dir {
+            dirs.push(picker_entry);
+        } else {
+            files.push(picker_entry);
+        }
+    }
+    dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut entries = Vec::new();
+    if let Some(parent) = dir.parent() {
+        entries.push(PickerEntry {
+            name: "..".to_string(),
+            path: parent.to_path_buf(),
+            is_dir: true,
+        });
+    }
+    entries.extend(dirs);
+    entries.extend(files);
+    Ok(entries)
+}
+
+/// State of the file browser: the directory shown, its entries, the selection
+/// and the last error (an unreadable directory or file).
+#[derive(Debug)]
+pub struct FilePicker {
+    pub dir: PathBuf,
+    pub entries: Vec<PickerEntry>,
+    pub list_state: ListState,
+    pub error: Option<String>,
+}
+
+impl FilePicker {
+    pub fn open(dir: PathBuf) -> Self {
+        let mut picker = FilePicker {
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            error: None,
+        };
+        picker.change_dir(dir);
+        picker
+    }
+
+    /// Shows `dir`, or keeps the current listing and records the error if it
+    /// can't be read.
+    fn change_dir(&mut self, dir: PathBuf) {
+        match list_directory(&dir) {
+            Ok(entries) => {
+                self.list_state
+                    .select(if entries.is_empty() { None } else { Some(0) });
+                self.entries = entries;
+                self.dir = dir;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Cannot open {}: {}", dir.display(), e)),
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let last = self.entries.len() as isize - 1;
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        self.list_state
+            .select(Some((current + delta).clamp(0, last) as usize));
+    }
+}
+
+/// The matches the custom and builtin engines each find for one pattern and
+/// text, or the engine's compile error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffView {
+    pub pattern: String,
+    pub custom: Result<Vec<String>, String>,
+    pub builtin: Result<Vec<String>, String>,
+}
+
+impl DiffView {
+    pub fn new(pattern: &str, text: &str) -> Self {
+        let limits = SearchLimits::default();
+        let run = |engine_choice| {
+            validate(pattern, &engine_choice, &limits).map(|()| {
+                match_ranges(pattern, text, &engine_choice, &limits, None)
+                    .into_iter()
+                    .map(|range| text[range].to_string())
+                    .collect()
+            })
+        };
+        Self {
+            pattern: pattern.to_string(),
+            custom: run(EngineChoice::Custom),
+            builtin: run(EngineChoice::Builtin),
+        }
+    }
 }
 
 impl App {
@@ -43,6 +279,29 @@ impl App {
             character_index: 0,
             file: None,
             engine_choice,
+            list_state: ListState::default(),
+            last_text: String::new(),
+            preview: None,
+            history_file: None,
+            compiled: None,
+            validity: None,
+            show_help: false,
+            show_ast: false,
+            show_histogram: false,
+            diff_view: None,
+            file_picker: None,
+            message: None,
+            pending: None,
+            replacement: String::new(),
+            replace_preview: None,
+            file_cache: None,
+            clipboard: None,
+            output_file: None,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            pattern_history: Vec::new(),
+            history_index: None,
+            history_draft: String::new(),
         }
     }
 
@@ -51,6 +310,7 @@ impl App {
     }
 
     pub fn set_text(&mut self, t: &str) {
+        self.last_text = t.to_string();
         self.input = t.to_string();
         self.character_index = self.input.chars().count();
     }
@@ -59,6 +319,17 @@ impl App {
         self.file = f;
     }
 
+    /// Where applying a replacement (`r`, then Enter) writes the replaced text.
+    pub fn set_output_file(&mut self, path: &str) {
+        self.output_file = Some(path.to_string());
+    }
+
+    /// Loads the saved expressions from `path` and saves back to it on exit.
+    pub fn set_history_file(&mut self, path: &str) {
+        self.expressions = load_history(path);
+        self.history_file = Some(path.to_string());
+    }
+
     pub fn pattern_is_empty(&self) -> bool {
         self.pattern.is_empty()
     }
@@ -81,7 +352,110 @@ impl App {
         self.character_index = self.clamp_cursor(cursor_moved_right);
     }
 
+    fn move_cursor_to_end(&mut self) {
+        self.character_index = self.clamp_cursor(self.input.chars().count());
+    }
+
+    /// Moves to the start of the previous whitespace-delimited word.
+    fn move_cursor_word_left(&mut self) {
+        let chars: Vec<char> = self.input.chars().collect();
+        let mut index = self.clamp_cursor(self.character_index);
+        while index > 0 && chars[index - 1].is_whitespace() {
+            index -= 1;
+        }
+        while index > 0 && !chars[index - 1].is_whitespace() {
+            index -= 1;
+        }
+        self.character_index = self.clamp_cursor(index);
+    }
+
+    /// Moves to the end of the next whitespace-delimited word.
+    fn move_cursor_word_right(&mut self) {
+        let chars: Vec<char> = self.input.chars().collect();
+        let mut index = self.clamp_cursor(self.character_index);
+        while index < chars.len() && chars[index].is_whitespace() {
+            index += 1;
+        }
+        while index < chars.len() && !chars[index].is_whitespace() {
+            index += 1;
+        }
+        self.character_index = self.clamp_cursor(index);
+    }
+
+    /// Saves the input before an edit so Ctrl+Z can restore it. A new edit
+    /// discards anything that was undone.
+    fn save_undo(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack
+            .push_back((self.input.clone(), self.character_index));
+        self.redo_stack.clear();
+    }
+
+    /// Restores the input as it was before the last edit (Ctrl+Z).
+    pub fn undo(&mut self) {
+        if let Some(state) = self.undo_stack.pop_back() {
+            let current = std::mem::replace(&mut self.input, state.0);
+            self.redo_stack.push((
+                current,
+                std::mem::replace(&mut self.character_index, state.1),
+            ));
+        }
+    }
+
+    /// Reapplies the last undone edit (Ctrl+Y).
+    pub fn redo(&mut self) {
+        if let Some(state) = self.redo_stack.pop() {
+            let current = std::mem::replace(&mut self.input, state.0);
+            self.undo_stack.push_back((
+                current,
+                std::mem::replace(&mut self.character_index, state.1),
+            ));
+        }
+    }
+
+    /// Shows the previous submitted pattern (Up), keeping the new one as a
+    /// draft. Stays on the oldest entry.
+    pub fn history_prev(&mut self) {
+        let index = match self.history_index {
+            _ if self.pattern_history.is_empty() => return,
+            None => {
+                self.history_draft = self.input.clone();
+                self.pattern_history.len() - 1
+            }
+            Some(index) => index.saturating_sub(1),
+        };
+        self.show_history_entry(Some(index));
+    }
+
+    /// Shows the next submitted pattern (Down), and past the newest the draft
+    /// as it was left.
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            None => {}
+            Some(index) if index + 1 < self.pattern_history.len() => {
+                self.show_history_entry(Some(index + 1))
+            }
+            Some(_) => self.show_history_entry(None),
+        }
+    }
+
+    fn show_history_entry(&mut self, index: Option<usize>) {
+        let entry = match index {
+            Some(index) => self.pattern_history[index].clone(),
+            None => std::mem::take(&mut self.history_draft),
+        };
+        if entry != self.input {
+            self.save_undo();
+            self.input = entry;
+        }
+        self.history_index = index;
+        self.move_cursor_to_end();
+    }
+
     fn enter_char(&mut self, new_char: char) {
+        self.save_undo();
         let index = self.byte_index();
         self.input.insert(index, new_char);
         self.move_cursor_right();
@@ -97,6 +471,7 @@ impl App {
 
     fn delete_char(&mut self) {
         if self.character_index != 0 {
+            self.save_undo();
             let current_index = self.character_index;
             let from_left_to_current_index = current_index - 1;
 
@@ -108,6 +483,37 @@ impl App {
         }
     }
 
+    /// Deletes the word before the cursor, along with any whitespace between it
+    /// and the cursor (Ctrl+W).
+    fn delete_word_before_cursor(&mut self) {
+        let end = self.clamp_cursor(self.character_index);
+        if end == 0 {
+            return;
+        }
+        self.save_undo();
+        self.move_cursor_word_left();
+        let start = self.character_index;
+        self.remove_chars(start, end);
+    }
+
+    /// Deletes everything from the start of the input up to the cursor (Ctrl+U).
+    fn delete_to_line_start(&mut self) {
+        let end = self.clamp_cursor(self.character_index);
+        if end == 0 {
+            return;
+        }
+        self.save_undo();
+        self.reset_cursor();
+        self.remove_chars(0, end);
+    }
+
+    /// Removes the characters in `start..end` (character indices, not bytes).
+    fn remove_chars(&mut self, start: usize, end: usize) {
+        let before = self.input.chars().take(start);
+        let after = self.input.chars().skip(end);
+        self.input = before.chain(after).collect();
+    }
+
     fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
         new_cursor_pos.clamp(0, self.input.chars().count())
     }
@@ -116,109 +522,795 @@ impl App {
         self.character_index = 0;
     }
 
+    /// Reports a running match in the status bar; submissions wait for it.
+    fn busy(&mut self) -> bool {
+        if self.pending.is_some() {
+            self.message = Some("A match is still running (Esc to cancel)".to_string());
+        }
+        self.pending.is_some()
+    }
+
     fn submit_pattern(&mut self) {
+        if self.busy() {
+            return;
+        }
+        // Like a shell, an immediate repeat is only remembered once.
+        if !self.input.is_empty() && self.pattern_history.last() != Some(&self.input) {
+            self.pattern_history.push(self.input.clone());
+        }
         self.pattern = self.input.clone();
         self.input.clear();
         self.reset_cursor();
         self.input_mode = InputMode::Normal;
 
         if let Some(file_name) = &self.file {
-            if let Ok(contents) = fs::read_to_string(file_name) {
-                self.add_expression(contents);
+            match fs::read_to_string(file_name) {
+                Ok(contents) => {
+                    self.message = None;
+                    self.start_expression(contents);
+                }
+                // An empty name means the file was already used for an earlier pattern.
+                Err(_) if file_name.is_empty() => {}
+                Err(e) => self.message = Some(format!("Cannot read file {}: {}", file_name, e)),
             }
         }
         self.file = Some("".to_string());
     }
 
     fn submit_text(&mut self) {
+        if self.busy() {
+            return;
+        }
         let txt = self.input.clone();
+        self.last_text = txt.clone();
         self.input.clear();
         self.reset_cursor();
         self.input_mode = InputMode::Normal;
-        self.add_expression(txt);
+        self.start_expression(txt);
     }
 
-    fn add_expression(&mut self, text: String) {
-        let matches = apply_pattern(&self.pattern, &text, &self.engine_choice);
+    /// Moves the expression selection by `delta` rows, clamped to the list bounds.
+    /// With nothing selected yet, moving down starts at the first entry and moving
+    /// up starts at the last (newest) one.
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.expressions.is_empty() {
+            self.list_state.select(None);
+            return;
+        }
+        let last = self.expressions.len() - 1;
+        let selected = match self.list_state.selected() {
+            Some(current) => (current as isize + delta).clamp(0, last as isize) as usize,
+            None if delta > 0 => 0,
+            None => last,
+        };
+        self.list_state.select(Some(selected));
+    }
+
+    /// Removes the selected expression and keeps the selection on a valid row:
+    /// the entry that moved into its place, or the new last entry when the last
+    /// one was deleted.
+    pub fn delete_selected_expression(&mut self) {
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        if selected >= self.expressions.len() {
+            return;
+        }
+        self.expressions.remove(selected);
+        if self.expressions.is_empty() {
+            self.list_state.select(None);
+        } else {
+            self.list_state
+                .select(Some(selected.min(self.expressions.len() - 1)));
+        }
+    }
+
+    /// Empties the expression history. The pattern and input buffer are untouched.
+    pub fn clear_expressions(&mut self) {
+        self.expressions.clear();
+        self.list_state = ListState::default();
+    }
+
+    /// The compiled matcher for the current pattern and engine, reusing the
+    /// cached one when neither has changed. `None` if the pattern doesn't compile.
+    pub fn compiled_regexer(&mut self) -> Option<Arc<Regexer>> {
+        if let Some((pattern, engine, regexer)) = &self.compiled {
+            if *pattern == self.pattern && *engine == self.engine_choice {
+                return Some(Arc::clone(regexer));
+            }
+        }
+        self.compiled = None;
+        let regexer = Arc::new(
+            RegexerBuilder::new(&self.pattern)
+                .engine(self.engine_choice)
+                .build()
+                .ok()?,
+        );
+        self.compiled = Some((
+            self.pattern.clone(),
+            self.engine_choice,
+            Arc::clone(&regexer),
+        ));
+        Some(regexer)
+    }
+
+    /// Whether the current pattern compiles for the current engine, checked
+    /// once per pattern and engine rather than on every frame.
+    fn pattern_compiles(&mut self) -> bool {
+        if let Some((pattern, engine, valid)) = &self.validity {
+            if *pattern == self.pattern && *engine == self.engine_choice {
+                return *valid;
+            }
+        }
+        let valid = compiles(&self.pattern, &self.engine_choice);
+        self.validity = Some((self.pattern.clone(), self.engine_choice, valid));
+        valid
+    }
+
+    pub fn add_expression(&mut self, text: String) {
+        // Placeholder engines only echo their name, so don't pretend to evaluate.
+        let (matches, ranges) = if is_implemented(&self.engine_choice) {
+            match self.compiled_regexer() {
+                Some(regexer) => {
+                    let ranges = regexer.find_ranges(&text);
+                    let found: Vec<&str> = ranges.iter().map(|r| &text[r.clone()]).collect();
+                    (format_matches(&found), ranges)
+                }
+                // Let apply_pattern word the compile error.
+                None => (
+                    apply_pattern(&self.pattern, &text, &self.engine_choice).describe(&text),
+                    Vec::new(),
+                ),
+            }
+        } else {
+            (MatchOutcome::NotImplemented.describe(&text), Vec::new())
+        };
+        self.push_expression(self.pattern.clone(), text, matches, ranges);
+    }
+
+    fn push_expression(
+        &mut self,
+        pattern: String,
+        text: String,
+        matches: String,
+        ranges: Vec<Range<usize>>,
+    ) {
         self.expressions.push(ExpressionEntry {
-            pattern: self.pattern.clone(),
+            pattern,
             text,
             matches,
+            ranges,
+        });
+        // Follow the newest entry so it scrolls into view.
+        self.list_state.select(Some(self.expressions.len() - 1));
+    }
+
+    /// Like `add_expression`, but searches on a background thread so a large
+    /// text doesn't freeze the UI. `poll_pending` picks up the result. Compile
+    /// errors and placeholder engines are reported straight away. Rejected
+    /// while another match is still running.
+    pub fn start_expression(&mut self, text: String) {
+        if self.busy() {
+            return;
+        }
+        let regexer = if is_implemented(&self.engine_choice) {
+            self.compiled_regexer()
+        } else {
+            None
+        };
+        let Some(regexer) = regexer else {
+            self.add_expression(text);
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = Arc::clone(&cancel);
+        let worker_text = text.clone();
+        thread::spawn(move || {
+            // A cancelled match has nobody left to report to.
+            if let Some(ranges) = regexer.find_ranges_cancellable(&worker_text, &worker_cancel) {
+                let _ = sender.send(ranges);
+            }
+        });
+        self.pending = Some(PendingMatch {
+            pattern: self.pattern.clone(),
+            text,
+            receiver,
+            cancel,
+            started: Instant::now(),
         });
     }
 
+    /// Adds the background match's expression once its result has arrived.
+    pub fn poll_pending(&mut self) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        match pending.receiver.try_recv() {
+            Err(TryRecvError::Empty) => {}
+            Ok(ranges) => {
+                let pending = self.pending.take().unwrap();
+                let found: Vec<&str> = ranges.iter().map(|r| &pending.text[r.clone()]).collect();
+                let matches = format_matches(&found);
+                self.push_expression(pending.pattern, pending.text, matches, ranges);
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.pending = None;
+                self.message = Some("Match failed".to_string());
+            }
+        }
+    }
+
+    /// Stops the background match; the worker gives up at its next check.
+    pub fn cancel_pending(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.cancel.store(true, Ordering::Relaxed);
+            self.message = Some("Match cancelled".to_string());
+        }
+    }
+
     pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         loop {
             terminal.draw(|frame| self.draw(frame))?;
 
-            if let Event::Key(key) = event::read()? {
-                // Handle Ctrl+C globally
-                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                    return Ok(());
+            // Poll rather than block so the loop wakes up regularly and redraws,
+            // e.g. after a resize, even when no key is pressed. Wake up more
+            // often while a match runs, to turn the spinner and pick up the result.
+            let timeout = if self.pending.is_some() {
+                SPINNER_INTERVAL
+            } else {
+                POLL_INTERVAL
+            };
+            let ready = event::poll(timeout)?;
+            self.poll_pending();
+            if !ready {
+                continue;
+            }
+            if self.handle_event(event::read()?) {
+                if let Some(path) = &self.history_file {
+                    save_history(path, &self.expressions)?;
                 }
+                return Ok(());
+            }
+        }
+    }
 
-                match self.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('e') => {
-                            self.input_mode = InputMode::EditingText;
-                        }
-                        KeyCode::Char('p') => {
-                            self.input_mode = InputMode::EditingPattern;
-                            self.input = self.pattern.clone();
-                            self.character_index = self.input.chars().count();
-                        }
-                        KeyCode::Char('t') => {
-                            self.input_mode = InputMode::EditingText;
-                            self.input.clear();
-                            self.reset_cursor();
-                        }
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            return Ok(());
-                        }
-                        _ => {}
-                    },
-                    InputMode::EditingPattern if key.kind == KeyEventKind::Press => {
-                        match key.code {
-                            KeyCode::Enter => self.submit_pattern(),
-                            KeyCode::Char(to_insert) => self.enter_char(to_insert),
-                            KeyCode::Backspace => self.delete_char(),
-                            KeyCode::Left => self.move_cursor_left(),
-                            KeyCode::Right => self.move_cursor_right(),
-                            KeyCode::Esc => {
-                                self.input_mode = InputMode::Normal;
-                                self.input.clear();
-                                self.reset_cursor();
-                            }
-                            _ => {}
+    /// Applies one terminal event and returns true when the app should exit.
+    /// Resizes need no state change; the next loop iteration redraws at the
+    /// new size.
+    pub fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Key(key) => self.handle_key(key),
+            _ => false,
+        }
+    }
+
+    /// Applies one key press and returns true when the app should exit.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        // Handle Ctrl+C globally
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return true;
+        }
+
+        if self.file_picker.is_some() {
+            self.handle_picker_key(key);
+            return false;
+        }
+
+        // The help overlay swallows every other key until it is dismissed.
+        if self.show_help {
+            if matches!(key.code, KeyCode::Char('?') | KeyCode::Esc) {
+                self.show_help = false;
+            }
+            return false;
+        }
+
+        match self.input_mode {
+            InputMode::Normal => match key.code {
+                KeyCode::Char('e') => {
+                    self.input_mode = InputMode::EditingText;
+                }
+                KeyCode::Char('p') => {
+                    self.input_mode = InputMode::EditingPattern;
+                    self.input = self.pattern.clone();
+                    self.character_index = self.input.chars().count();
+                }
+                KeyCode::Char('t') => {
+                    self.input_mode = InputMode::EditingText;
+                    self.input.clear();
+                    self.reset_cursor();
+                }
+                KeyCode::Esc if self.pending.is_some() => self.cancel_pending(),
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    return true;
+                }
+                KeyCode::Up => self.move_selection(-1),
+                KeyCode::Down => self.move_selection(1),
+                KeyCode::PageUp => self.move_selection(-PAGE_SIZE),
+                KeyCode::PageDown => self.move_selection(PAGE_SIZE),
+                KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.clear_expressions()
+                }
+                KeyCode::Char('C') => self.clear_expressions(),
+                KeyCode::Char('g') => self.engine_choice = self.engine_choice.next(),
+                KeyCode::Char('d') | KeyCode::Delete => self.delete_selected_expression(),
+                KeyCode::Char('?') => self.show_help = true,
+                KeyCode::Char('v') => self.toggle_diff_view(),
+                KeyCode::Char('a') => self.show_ast = !self.show_ast,
+                KeyCode::Char('h') => self.show_histogram = !self.show_histogram,
+                KeyCode::Char('y') => self.copy_selected_matches(),
+                KeyCode::Char('r') => {
+                    self.input_mode = InputMode::EditingReplacement;
+                    self.input = self.replacement.clone();
+                    self.character_index = self.input.chars().count();
+                }
+                KeyCode::Char('o') => {
+                    let dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+                    self.file_picker = Some(FilePicker::open(dir));
+                }
+                _ => {}
+            },
+            InputMode::EditingPattern if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => self.submit_pattern(),
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.delete_word_before_cursor()
+                }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.delete_to_line_start()
+                }
+                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => self.undo(),
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => self.redo(),
+                KeyCode::Char(to_insert) => self.enter_char(to_insert),
+                KeyCode::Backspace => self.delete_char(),
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_cursor_word_left()
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_cursor_word_right()
+                }
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Up => self.history_prev(),
+                KeyCode::Down => self.history_next(),
+                KeyCode::Home => self.reset_cursor(),
+                KeyCode::End => self.move_cursor_to_end(),
+                KeyCode::Esc => {
+                    self.input_mode = InputMode::Normal;
+                    self.input.clear();
+                    self.reset_cursor();
+                }
+                _ => {}
+            },
+            InputMode::EditingText if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => self.submit_text(),
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.delete_word_before_cursor()
+                }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.delete_to_line_start()
+                }
+                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => self.undo(),
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => self.redo(),
+                KeyCode::Char(to_insert) => self.enter_char(to_insert),
+                KeyCode::Backspace => self.delete_char(),
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_cursor_word_left()
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_cursor_word_right()
+                }
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Home => self.reset_cursor(),
+                KeyCode::End => self.move_cursor_to_end(),
+                KeyCode::Esc => {
+                    self.input_mode = InputMode::Normal;
+                    self.input.clear();
+                    self.reset_cursor();
+                }
+                _ => {}
+            },
+            InputMode::EditingReplacement if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => self.apply_replacement(),
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.delete_word_before_cursor()
+                }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.delete_to_line_start()
+                }
+                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => self.undo(),
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => self.redo(),
+                KeyCode::Char(to_insert) => self.enter_char(to_insert),
+                KeyCode::Backspace => self.delete_char(),
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_cursor_word_left()
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_cursor_word_right()
+                }
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Home => self.reset_cursor(),
+                KeyCode::End => self.move_cursor_to_end(),
+                KeyCode::Esc => {
+                    self.input_mode = InputMode::Normal;
+                    self.input.clear();
+                    self.reset_cursor();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        if matches!(self.input_mode, InputMode::EditingPattern) {
+            self.update_preview();
+        } else {
+            self.preview = None;
+        }
+        if matches!(self.input_mode, InputMode::EditingReplacement) {
+            self.update_replace_preview();
+        } else {
+            self.replace_preview = None;
+        }
+        // Each editing session starts with nothing to undo, on a new pattern.
+        if matches!(self.input_mode, InputMode::Normal) {
+            self.undo_stack.clear();
+            self.redo_stack.clear();
+            self.history_index = None;
+            self.history_draft.clear();
+        }
+        false
+    }
+
+    /// Keys while the file browser is open: arrows move, Enter opens a directory
+    /// or picks a file, Esc closes it.
+    fn handle_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.file_picker else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => picker.move_selection(-1),
+            KeyCode::Down => picker.move_selection(1),
+            KeyCode::PageUp => picker.move_selection(-PAGE_SIZE),
+            KeyCode::PageDown => picker.move_selection(PAGE_SIZE),
+            KeyCode::Esc => self.file_picker = None,
+            KeyCode::Enter => {
+                let Some(entry) = picker
+                    .list_state
+                    .selected()
+                    .and_then(|i| picker.entries.get(i))
+                    .cloned()
+                else {
+                    return;
+                };
+                if entry.is_dir {
+                    picker.change_dir(entry.path);
+                    return;
+                }
+                match fs::read_to_string(&entry.path) {
+                    Ok(contents) => {
+                        self.file = Some(entry.path.to_string_lossy().into_owned());
+                        self.file_picker = None;
+                        // Re-run the current pattern against the new file.
+                        if !self.pattern.is_empty() {
+                            self.start_expression(contents);
                         }
                     }
-                    InputMode::EditingText if key.kind == KeyEventKind::Press => match key.code {
-                        KeyCode::Enter => self.submit_text(),
-                        KeyCode::Char(to_insert) => self.enter_char(to_insert),
-                        KeyCode::Backspace => self.delete_char(),
-                        KeyCode::Left => self.move_cursor_left(),
-                        KeyCode::Right => self.move_cursor_right(),
-                        KeyCode::Esc => {
-                            self.input_mode = InputMode::Normal;
-                            self.input.clear();
-                            self.reset_cursor();
-                        }
-                        _ => {}
-                    },
-                    _ => {}
+                    Err(e) => {
+                        picker.error = Some(format!("Cannot read {}: {}", entry.name, e));
+                    }
                 }
             }
+            _ => {}
+        }
+    }
+
+    /// The bottom status bar: active engine, history size and how many matches
+    /// the most recent expression found.
+    pub fn status_line(&self) -> String {
+        let last = match self.expressions.last() {
+            Some(entry) => entry.ranges.len().to_string(),
+            None => "-".to_string(),
+        };
+        let status = format!(
+            "Engine: {} | Expressions: {} | Last matches: {}",
+            self.engine_choice.name(),
+            self.expressions.len(),
+            last
+        );
+        let status = match &self.pending {
+            Some(pending) => {
+                let turns = pending.started.elapsed().as_millis() / SPINNER_INTERVAL.as_millis();
+                let frame = SPINNER[turns as usize % SPINNER.len()];
+                format!("{} | {} Matching... (Esc to cancel)", status, frame)
+            }
+            None => status,
+        };
+        match &self.message {
+            Some(message) => format!("{} | {}", status, message),
+            None => status,
+        }
+    }
+
+    /// The match string `y` copies: the selected expression's result line.
+    pub fn selected_matches(&self) -> Option<&str> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.expressions.get(i))
+            .map(|entry| entry.matches.as_str())
+    }
+
+    /// Copies the selected expression's matches to the system clipboard and
+    /// reports how it went in the status bar.
+    fn copy_selected_matches(&mut self) {
+        let Some(matches) = self.selected_matches().map(str::to_string) else {
+            self.message = Some("Select an expression to copy its matches".to_string());
+            return;
+        };
+        let message = match self.copy_to_clipboard(matches) {
+            Ok(()) => "Copied matches to the clipboard".to_string(),
+            Err(e) => format!("Cannot copy: {}", e),
+        };
+        self.message = Some(message);
+    }
+
+    /// Opens the system clipboard on first use and keeps it open, since on X11
+    /// the copied text is only served while it lives. Fails instead of
+    /// panicking when there is none, as on a headless box.
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), arboard::Error> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_text(text)
+    }
+
+    /// The pattern the parse tree pane shows: the one being edited, so the tree
+    /// follows each keystroke, otherwise the submitted pattern.
+    pub fn ast_pattern(&self) -> &str {
+        if matches!(self.input_mode, InputMode::EditingPattern) && !self.input.is_empty() {
+            &self.input
+        } else {
+            &self.pattern
+        }
+    }
+
+    /// One bar per expression for the `h` chart: its index as the label and
+    /// how many matches it found.
+    pub fn histogram_data(&self) -> Vec<(String, u64)> {
+        self.expressions
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (i.to_string(), entry.ranges.len() as u64))
+            .collect()
+    }
+
+    /// Opens the diff view for the selected expression, or for the pattern and
+    /// last entered text when nothing is selected; closes it if already open.
+    pub fn toggle_diff_view(&mut self) {
+        if self.diff_view.take().is_some() {
+            return;
+        }
+        let (pattern, text) = match self
+            .list_state
+            .selected()
+            .and_then(|i| self.expressions.get(i))
+        {
+            Some(entry) => (entry.pattern.clone(), entry.text.clone()),
+            None => (self.pattern.clone(), self.last_text.clone()),
+        };
+        self.diff_view = Some(DiffView::new(&pattern, &text));
+    }
+
+    /// Full-screen list of every key binding, shown instead of the main layout.
+    fn draw_help(&self, frame: &mut Frame) {
+        const BINDINGS: [(&str, &str); 24] = [
+            ("Normal mode", ""),
+            ("p", "edit the pattern"),
+            ("t / e", "enter text to match against the pattern"),
+            ("Up / Down", "select the previous / next expression"),
+            ("PgUp / PgDn", "move the selection by a page"),
+            ("d / Delete", "delete the selected expression"),
+            ("C / Ctrl+L", "clear all expressions"),
+            ("g", "switch to the next engine"),
+            ("v", "compare custom and builtin matches side by side"),
+            ("a", "show or hide the pattern's parse tree"),
+            ("h", "show or hide a chart of each expression's match count"),
+            (
+                "r",
+                "preview replacing the pattern's matches; Enter applies",
+            ),
+            ("o", "browse for an input file"),
+            ("y", "copy the selected expression's matches"),
+            ("?", "show or hide this help"),
+            ("q / Esc", "exit (Esc first cancels a running match)"),
+            ("Editing", ""),
+            ("Enter", "submit the pattern or text"),
+            ("Esc", "stop editing"),
+            (
+                "Left / Right, Home / End",
+                "move the cursor (with Ctrl: by word)",
+            ),
+            (
+                "Ctrl+W / Ctrl+U",
+                "delete the previous word / to the line start",
+            ),
+            ("Ctrl+Z / Ctrl+Y", "undo / redo an edit"),
+            ("Up / Down", "recall earlier patterns while editing one"),
+            ("Ctrl+C", "exit from anywhere"),
+        ];
+        let mut lines = vec![
+            Line::from(vec!["Engine: ".into(), self.engine_choice.name().bold()]),
+            Line::from(""),
+        ];
+        for (keys, action) in BINDINGS {
+            if action.is_empty() {
+                lines.push(Line::from(keys.underlined()));
+            } else {
+                lines.push(Line::from(vec![
+                    format!("  {:<26}", keys).bold(),
+                    action.into(),
+                ]));
+            }
+        }
+        let help = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title("Help (press ? or Esc to close)"));
+        frame.render_widget(help, frame.area());
+    }
+
+    /// The text previews run against: the file contents, read once per path,
+    /// or the last entered text when there is no readable file.
+    fn preview_text(&mut self) -> Arc<str> {
+        let Some(file_name) = &self.file else {
+            return Arc::from(self.last_text.as_str());
+        };
+        if let Some((path, contents)) = &self.file_cache {
+            if path == file_name {
+                return Arc::clone(contents);
+            }
+        }
+        match fs::read_to_string(file_name) {
+            Ok(contents) => {
+                let contents: Arc<str> = Arc::from(contents);
+                self.file_cache = Some((file_name.clone(), Arc::clone(&contents)));
+                contents
+            }
+            Err(_) => Arc::from(self.last_text.as_str()),
+        }
+    }
+
+    /// The largest input the previews match on every keystroke with the
+    /// current engine.
+    fn preview_max_bytes(&self) -> usize {
+        match self.engine_choice {
+            EngineChoice::Custom | EngineChoice::Custommeta => CUSTOM_PREVIEW_MAX_BYTES,
+            _ => PREVIEW_MAX_BYTES,
         }
     }
 
-    fn draw(&self, frame: &mut Frame) {
+    /// Re-runs the pattern being edited against `preview_text`. Invalid patterns
+    /// show up as the engine's error message instead of a match result. Inputs
+    /// over `preview_max_bytes` are only checked for compile errors.
+    fn update_preview(&mut self) {
+        if self.input.is_empty() {
+            self.preview = None;
+            return;
+        }
+        let text = self.preview_text();
+        let max_bytes = self.preview_max_bytes();
+        let outcome = if text.len() <= max_bytes {
+            apply_pattern(&self.input, &text, &self.engine_choice)
+        } else {
+            match validate(&self.input, &self.engine_choice, &SearchLimits::default()) {
+                Err(e) => MatchOutcome::InvalidPattern(e),
+                Ok(()) => {
+                    self.preview = Some(Preview {
+                        outcome: None,
+                        summary: too_large_to_preview(max_bytes, "match it in the background"),
+                    });
+                    return;
+                }
+            }
+        };
+        self.preview = Some(Preview {
+            summary: outcome.describe(&text),
+            outcome: Some(outcome),
+        });
+    }
+
+    /// Applies the replacement being edited to `preview_text` with the current
+    /// pattern, unless the input is over `preview_max_bytes`.
+    fn update_replace_preview(&mut self) {
+        let text = self.preview_text();
+        let max_bytes = self.preview_max_bytes();
+        self.replace_preview = Some(if text.len() <= max_bytes {
+            replace_matches(&self.pattern, &text, &self.engine_choice, &self.input)
+        } else {
+            Err(too_large_to_preview(max_bytes, "apply the replacement"))
+        });
+    }
+
+    /// Keeps the replacement and applies it: the result is written to the
+    /// output file when there is one, and otherwise becomes the text later
+    /// expressions match against.
+    fn apply_replacement(&mut self) {
+        self.replacement = std::mem::take(&mut self.input);
+        self.reset_cursor();
+        self.input_mode = InputMode::Normal;
+        let text = self.preview_text();
+        let replaced =
+            match replace_matches(&self.pattern, &text, &self.engine_choice, &self.replacement) {
+                Ok(replaced) => replaced,
+                Err(e) => {
+                    self.message = Some(format!("Cannot replace: {}", e));
+                    return;
+                }
+            };
+        let count = replaced.replaced.len();
+        self.message = Some(match &self.output_file {
+            Some(path) => match fs::write(path, &replaced.text) {
+                Ok(()) => format!("Replaced {} matches into {}", count, path),
+                Err(e) => format!("Cannot write {}: {}", path, e),
+            },
+            None => {
+                self.last_text = replaced.text;
+                format!("Replaced {} matches", count)
+            }
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        if self.show_help {
+            self.draw_help(frame);
+            return;
+        }
+        if let Some(picker) = &mut self.file_picker {
+            draw_file_picker(frame, picker);
+            return;
+        }
+
+        let banner_height = if is_implemented(&self.engine_choice) {
+            0
+        } else {
+            3
+        };
+        let preview_height = if self.preview.is_some() { 3 } else { 0 };
         let vertical = Layout::vertical([
+            Constraint::Length(banner_height),
             Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(3),
+            Constraint::Length(preview_height),
             Constraint::Min(1),
+            Constraint::Length(1),
         ]);
-        let [help_area, pattern_area, input_area, expressions_area] = vertical.areas(frame.area());
+        let [banner_area, help_area, pattern_area, input_area, preview_area, expressions_area, status_area] =
+            vertical.areas(frame.area());
+
+        let status = Paragraph::new(self.status_line())
+            .style(Style::default().fg(Color::Black).bg(Color::Gray));
+        frame.render_widget(status, status_area);
+
+        if !is_implemented(&self.engine_choice) {
+            let banner = Paragraph::new(Line::from(vec![
+                "The selected engine is a placeholder and results are not evaluated. ".into(),
+                "Press ".into(),
+                "g".bold(),
+                " to switch to ".into(),
+                "builtin".bold(),
+                ", ".into(),
+                "custom".bold(),
+                ", ".into(),
+                "dfa".bold(),
+                " or ".into(),
+                "custommeta".bold(),
+                ".".into(),
+            ]))
+            .style(Style::default().fg(Color::Black).bg(Color::Yellow))
+            .block(Block::bordered().title("Engine not implemented"));
+            frame.render_widget(banner, banner_area);
+        }
 
         let (msg, style) = match self.input_mode {
             InputMode::Normal => (
@@ -233,9 +1325,21 @@ impl App {
                     "t".bold(),
                     " to edit text, ".into(),
                     "e".bold(),
-                    " to edit text (legacy), or ".into(),
+                    " to edit text (legacy), ".into(),
+                    "Up/Down/PgUp/PgDn".bold(),
+                    " to scroll expressions, ".into(),
+                    "g".bold(),
+                    " to switch engine, ".into(),
+                    "d".bold(),
+                    " to delete the selected expression, ".into(),
+                    "C".bold(),
+                    " or ".into(),
+                    "Ctrl+L".bold(),
+                    " to clear all expressions, or ".into(),
                     "Ctrl+C".bold(),
-                    " at any time to exit.".into(),
+                    " at any time to exit. Press ".into(),
+                    "?".bold(),
+                    " for all keys.".into(),
                 ],
                 Style::default().add_modifier(Modifier::RAPID_BLINK),
             ),
@@ -259,19 +1363,41 @@ impl App {
                 ],
                 Style::default(),
             ),
+            InputMode::EditingReplacement => (
+                vec![
+                    "Editing Replacement: Press ".into(),
+                    "Esc".bold(),
+                    " to cancel, ".into(),
+                    "Enter".bold(),
+                    " to apply the replacement.".into(),
+                ],
+                Style::default(),
+            ),
         };
         let text = Text::from(Line::from(msg)).patch_style(style);
-        let help_message = Paragraph::new(text);
+        let help_message = Paragraph::new(text).wrap(Wrap { trim: true });
         frame.render_widget(help_message, help_area);
 
+        let pattern_block = if self.pattern.is_empty() {
+            Block::bordered().title("Pattern")
+        } else if self.pattern_compiles() {
+            Block::bordered()
+                .title("Pattern (ok)")
+                .border_style(Style::default().fg(Color::Green))
+        } else {
+            Block::bordered()
+                .title("Pattern (invalid)")
+                .border_style(Style::default().fg(Color::Red))
+        };
         let pattern_par = Paragraph::new(self.pattern.as_str())
             .style(Style::default().fg(Color::Cyan))
-            .block(Block::bordered().title("Pattern"));
+            .block(pattern_block);
         frame.render_widget(pattern_par, pattern_area);
 
         let input_title = match self.input_mode {
             InputMode::EditingPattern => "Editing Pattern",
             InputMode::EditingText => "Editing Text",
+            InputMode::EditingReplacement => "Editing Replacement",
             InputMode::Normal => "Text",
         };
         let input = Paragraph::new(self.input.as_str())
@@ -279,33 +1405,299 @@ impl App {
                 InputMode::Normal => Style::default(),
                 InputMode::EditingPattern => Style::default().fg(Color::Green),
                 InputMode::EditingText => Style::default().fg(Color::Yellow),
+                InputMode::EditingReplacement => Style::default().fg(Color::Magenta),
             })
             .block(Block::bordered().title(input_title));
         frame.render_widget(input, input_area);
 
         match self.input_mode {
             InputMode::Normal => {}
-            InputMode::EditingPattern | InputMode::EditingText => {
+            InputMode::EditingPattern | InputMode::EditingText | InputMode::EditingReplacement => {
                 frame.set_cursor_position(Position::new(
-                    input_area.x + self.character_index as u16 + 1,
+                    input_area.x + cursor_column(&self.input, self.character_index) as u16 + 1,
                     input_area.y + 1,
                 ))
             }
         }
 
+        if let Some(preview) = &self.preview {
+            let style = match preview.outcome {
+                Some(MatchOutcome::InvalidPattern(_)) => Style::default().fg(Color::Red),
+                _ => Style::default(),
+            };
+            let preview_par = Paragraph::new(preview.summary.as_str())
+                .style(style)
+                .block(Block::bordered().title("Preview"));
+            frame.render_widget(preview_par, preview_area);
+        }
+
+        let expressions_area = if self.show_ast {
+            let [list_area, ast_area] =
+                Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(expressions_area);
+            draw_ast(frame, self.ast_pattern(), ast_area);
+            list_area
+        } else {
+            expressions_area
+        };
+
+        let expressions_area = if self.show_histogram {
+            let [list_area, chart_area] =
+                Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(expressions_area);
+            draw_histogram(frame, &self.histogram_data(), chart_area);
+            list_area
+        } else {
+            expressions_area
+        };
+
+        if let Some(replaced) = &self.replace_preview {
+            draw_replace_preview(frame, replaced, expressions_area);
+            return;
+        }
+
+        if let Some(diff) = &self.diff_view {
+            draw_diff_view(frame, diff, expressions_area);
+            return;
+        }
+
         let expressions: Vec<ListItem> = self
             .expressions
             .iter()
             .enumerate()
             .map(|(i, m)| {
-                let content = Line::from(Span::raw(format!(
-                    "{i}: Pattern: {}, Text: {}, {}",
-                    m.pattern, m.text, m.matches
-                )));
+                let mut spans = vec![Span::raw(format!("{i}: Pattern: {}, Text: ", m.pattern))];
+                spans.extend(highlight_matches(&m.text, &m.ranges).spans);
+                spans.push(Span::raw(format!(", {}", m.matches)));
+                let content = Line::from(spans);
                 ListItem::new(content)
             })
             .collect();
-        let expressions = List::new(expressions).block(Block::bordered().title("Expressions"));
-        frame.render_widget(expressions, expressions_area);
+        let expressions = List::new(expressions)
+            .block(Block::bordered().title(format!(
+                "Expressions (engine: {})",
+                self.engine_choice.name()
+            )))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ");
+        frame.render_stateful_widget(expressions, expressions_area, &mut self.list_state);
+    }
+}
+
+/// Renders the file browser full screen, with any error below the listing.
+fn draw_file_picker(frame: &mut Frame, picker: &mut FilePicker) {
+    let error_height = if picker.error.is_some() { 3 } else { 0 };
+    let [list_area, error_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(error_height)])
+            .areas(frame.area());
+
+    let items: Vec<ListItem> = picker
+        .entries
+        .iter()
+        .map(|entry| {
+            if entry.is_dir {
+                ListItem::new(format!("{}/", entry.name)).style(Style::default().fg(Color::Blue))
+            } else {
+                ListItem::new(entry.name.as_str())
+            }
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::bordered().title(format!(
+            "Open file: {} (Enter to open, Esc to cancel)",
+            picker.dir.display()
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, list_area, &mut picker.list_state);
+
+    if let Some(error) = &picker.error {
+        let error = Paragraph::new(error.as_str())
+            .style(Style::default().fg(Color::Red))
+            .block(Block::bordered().title("Error"));
+        frame.render_widget(error, error_area);
     }
 }
+
+/// What the previews say instead of matching an input over `max_bytes`.
+fn too_large_to_preview(max_bytes: usize, enter_does: &str) -> String {
+    format!(
+        "Input is over {} KiB, too large to preview; press Enter to {}",
+        max_bytes / 1024,
+        enter_does
+    )
+}
+
+/// Renders the text with the replacement applied and each replacement
+/// highlighted, or why the pattern can't be used.
+fn draw_replace_preview(frame: &mut Frame, replaced: &Result<Replaced, String>, area: Rect) {
+    let block = Block::bordered().title("Replace preview (Enter to apply, Esc to cancel)");
+    let pane = match replaced {
+        Ok(replaced) => Paragraph::new(highlight_matches(&replaced.text, &replaced.replaced)),
+        Err(e) => {
+            Paragraph::new(format!("Cannot replace: {}", e)).style(Style::default().fg(Color::Red))
+        }
+    };
+    frame.render_widget(pane.wrap(Wrap { trim: false }).block(block), area);
+}
+
+/// Renders the custom engine's parse tree of `pattern`, or why it doesn't parse.
+fn draw_ast(frame: &mut Frame, pattern: &str, area: Rect) {
+    let block = Block::bordered().title("Parse tree (custom engine)");
+    let pane = if pattern.is_empty() {
+        Paragraph::new("Enter a pattern to see its parse tree")
+    } else {
+        match ast_tree(pattern) {
+            Ok(lines) => Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>()),
+            Err(e) => Paragraph::new(format!("Invalid pattern: {}", e))
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: false }),
+        }
+    };
+    frame.render_widget(pane.block(block), area);
+}
+
+/// Renders a bar per expression, labelled by its index, as tall as its match count.
+fn draw_histogram(frame: &mut Frame, data: &[(String, u64)], area: Rect) {
+    let bars: Vec<(&str, u64)> = data
+        .iter()
+        .map(|(label, count)| (label.as_str(), *count))
+        .collect();
+    let chart = BarChart::default()
+        .block(Block::bordered().title("Matches per expression"))
+        .data(bars.as_slice())
+        .bar_width(3)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+    frame.render_widget(chart, area);
+}
+
+/// Renders the custom and builtin matches in two columns. Matches only one
+/// engine found are marked and colored red.
+fn draw_diff_view(frame: &mut Frame, diff: &DiffView, area: Rect) {
+    let (only_custom, only_builtin) = match (&diff.custom, &diff.builtin) {
+        (Ok(custom), Ok(builtin)) => {
+            let custom: Vec<&str> = custom.iter().map(String::as_str).collect();
+            let builtin: Vec<&str> = builtin.iter().map(String::as_str).collect();
+            let (c, b) = match_set_split(&custom, &builtin);
+            (
+                c.into_iter().map(str::to_string).collect(),
+                b.into_iter().map(str::to_string).collect(),
+            )
+        }
+        _ => (Vec::new(), Vec::new()),
+    };
+    let column = |name: &str, matches: &Result<Vec<String>, String>, only: &[String]| {
+        let items: Vec<ListItem> = match matches {
+            Ok(matches) => matches
+                .iter()
+                .map(|m| {
+                    if only.contains(m) {
+                        ListItem::new(format!("+ {:?}", m)).style(Style::default().fg(Color::Red))
+                    } else {
+                        ListItem::new(format!("  {:?}", m))
+                    }
+                })
+                .collect(),
+            Err(e) => vec![ListItem::new(format!("Invalid pattern: {}", e))
+                .style(Style::default().fg(Color::Red))],
+        };
+        List::new(items).block(Block::bordered().title(format!(
+            "{} ({} only here)",
+            name,
+            only.len()
+        )))
+    };
+    let [left, right] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(area);
+    frame.render_widget(
+        column(
+            &format!("custom: {} (v to close)", diff.pattern),
+            &diff.custom,
+            &only_custom,
+        ),
+        left,
+    );
+    frame.render_widget(column("builtin", &diff.builtin, &only_builtin), right);
+}
+
+/// Terminal cells taken by the first `character_index` chars of `input`, where
+/// the cursor sits: wide (CJK) characters take two and combining marks none.
+pub fn cursor_column(input: &str, character_index: usize) -> usize {
+    input
+        .chars()
+        .take(character_index)
+        .map(|c| c.width().unwrap_or(0))
+        .sum()
+}
+
+/// Splits `text` into spans with the matched byte `ranges` highlighted.
+/// Overlapping ranges are merged first (see `merge_ranges`).
+pub fn highlight_matches<'a>(text: &'a str, ranges: &[Range<usize>]) -> Line<'a> {
+    let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for range in merge_ranges(ranges) {
+        if range.start > pos {
+            spans.push(Span::raw(&text[pos..range.start]));
+        }
+        spans.push(Span::styled(&text[range.clone()], highlight));
+        pos = range.end;
+    }
+    if pos < text.len() {
+        spans.push(Span::raw(&text[pos..]));
+    }
+    Line::from(spans)
+}
+
+/// Reads saved expressions from a JSON history file. A missing or malformed
+/// file is reported on stderr and treated as an empty history. Entries whose
+/// ranges do not fall on char boundaries within their text, as a hand-edited
+/// or stale file may have, are dropped so drawing them cannot panic.
+pub fn load_history(path: &str) -> Vec<ExpressionEntry> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            eprintln!(
+                "History file {} not found, starting with an empty history.",
+                path
+            );
+            return Vec::new();
+        }
+        Err(e) => {
+            eprintln!(
+                "Could not read history file {}: {}. Starting empty.",
+                path, e
+            );
+            return Vec::new();
+        }
+    };
+    let mut entries: Vec<ExpressionEntry> = match serde_json::from_str(&contents) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Malformed history file {}: {}. Starting empty.", path, e);
+            return Vec::new();
+        }
+    };
+    let loaded = entries.len();
+    entries.retain(|entry| {
+        entry
+            .ranges
+            .iter()
+            .all(|range| entry.text.get(range.clone()).is_some())
+    });
+    if entries.len() < loaded {
+        eprintln!(
+            "Dropped {} history entries with ranges outside their text from {}.",
+            loaded - entries.len(),
+            path
+        );
+    }
+    entries
+}
+
+pub fn save_history(path: &str, entries: &[ExpressionEntry]) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(entries)?)?;
+    Ok(())
+}
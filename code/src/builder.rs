@@ -0,0 +1,257 @@
+use crate::custom_regex::{CustomRegex, DEFAULT_MAX_DFA_STATES};
+use crate::engines::{
+    custommeta_choice, is_implemented, probe_limit, EngineChoice, MatchOutcome, SearchLimits,
+};
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Configures a [`Regexer`]: which engine to run and how to match. Compiling
+/// happens once in [`RegexerBuilder::build`], so the result can be reused
+/// across many texts.
+pub struct RegexerBuilder {
+    pattern: String,
+    engine: EngineChoice,
+    case_insensitive: bool,
+    max_dfa_states: usize,
+}
+
+impl RegexerBuilder {
+    /// Starts a builder for `pattern` using the builtin engine.
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            engine: EngineChoice::Builtin,
+            case_insensitive: false,
+            max_dfa_states: DEFAULT_MAX_DFA_STATES,
+        }
+    }
+
+    pub fn engine(mut self, engine: EngineChoice) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Ignore case while matching. The custom engine only folds ASCII letters.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Reject patterns whose custom engine DFA needs more than `limit` states.
+    pub fn max_dfa_states(mut self, limit: usize) -> Self {
+        self.max_dfa_states = limit;
+        self
+    }
+
+    /// Compiles the pattern. Fails with the compile error, or when the engine
+    /// is still a placeholder.
+    pub fn build(self) -> Result<Regexer, String> {
+        if !is_implemented(&self.engine) {
+            return Err(format!("Engine {} is not implemented", self.engine.name()));
+        }
+        let matcher = match self.engine {
+            EngineChoice::Custom => Matcher::Custom(self.custom()?),
+            // Like apply_pattern: custom first, builtin when it is rejected.
+            EngineChoice::Custommeta => match (self.custom(), self.builtin()) {
+                (Err(_), Err(e)) => return Err(e),
+                (custom, builtin) => Matcher::Custommeta {
+                    custom: custom.ok(),
+                    builtin: builtin.ok(),
+                },
+            },
+            _ => Matcher::Builtin(self.builtin()?),
+        };
+        Ok(Regexer {
+            matcher,
+            case_insensitive: self.case_insensitive,
+        })
+    }
+
+    fn builtin(&self) -> Result<Regex, String> {
+        RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+
+    fn custom(&self) -> Result<CustomRegex, String> {
+        // The custom parser only takes lowercase letters, so fold the pattern
+        // here and the text in `Regexer::custom_ranges`. Escapes keep their
+        // case so `\D`, `\W` and `\S` stay negated.
+        let pattern = if self.case_insensitive {
+            let mut escaped = false;
+            self.pattern
+                .chars()
+                .map(|c| {
+                    let folded = if escaped { c } else { c.to_ascii_lowercase() };
+                    escaped = !escaped && c == '\\';
+                    folded
+                })
+                .collect()
+        } else {
+            self.pattern.clone()
+        };
+        CustomRegex::with_max_states(&pattern, self.max_dfa_states).map_err(|e| e.to_string())
+    }
+}
+
+enum Matcher {
+    Builtin(Regex),
+    Custom(CustomRegex),
+    /// At least one of the two compiled.
+    Custommeta {
+        custom: Option<CustomRegex>,
+        builtin: Option<Regex>,
+    },
+}
+
+/// A compiled pattern, built with [`RegexerBuilder`].
+pub struct Regexer {
+    matcher: Matcher,
+    case_insensitive: bool,
+}
+
+impl Regexer {
+    /// Byte ranges of every match, in the order the engine reports them.
+    pub fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.find_ranges_cancellable(text, &AtomicBool::new(false))
+            .expect("nothing else can cancel this search")
+    }
+
+    /// Like `find_ranges`, but gives up with `None` once `cancel` is set, so
+    /// another thread can stop a long search.
+    pub fn find_ranges_cancellable(
+        &self,
+        text: &str,
+        cancel: &AtomicBool,
+    ) -> Option<Vec<Range<usize>>> {
+        self.search(text, None, cancel)
+    }
+
+    /// `find_ranges_cancellable` stopping once `limit` matches are found.
+    fn search(
+        &self,
+        text: &str,
+        limit: Option<usize>,
+        cancel: &AtomicBool,
+    ) -> Option<Vec<Range<usize>>> {
+        Some(match &self.matcher {
+            Matcher::Builtin(regex) => builtin_ranges(regex, text, limit, cancel)?,
+            Matcher::Custom(custom) => self.custom_ranges(custom, text, limit, cancel)?,
+            // custommeta_choice, without the stderr notes.
+            Matcher::Custommeta { custom, builtin } => match (custom, builtin) {
+                (Some(custom), Some(builtin)) => {
+                    let custom = self.custom_ranges(custom, text, limit, cancel)?;
+                    let builtin = builtin_ranges(builtin, text, limit, cancel)?;
+                    custommeta_choice(text, custom, builtin).0
+                }
+                (Some(custom), None) => self.custom_ranges(custom, text, limit, cancel)?,
+                (None, Some(builtin)) => builtin_ranges(builtin, text, limit, cancel)?,
+                (None, None) => unreachable!("build rejects patterns neither engine compiles"),
+            },
+        })
+    }
+
+    /// `find_ranges` under `limits`, the way `apply_pattern_limited` searches:
+    /// at most one match past `max_matches`, and on the custom engine at most
+    /// `max_steps` DFA steps.
+    pub(crate) fn find_ranges_limited(
+        &self,
+        text: &str,
+        limits: &SearchLimits,
+    ) -> Result<Vec<Range<usize>>, String> {
+        let limit = probe_limit(limits.max_matches);
+        if let (Matcher::Custom(custom), Some(max_steps)) = (&self.matcher, limits.max_steps) {
+            let folded;
+            let text = if self.case_insensitive {
+                folded = text.to_ascii_lowercase();
+                &folded
+            } else {
+                text
+            };
+            return custom
+                .find_ranges_budgeted(text, limit, max_steps)
+                .map_err(|e| e.to_string());
+        }
+        Ok(self
+            .search(text, limit, &AtomicBool::new(false))
+            .expect("nothing else can cancel this search"))
+    }
+
+    /// Whether there is any match in `text`. Stops at the first one instead of
+    /// collecting them all.
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.matcher {
+            Matcher::Builtin(regex) => regex.is_match(text),
+            Matcher::Custom(custom) => self.custom_is_match(custom, text),
+            // Like `engines::is_match`: custom whenever it compiled.
+            Matcher::Custommeta {
+                custom: Some(custom),
+                ..
+            } => self.custom_is_match(custom, text),
+            Matcher::Custommeta {
+                builtin: Some(builtin),
+                ..
+            } => builtin.is_match(text),
+            Matcher::Custommeta { .. } => {
+                unreachable!("build rejects patterns neither engine compiles")
+            }
+        }
+    }
+
+    /// The matched substrings, in the order the engine reports them.
+    pub fn find_iter<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        self.find_ranges(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    /// Like the free [`crate::match_all`], reusing the compiled pattern.
+    pub fn match_all(&self, text: &str) -> MatchOutcome {
+        let ranges = self.find_ranges(text);
+        if ranges.is_empty() {
+            MatchOutcome::NoMatches
+        } else {
+            MatchOutcome::Matches(ranges)
+        }
+    }
+
+    fn custom_is_match(&self, custom: &CustomRegex, text: &str) -> bool {
+        if self.case_insensitive {
+            custom.is_match(&text.to_ascii_lowercase())
+        } else {
+            custom.is_match(text)
+        }
+    }
+
+    fn custom_ranges(
+        &self,
+        custom: &CustomRegex,
+        text: &str,
+        limit: Option<usize>,
+        cancel: &AtomicBool,
+    ) -> Option<Vec<Range<usize>>> {
+        if self.case_insensitive {
+            // ASCII folding keeps every byte offset, so the ranges still fit `text`.
+            custom.find_ranges_cancellable_up_to(&text.to_ascii_lowercase(), limit, cancel)
+        } else {
+            custom.find_ranges_cancellable_up_to(text, limit, cancel)
+        }
+    }
+}
+
+/// Checks `cancel` between matches; the regex crate can't be stopped mid-search.
+fn builtin_ranges(
+    regex: &Regex,
+    text: &str,
+    limit: Option<usize>,
+    cancel: &AtomicBool,
+) -> Option<Vec<Range<usize>>> {
+    regex
+        .find_iter(text)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|mat| (!cancel.load(Ordering::Relaxed)).then(|| mat.range()))
+        .collect()
+}
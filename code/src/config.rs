@@ -0,0 +1,66 @@
+//! Defaults read from a `.regexerrc` file, which command-line flags override.
+
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The config file's name, looked up in the current directory, then the home
+/// directory.
+pub const CONFIG_FILE: &str = ".regexerrc";
+
+/// The settings a `.regexerrc` may hold. Anything left out keeps the CLI default.
+///
+/// ```toml
+/// engine = "custom"
+/// color = "never"
+/// ignore_case = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Default for `--engine`.
+    pub engine: Option<String>,
+    /// Default for `--color`: auto, always or never.
+    pub color: Option<String>,
+    /// Default for `--ignore-case`.
+    pub ignore_case: Option<bool>,
+}
+
+impl Config {
+    /// Parses the TOML contents of a config file. The engine name is checked
+    /// later, the same way `--engine` is.
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(contents).map_err(|e| e.message().to_string())?;
+        match config.color.as_deref() {
+            None | Some("auto" | "always" | "never") => Ok(config),
+            Some(other) => Err(format!(
+                "color must be auto, always or never, not '{}'",
+                other
+            )),
+        }
+    }
+}
+
+/// The first `.regexerrc` found in `dir`, then in `home`.
+pub fn find_config(dir: &Path, home: Option<&Path>) -> Option<PathBuf> {
+    std::iter::once(dir)
+        .chain(home)
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Reads the `.regexerrc` from the current or home directory. No file at all
+/// gives the default config; a file that can't be read or parsed is an error
+/// naming it.
+pub fn load() -> Result<Config, String> {
+    let dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let Some(path) = find_config(&dir, home.as_deref()) else {
+        return Ok(Config::default());
+    };
+    let contents =
+        fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    Config::parse(&contents).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
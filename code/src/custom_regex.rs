@@ -1,16 +1,210 @@
 #[derive(Debug, Clone, PartialEq, Eq)]
 // disclosure; I used these sites as reference while making this: https://jneem.github.io/regex-dfa/src/regex_dfa/src/regex.rs.html?search=
-enum RegexAST {
+pub(crate) enum RegexAST {
     Empty,                                // ε
     Literal(char),                        // single character
     Concat(Box<RegexAST>, Box<RegexAST>), // AB
     Union(Box<RegexAST>, Box<RegexAST>),  // A|B
     Kleene(Box<RegexAST>),                // A*
+    Optional(Box<RegexAST>),              // A?
+    WordBoundary,                         // \b
+    // \d \w \s, or \D \W \S when the letter is uppercase (see `class_contains`)
+    Class(char),
+    // A*? A+? A?? -- same language as the greedy form, but it switches the
+    // search to leftmost-shortest (see `CustomRegex::find_ranges_up_to`).
+    Lazy(Box<RegexAST>),
+    // (A) -- kept for display; matches exactly like A
+    Group(Box<RegexAST>),
+}
+
+impl RegexAST {
+    /// Whether any quantifier in the tree is lazy.
+    fn has_lazy(&self) -> bool {
+        match self {
+            RegexAST::Lazy(_) => true,
+            RegexAST::Concat(a, b) | RegexAST::Union(a, b) => a.has_lazy() || b.has_lazy(),
+            RegexAST::Kleene(a) | RegexAST::Optional(a) | RegexAST::Group(a) => a.has_lazy(),
+            RegexAST::Empty
+            | RegexAST::Literal(_)
+            | RegexAST::WordBoundary
+            | RegexAST::Class(_) => false,
+        }
+    }
+}
+
+impl RegexAST {
+    /// The tree one node per line, each child indented two spaces under its
+    /// parent, for the TUI's parse tree pane.
+    pub(crate) fn tree_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.push_tree_lines(0, &mut lines);
+        lines
+    }
+
+    fn push_tree_lines(&self, depth: usize, lines: &mut Vec<String>) {
+        let indent = "  ".repeat(depth);
+        let (label, children): (String, Vec<&RegexAST>) = match self {
+            RegexAST::Empty => ("Empty".to_string(), vec![]),
+            RegexAST::Literal(c) => (format!("Literal({:?})", c), vec![]),
+            RegexAST::WordBoundary => ("WordBoundary".to_string(), vec![]),
+            RegexAST::Class(class) => (format!("Class(\\{})", class), vec![]),
+            RegexAST::Concat(a, b) => ("Concat".to_string(), vec![a, b]),
+            RegexAST::Union(a, b) => ("Union".to_string(), vec![a, b]),
+            RegexAST::Kleene(a) => ("Kleene".to_string(), vec![a]),
+            RegexAST::Optional(a) => ("Optional".to_string(), vec![a]),
+            RegexAST::Lazy(a) => ("Lazy".to_string(), vec![a]),
+            RegexAST::Group(a) => ("Group".to_string(), vec![a]),
+        };
+        lines.push(format!("{}{}", indent, label));
+        for child in children {
+            child.push_tree_lines(depth + 1, lines);
+        }
+    }
+}
+
+/// `c` as pattern source: `\n`, `\t` and `\r` for the control characters
+/// those escapes stand for, otherwise the character itself.
+fn escaped(c: char) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        _ => c.to_string(),
+    }
+}
+
+/// `c` unioned with its lowercase and uppercase forms, each only when it is a
+/// single character other than `c`, so `(?i)É` matches `é` as well as `É`.
+fn fold_case(c: char) -> RegexAST {
+    let lower = other_case(c, c.to_lowercase());
+    let upper = other_case(c, c.to_uppercase());
+    [lower, upper.filter(|&u| Some(u) != lower)]
+        .into_iter()
+        .flatten()
+        .fold(RegexAST::Literal(c), |folded, other| {
+            RegexAST::Union(Box::new(folded), Box::new(RegexAST::Literal(other)))
+        })
+}
+
+/// The case mapping `cases` of `c`, when it is one character other than `c`.
+fn other_case(c: char, mut cases: impl Iterator<Item = char>) -> Option<char> {
+    match (cases.next(), cases.next()) {
+        (Some(folded), None) if folded != c => Some(folded),
+        _ => None,
+    }
+}
+
+// Binding strength of each form in pattern source, loosest first.
+const UNION_LEVEL: u8 = 0;
+const CONCAT_LEVEL: u8 = 1;
+const POSTFIX_LEVEL: u8 = 2;
+const ATOM_LEVEL: u8 = 3;
+
+impl RegexAST {
+    /// Writes the tree back as pattern source that parses to the same tree.
+    /// Parentheses are added only where precedence or the parser's left-nesting
+    /// needs them, as non-capturing `(?:...)` so no `Group` appears.
+    pub(crate) fn to_pattern(&self) -> String {
+        match self {
+            RegexAST::Empty => String::new(),
+            ast => ast.source(),
+        }
+    }
+
+    fn level(&self) -> u8 {
+        match self {
+            RegexAST::Union(..) if self.folded_literal().is_some() => ATOM_LEVEL,
+            RegexAST::Union(..) => UNION_LEVEL,
+            RegexAST::Concat(..) if self.repeated().is_some() => POSTFIX_LEVEL,
+            RegexAST::Concat(..) => CONCAT_LEVEL,
+            RegexAST::Kleene(_) | RegexAST::Optional(_) | RegexAST::Lazy(_) => POSTFIX_LEVEL,
+            RegexAST::Empty
+            | RegexAST::Literal(_)
+            | RegexAST::WordBoundary
+            | RegexAST::Class(_)
+            | RegexAST::Group(_) => ATOM_LEVEL,
+        }
+    }
+
+    /// `A` when this is `AA*`, the tree `A+` parses to.
+    fn repeated(&self) -> Option<&RegexAST> {
+        match self {
+            RegexAST::Concat(a, b) if **b == RegexAST::Kleene(a.clone()) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// `c` when this is `fold_case(c)`, the tree a literal parses to under `(?i)`.
+    fn folded_literal(&self) -> Option<char> {
+        let mut first = self;
+        while let RegexAST::Union(a, _) = first {
+            first = &**a;
+        }
+        match (self, first) {
+            (RegexAST::Union(..), RegexAST::Literal(c)) if *self == fold_case(*c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// The source of this node, wrapped when it binds looser than `min_level`.
+    fn operand(&self, min_level: u8) -> String {
+        if self.level() < min_level {
+            format!("(?:{})", self.source())
+        } else {
+            self.source()
+        }
+    }
+
+    fn source(&self) -> String {
+        if let Some(c) = self.folded_literal() {
+            return format!("(?i:{})", c);
+        }
+        if let Some(a) = self.repeated() {
+            return format!("{}+", a.operand(POSTFIX_LEVEL));
+        }
+        match self {
+            // An empty flag group is the one way to write ε inside a pattern.
+            RegexAST::Empty => "(?)".to_string(),
+            RegexAST::Literal(c) if c.is_ascii_punctuation() => format!("\\{}", c),
+            RegexAST::Literal(c) => escaped(*c),
+            RegexAST::WordBoundary => "\\b".to_string(),
+            RegexAST::Class(class) => format!("\\{}", class),
+            RegexAST::Group(a) => format!("({})", a.source()),
+            RegexAST::Optional(a) => format!("{}?", a.operand(ATOM_LEVEL)),
+            RegexAST::Union(a, b) => {
+                format!("{}|{}", a.operand(UNION_LEVEL), b.operand(CONCAT_LEVEL))
+            }
+            RegexAST::Concat(a, b) => {
+                format!("{}{}", a.operand(CONCAT_LEVEL), b.operand(POSTFIX_LEVEL))
+            }
+            RegexAST::Kleene(a) => format!("{}*", a.operand(POSTFIX_LEVEL)),
+            RegexAST::Lazy(a) => format!("{}?", a.operand(POSTFIX_LEVEL)),
+        }
+    }
+}
+
+impl std::fmt::Display for RegexAST {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegexAST::Empty => write!(f, "Empty"),
+            RegexAST::Literal(c) => write!(f, "Literal({})", escaped(*c)),
+            RegexAST::Concat(a, b) => write!(f, "Concat({}, {})", a, b),
+            RegexAST::Union(a, b) => write!(f, "Union({}, {})", a, b),
+            RegexAST::Kleene(a) => write!(f, "Kleene({})", a),
+            RegexAST::Optional(a) => write!(f, "Optional({})", a),
+            RegexAST::WordBoundary => write!(f, "WordBoundary"),
+            RegexAST::Class(class) => write!(f, "Class(\\{})", class),
+            RegexAST::Lazy(a) => write!(f, "Lazy({})", a),
+            RegexAST::Group(a) => write!(f, "Group({})", a),
+        }
+    }
 }
 
 struct Parser {
     chars: Vec<char>,
     pos: usize,
+    // Set by the inline flag `i`; literals then match either case.
+    case_insensitive: bool,
 }
 
 impl Parser {
@@ -18,6 +212,7 @@ impl Parser {
         Self {
             chars: s.chars().collect(),
             pos: 0,
+            case_insensitive: false,
         }
     }
 
@@ -33,57 +228,131 @@ impl Parser {
         c
     }
 
-    fn parse(&mut self) -> RegexAST {
-        self.parse_union()
+    fn parse(&mut self) -> Result<RegexAST, RegexError> {
+        let ast = self.parse_union()?;
+        // parse_union stops at a ')' it has no group for.
+        if let Some(c) = self.peek() {
+            return Err(self.error(&format!("Unmatched '{}'", c)));
+        }
+        Ok(ast)
+    }
+
+    fn error(&self, message: &str) -> RegexError {
+        RegexError(format!("{} at position {}", message, self.pos))
     }
 
-    fn parse_union(&mut self) -> RegexAST {
-        let mut node = self.parse_concat();
+    fn parse_union(&mut self) -> Result<RegexAST, RegexError> {
+        let mut node = self.parse_concat()?;
         while let Some('|') = self.peek() {
             self.next();
-            let right = self.parse_concat();
+            let right = self.parse_concat()?;
             node = RegexAST::Union(Box::new(node), Box::new(right));
         }
-        node
+        Ok(node)
     }
 
-    fn parse_concat(&mut self) -> RegexAST {
-        let mut node = self.parse_kleene();
+    fn parse_concat(&mut self) -> Result<RegexAST, RegexError> {
+        let mut node = self.parse_kleene()?;
         while let Some(c) = self.peek() {
             if c == ')' || c == '|' {
                 break;
             }
-            let right = self.parse_kleene();
+            let right = self.parse_kleene()?;
             node = RegexAST::Concat(Box::new(node), Box::new(right));
         }
-        node
+        Ok(node)
     }
 
-    fn parse_kleene(&mut self) -> RegexAST {
-        let mut node = self.parse_base();
-        while let Some('*') = self.peek() {
+    /// Parses a base followed by any number of `*`, `+` and `?`, each optionally
+    /// made lazy by a trailing `?`. `A+` is built as `AA*`.
+    fn parse_kleene(&mut self) -> Result<RegexAST, RegexError> {
+        let mut node = self.parse_base()?;
+        while let Some(op @ ('*' | '+' | '?')) = self.peek() {
             self.next();
-            node = RegexAST::Kleene(Box::new(node));
+            node = match op {
+                '*' => RegexAST::Kleene(Box::new(node)),
+                '+' => RegexAST::Concat(
+                    Box::new(node.clone()),
+                    Box::new(RegexAST::Kleene(Box::new(node))),
+                ),
+                _ => RegexAST::Optional(Box::new(node)),
+            };
+            if self.peek() == Some('?') {
+                self.next();
+                node = RegexAST::Lazy(Box::new(node));
+            }
         }
-        node
+        Ok(node)
     }
 
-    fn parse_base(&mut self) -> RegexAST {
+    fn parse_base(&mut self) -> Result<RegexAST, RegexError> {
         match self.peek() {
             Some('(') => {
                 self.next();
-                let node = self.parse_union();
+                // Flags set inside a group, e.g. `(a(?i)b)`, end with it.
+                let outer_flags = self.case_insensitive;
+                let node = if self.peek() == Some('?') {
+                    self.next();
+                    match self.parse_flags()? {
+                        // `(?flags)` applies to the rest of the enclosing group.
+                        ')' => return Ok(RegexAST::Empty),
+                        _ => self.parse_union()?,
+                    }
+                } else {
+                    RegexAST::Group(Box::new(self.parse_union()?))
+                };
                 if self.next() != Some(')') {
-                    panic!("Unmatched parenthesis");
+                    return Err(self.error("Unmatched parenthesis"));
                 }
-                node
+                self.case_insensitive = outer_flags;
+                Ok(node)
             }
-            Some(c) if c.is_ascii_lowercase() => {
+            Some('\\') => {
                 self.next();
-                RegexAST::Literal(c)
+                match self.next() {
+                    Some('b') => Ok(RegexAST::WordBoundary),
+                    Some('n') => Ok(RegexAST::Literal('\n')),
+                    Some('t') => Ok(RegexAST::Literal('\t')),
+                    Some('r') => Ok(RegexAST::Literal('\r')),
+                    Some(class @ ('d' | 'w' | 's' | 'D' | 'W' | 'S')) => Ok(RegexAST::Class(class)),
+                    // `\.`, `\*`, `\(` and the like match the metacharacter itself.
+                    Some(c) if c.is_ascii_punctuation() => Ok(self.literal(c)),
+                    Some(c) => Err(self.error(&format!("Unsupported escape '\\{}'", c))),
+                    None => Err(self.error("Trailing backslash")),
+                }
             }
-            None => RegexAST::Empty,
-            _ => panic!("Unexpected character"),
+            // Non-ASCII characters are never operators, so they are always literals.
+            Some(c) if c.is_ascii_lowercase() || !c.is_ascii() => {
+                self.next();
+                Ok(self.literal(c))
+            }
+            None => Ok(RegexAST::Empty),
+            Some(c) => Err(self.error(&format!("Unexpected character '{}'", c))),
+        }
+    }
+
+    /// Parses the flags of `(?flags)` or `(?flags:...)` after the `?`, applies
+    /// them, and returns the `)` or `:` that ended them. `i` makes literals
+    /// case-insensitive. `m` and `s` are accepted for compatibility with the
+    /// builtin engine but change nothing, since this engine has no `^`, `$` or `.`.
+    fn parse_flags(&mut self) -> Result<char, RegexError> {
+        loop {
+            match self.next() {
+                Some('i') => self.case_insensitive = true,
+                Some('m' | 's') => {}
+                Some(end @ (')' | ':')) => return Ok(end),
+                Some(c) => return Err(self.error(&format!("Unknown flag '{}'", c))),
+                None => return Err(self.error("Unterminated flag group")),
+            }
+        }
+    }
+
+    /// A literal `c`, or any case of it under the `i` flag.
+    fn literal(&self, c: char) -> RegexAST {
+        if self.case_insensitive {
+            fold_case(c)
+        } else {
+            RegexAST::Literal(c)
         }
     }
 }
@@ -97,8 +366,13 @@ struct State(usize);
 enum NFASymbol {
     Char(char),
     Epsilon,
+    // Zero-width; only followed where a word character meets a non-word character.
+    WordBoundary,
+    // Any character in a shorthand class, named by its escape letter.
+    Class(char),
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone)]
 struct NFA {
     start: State,
@@ -117,6 +391,22 @@ impl NFA {
         }
     }
 
+    fn new_epsilon() -> Self {
+        let mut nfa = NFA::new_empty();
+        // start --ε--> accept, so ε matches the empty string
+        nfa.transitions
+            .push((nfa.start, NFASymbol::Epsilon, nfa.accept));
+        nfa
+    }
+
+    fn new_word_boundary() -> Self {
+        let mut nfa = NFA::new_empty();
+        // start --\b--> accept
+        nfa.transitions
+            .push((nfa.start, NFASymbol::WordBoundary, nfa.accept));
+        nfa
+    }
+
     fn new_literal(c: char) -> Self {
         let mut nfa = NFA::new_empty();
         // start --c--> accept
@@ -125,16 +415,25 @@ impl NFA {
         nfa
     }
 
+    fn new_class(class: char) -> Self {
+        let mut nfa = NFA::new_empty();
+        // start --\d--> accept
+        nfa.transitions
+            .push((nfa.start, NFASymbol::Class(class), nfa.accept));
+        nfa
+    }
+
     fn new_concat(a: NFA, b: NFA) -> Self {
-        // Merge by connecting a.accept -> b.start (ε-transition)
+        // Merge by connecting a.accept -> b.start (ε-transition). b's states
+        // come right after a's.
         let mut nfa = NFA {
             start: a.start,
             accept: b.accept,
             transitions: a.transitions,
-            state_count: a.state_count.max(b.state_count),
+            state_count: a.state_count,
         };
 
-        let offset = nfa.state_count;
+        let offset = a.state_count;
         let remapped = b
             .transitions
             .into_iter()
@@ -186,7 +485,10 @@ impl NFA {
         nfa
     }
 
-    fn new_kleene(a: NFA) -> Self {
+    /// `a*`. Transitions are added in order of preference, which only
+    /// `PikeVM` looks at: a greedy star tries another round of `a` before
+    /// leaving, a lazy one leaves first.
+    fn new_kleene(a: NFA, greedy: bool) -> Self {
         let mut nfa = NFA {
             start: State(0),
             accept: State(1),
@@ -201,61 +503,158 @@ impl NFA {
                 .push((State(s.0 + offset), sym, State(t.0 + offset)));
         }
 
-        // ε from new start to a.start+offset and to new accept
-        nfa.transitions
-            .push((nfa.start, NFASymbol::Epsilon, State(a.start.0 + offset)));
-        nfa.transitions
-            .push((nfa.start, NFASymbol::Epsilon, nfa.accept));
-        // ε from a.accept+offset to new accept and back to a.start+offset
-        nfa.transitions
-            .push((State(a.accept.0 + offset), NFASymbol::Epsilon, nfa.accept));
-        nfa.transitions.push((
-            State(a.accept.0 + offset),
-            NFASymbol::Epsilon,
-            State(a.start.0 + offset),
-        ));
+        // ε from new start to a.start+offset and to new accept, and from
+        // a.accept+offset back to a.start+offset and to new accept
+        let (enter, exit) = (State(a.start.0 + offset), State(a.accept.0 + offset));
+        for from in [nfa.start, exit] {
+            let (first, second) = if greedy {
+                (enter, nfa.accept)
+            } else {
+                (nfa.accept, enter)
+            };
+            nfa.transitions.push((from, NFASymbol::Epsilon, first));
+            nfa.transitions.push((from, NFASymbol::Epsilon, second));
+        }
+
+        nfa
+    }
 
+    /// `a?` on a's own states: one ε from its start straight to its accept,
+    /// where `a|ε` would add four states around it. Like `new_kleene`, a greedy
+    /// `?` lists the ε last so `PikeVM` tries `a` first.
+    fn new_optional(a: NFA, greedy: bool) -> Self {
+        let mut nfa = a;
+        let skip = (nfa.start, NFASymbol::Epsilon, nfa.accept);
+        if greedy {
+            nfa.transitions.push(skip);
+        } else {
+            nfa.transitions.insert(0, skip);
+        }
         nfa
     }
 
     fn from_ast(ast: &RegexAST) -> Self {
         match ast {
-            RegexAST::Empty => NFA::new_empty(),
+            RegexAST::Empty => NFA::new_epsilon(),
             RegexAST::Literal(c) => NFA::new_literal(*c),
             RegexAST::Concat(a, b) => NFA::new_concat(NFA::from_ast(a), NFA::from_ast(b)),
             RegexAST::Union(a, b) => NFA::new_union(NFA::from_ast(a), NFA::from_ast(b)),
-            RegexAST::Kleene(a) => NFA::new_kleene(NFA::from_ast(a)),
+            RegexAST::Kleene(a) => NFA::new_kleene(NFA::from_ast(a), true),
+            RegexAST::Optional(a) => NFA::new_optional(NFA::from_ast(a), true),
+            RegexAST::WordBoundary => NFA::new_word_boundary(),
+            RegexAST::Class(class) => NFA::new_class(*class),
+            RegexAST::Lazy(a) => NFA::from_lazy_ast(a),
+            RegexAST::Group(a) => NFA::from_ast(a),
+        }
+    }
+
+    /// `from_ast` for the operand of a lazy quantifier: the same states and
+    /// transitions, but the quantifier prefers matching less.
+    fn from_lazy_ast(ast: &RegexAST) -> Self {
+        match ast {
+            RegexAST::Kleene(a) => NFA::new_kleene(NFA::from_ast(a), false),
+            // `a+?` is `aa*?`.
+            RegexAST::Concat(a, b) => NFA::new_concat(NFA::from_ast(a), NFA::from_lazy_ast(b)),
+            RegexAST::Optional(a) => NFA::new_optional(NFA::from_ast(a), false),
+            other => NFA::from_ast(other),
+        }
+    }
+
+    /// The NFA for the reversed language: every transition flipped and start
+    /// and accept swapped. `\b` looks at both sides of a position, so it
+    /// reverses as is.
+    fn reversed(&self) -> NFA {
+        NFA {
+            start: self.accept,
+            accept: self.start,
+            transitions: self
+                .transitions
+                .iter()
+                .map(|(s, sym, t)| (*t, sym.clone(), *s))
+                .collect(),
+            state_count: self.state_count,
+        }
+    }
+
+    /// Renders the NFA as Graphviz DOT, with ε labelling epsilon transitions.
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph NFA {\n    rankdir=LR;\n    node [shape=circle];\n");
+        dot.push_str(&format!("    {} [shape=doublecircle];\n", self.accept.0));
+        dot.push_str("    start [shape=point];\n");
+        dot.push_str(&format!("    start -> {};\n", self.start.0));
+        for (src, sym, dst) in &self.transitions {
+            let label = match sym {
+                NFASymbol::Char(c) => escaped(*c).replace('\\', "\\\\"),
+                NFASymbol::Epsilon => "ε".to_string(),
+                NFASymbol::WordBoundary => "\\\\b".to_string(),
+                NFASymbol::Class(class) => format!("\\\\{}", class),
+            };
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"{}\"];\n",
+                src.0, dst.0, label
+            ));
         }
+        dot.push_str("}\n");
+        dot
     }
 }
 
 // ----- Subset construction (NFA -> DFA) -----
 
 use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// How many subset states `DFA::from_nfa` builds before giving up, unless
+/// `--max-dfa-states` says otherwise.
+pub const DEFAULT_MAX_DFA_STATES: usize = 10_000;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct DFAState {
+pub(crate) struct DFAState {
     nfa_states: BTreeSet<usize>,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone)]
-struct DFA {
+pub(crate) struct DFA {
     start: DFAState,
     accept_states: Vec<DFAState>,
     transitions: HashMap<(DFAState, char), DFAState>,
+    // Where each state goes when the current position is a word boundary. States
+    // missing here have no `\b` to cross and stay where they are.
+    boundary_transitions: HashMap<DFAState, DFAState>,
+    // Where each state goes on a non-ASCII character outside the alphabet, which
+    // only the negated classes `\D`, `\W` and `\S` accept. States missing
+    // here go to the dead state.
+    other_transitions: HashMap<DFAState, DFAState>,
+    // Non-accepting sink. Every state has a transition for every character in
+    // the alphabet, and other characters lead here unless `other_transitions`
+    // says otherwise.
+    dead: DFAState,
 }
 
 impl DFA {
-    fn from_nfa(nfa: &NFA) -> Self {
+    /// Subset construction. Fails once it has built more than `max_states`
+    /// states, before a pathological pattern can exhaust memory.
+    fn from_nfa(nfa: &NFA, max_states: usize) -> Result<Self, RegexError> {
         let start_closure = epsilon_closure(&nfa.transitions, nfa.start.0);
         let start_state = DFAState {
             nfa_states: start_closure,
         };
 
+        // No subset state built below is empty, since every closure contains the
+        // state it started from, so the empty set is free to act as the dead state.
+        let dead = DFAState {
+            nfa_states: BTreeSet::new(),
+        };
         let mut dfa = DFA {
             start: start_state.clone(),
             accept_states: Vec::new(),
             transitions: HashMap::new(),
+            boundary_transitions: HashMap::new(),
+            other_transitions: HashMap::new(),
+            dead: dead.clone(),
         };
 
         let mut worklist = vec![start_state.clone()];
@@ -267,16 +666,50 @@ impl DFA {
                 dfa.accept_states.push(current.clone());
             }
 
+            // Classes are ASCII, so a class edge stands for its ASCII members
+            // plus, for the negated ones, every non-ASCII character.
             let mut char_map: HashMap<char, BTreeSet<usize>> = HashMap::new();
+            let mut other = BTreeSet::new();
             for &s in &current.nfa_states {
                 for (src, sym, dst) in &nfa.transitions {
-                    if src.0 == s {
-                        if let NFASymbol::Char(c) = sym {
+                    if src.0 != s {
+                        continue;
+                    }
+                    match sym {
+                        NFASymbol::Char(c) => {
                             char_map.entry(*c).or_default().insert(dst.0);
                         }
+                        NFASymbol::Class(class) => {
+                            for c in class_members(*class) {
+                                char_map.entry(c).or_default().insert(dst.0);
+                            }
+                            if class.is_ascii_uppercase() {
+                                other.insert(dst.0);
+                            }
+                        }
+                        NFASymbol::Epsilon | NFASymbol::WordBoundary => {}
                     }
                 }
             }
+            for (c, set) in char_map.iter_mut() {
+                if !c.is_ascii() {
+                    set.extend(&other);
+                }
+            }
+            if !other.is_empty() {
+                let mut new_set = BTreeSet::new();
+                for st in &other {
+                    new_set.extend(epsilon_closure(&nfa.transitions, *st));
+                }
+                let new_state = DFAState {
+                    nfa_states: new_set,
+                };
+                if !visited.contains(&new_state) {
+                    visited.insert(new_state.clone());
+                    worklist.push(new_state.clone());
+                }
+                dfa.other_transitions.insert(current.clone(), new_state);
+            }
 
             for (c, set) in char_map {
                 let mut new_set = BTreeSet::new();
@@ -293,24 +726,614 @@ impl DFA {
                 }
                 dfa.transitions.insert((current.clone(), c), new_state);
             }
+
+            let crossed = boundary_closure(&nfa.transitions, &current.nfa_states);
+            if crossed != current.nfa_states {
+                let new_state = DFAState {
+                    nfa_states: crossed,
+                };
+                if !visited.contains(&new_state) {
+                    visited.insert(new_state.clone());
+                    worklist.push(new_state.clone());
+                }
+                dfa.boundary_transitions.insert(current.clone(), new_state);
+            }
+
+            if visited.len() > max_states {
+                return Err(RegexError(format!(
+                    "DFA too large (>{} states)",
+                    max_states
+                )));
+            }
+        }
+
+        // Complete the transition function so it is total over the alphabet. A
+        // state with no edge for a non-ASCII character still takes it like any
+        // other character outside its classes.
+        let alphabet: BTreeSet<char> = dfa.transitions.keys().map(|(_, c)| *c).collect();
+        visited.insert(dead.clone());
+        for state in &visited {
+            let other = dfa.other_transitions.get(state).unwrap_or(&dead).clone();
+            for &c in &alphabet {
+                let target = if c.is_ascii() { &dead } else { &other };
+                dfa.transitions
+                    .entry((state.clone(), c))
+                    .or_insert_with(|| target.clone());
+            }
         }
 
-        dfa
+        Ok(dfa)
     }
 
-    fn matches(&self, input: &str) -> bool {
-        let mut current = self.start.clone();
-        for c in input.chars() {
-            if let Some(next) = self.transitions.get(&(current.clone(), c)) {
-                current = next.clone();
-            } else {
+    /// Merges equivalent states using Moore's partition refinement. States start
+    /// out split into accepting and non-accepting blocks, and a block is split
+    /// again whenever its states move to different blocks on the same character
+    /// (ASCII characters outside the alphabet always lead to the dead state),
+    /// when they cross a word boundary into different blocks, or when other
+    /// non-ASCII characters take them to different blocks.
+    pub(crate) fn minimize(&self) -> DFA {
+        let states: Vec<DFAState> = self.states().into_iter().collect();
+        let alphabet: BTreeSet<char> = self.transitions.keys().map(|(_, c)| *c).collect();
+
+        let mut block: HashMap<DFAState, usize> = states
+            .iter()
+            .map(|s| (s.clone(), usize::from(self.accept_states.contains(s))))
+            .collect();
+        let mut block_count = block.values().collect::<BTreeSet<_>>().len();
+        loop {
+            let mut signatures: HashMap<Signature, usize> = HashMap::new();
+            let mut refined = HashMap::new();
+            for s in &states {
+                let targets = alphabet
+                    .iter()
+                    .map(|c| self.transitions.get(&(s.clone(), *c)).map(|t| block[t]))
+                    .collect();
+                let boundary = block[&self.cross_boundary(s)];
+                let other = block[self.other_transitions.get(s).unwrap_or(&self.dead)];
+                let next_id = signatures.len();
+                let id = *signatures
+                    .entry((block[s], targets, boundary, other))
+                    .or_insert(next_id);
+                refined.insert(s.clone(), id);
+            }
+            block = refined;
+            if signatures.len() == block_count {
+                break;
+            }
+            block_count = signatures.len();
+        }
+
+        // `states` is sorted, so each block is represented by its smallest state.
+        let mut representative: HashMap<usize, DFAState> = HashMap::new();
+        for s in &states {
+            representative.entry(block[s]).or_insert_with(|| s.clone());
+        }
+        let rep = |s: &DFAState| representative[&block[s]].clone();
+
+        let mut accept_states: Vec<DFAState> = representative
+            .values()
+            .filter(|s| self.accept_states.contains(s))
+            .cloned()
+            .collect();
+        accept_states.sort();
+
+        DFA {
+            start: rep(&self.start),
+            dead: rep(&self.dead),
+            accept_states,
+            transitions: self
+                .transitions
+                .iter()
+                .map(|((s, c), t)| ((rep(s), *c), rep(t)))
+                .collect(),
+            boundary_transitions: self
+                .boundary_transitions
+                .iter()
+                .map(|(s, t)| (rep(s), rep(t)))
+                .filter(|(s, t)| s != t)
+                .collect(),
+            other_transitions: self
+                .other_transitions
+                .iter()
+                .map(|(s, t)| (rep(s), rep(t)))
+                .filter(|(_, t)| *t != rep(&self.dead))
+                .collect(),
+        }
+    }
+
+    fn states(&self) -> BTreeSet<DFAState> {
+        let mut states = BTreeSet::new();
+        states.insert(self.start.clone());
+        // With an empty alphabet no transition mentions the dead state.
+        states.insert(self.dead.clone());
+        for ((src, _), dst) in &self.transitions {
+            states.insert(src.clone());
+            states.insert(dst.clone());
+        }
+        for (src, dst) in self
+            .boundary_transitions
+            .iter()
+            .chain(&self.other_transitions)
+        {
+            states.insert(src.clone());
+            states.insert(dst.clone());
+        }
+        states
+    }
+
+    #[cfg(test)]
+    pub(crate) fn state_count(&self) -> usize {
+        self.states().len()
+    }
+
+    /// Runs the DFA over `input` and returns the state it ends in.
+    #[cfg(test)]
+    pub(crate) fn run(&self, input: &str) -> DFAState {
+        self.run_in(input, 0..input.len())
+    }
+
+    /// Numbers the states in sorted order, so the same pattern always gets the
+    /// same IDs. Returns the states and a lookup from state to ID.
+    fn numbered_states(&self) -> (Vec<DFAState>, HashMap<DFAState, usize>) {
+        let states: Vec<DFAState> = self.states().into_iter().collect();
+        let ids = states
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.clone(), i))
+            .collect();
+        (states, ids)
+    }
+
+    /// Every transition as `(state id, input, next state id)`, sorted. Word
+    /// boundary transitions use `\b` as their input, and the transitions on
+    /// non-ASCII characters outside the alphabet use `OTHER_INPUT`.
+    fn numbered_edges(&self, ids: &HashMap<DFAState, usize>) -> Vec<(usize, String, usize)> {
+        let mut edges: Vec<(usize, String, usize)> = self
+            .transitions
+            .iter()
+            .map(|((src, c), dst)| (ids[src], escaped(*c), ids[dst]))
+            .chain(
+                self.boundary_transitions
+                    .iter()
+                    .map(|(src, dst)| (ids[src], "\\b".to_string(), ids[dst])),
+            )
+            .chain(
+                self.other_transitions
+                    .iter()
+                    .map(|(src, dst)| (ids[src], OTHER_INPUT.to_string(), ids[dst])),
+            )
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// Renders the DFA as a plain transition table: the start, accept and dead
+    /// states, then one `state  input  next` row per transition.
+    pub(crate) fn to_table(&self) -> String {
+        let (states, ids) = self.numbered_states();
+        let accept: Vec<String> = states
+            .iter()
+            .filter(|s| self.accept_states.contains(s))
+            .map(|s| format!("q{}", ids[s]))
+            .collect();
+
+        let mut table = format!("start: q{}\n", ids[&self.start]);
+        table.push_str(&format!("accept: {}\n", accept.join(", ")));
+        table.push_str(&format!("dead: q{}\n", ids[&self.dead]));
+        table.push_str(&format!("{:<6} {:<6} {}\n", "state", "input", "next"));
+        for (src, label, dst) in self.numbered_edges(&ids) {
+            let src = format!("q{}", src);
+            table.push_str(&format!("{:<6} {:<6} q{}\n", src, label, dst));
+        }
+        table
+    }
+
+    /// Renders the DFA as Graphviz DOT, numbered as in `to_table`. The dead
+    /// state is labelled so it stands out.
+    fn to_dot(&self) -> String {
+        let (states, ids) = self.numbered_states();
+        let id = |state: &DFAState| ids[state];
+
+        let mut dot = String::from("digraph DFA {\n    rankdir=LR;\n    node [shape=circle];\n");
+        for state in &states {
+            if self.accept_states.contains(state) {
+                dot.push_str(&format!("    q{} [shape=doublecircle];\n", id(state)));
+            } else if self.is_dead(state) {
+                dot.push_str(&format!("    q{} [label=\"dead\"];\n", id(state)));
+            }
+        }
+        dot.push_str("    start [shape=point];\n");
+        dot.push_str(&format!("    start -> q{};\n", id(&self.start)));
+
+        for (src, label, dst) in self.numbered_edges(&ids) {
+            // DOT reads a backslash as an escape, so `\b` needs doubling.
+            let label = label.replace('\\', "\\\\");
+            dot.push_str(&format!(
+                "    q{} -> q{} [label=\"{}\"];\n",
+                src, dst, label
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Accepted strings in order of length, then character order, found by a
+    /// breadth-first walk from the start state. Stops after `count` strings,
+    /// past `max_len` characters, or after `GENERATE_BUDGET` partial strings,
+    /// so Kleene loops and large alphabets can't make it run forever.
+    fn accepted_strings(&self, count: usize, max_len: usize) -> Vec<String> {
+        let mut edges: HashMap<&DFAState, Vec<char>> = HashMap::new();
+        for (src, c) in self.transitions.keys() {
+            edges.entry(src).or_default().push(*c);
+        }
+        for chars in edges.values_mut() {
+            chars.sort_unstable();
+        }
+        let mut found = Vec::new();
+        let mut explored = 0;
+        // Each entry is a prefix, the state after it, and whether it ends in
+        // a word character, so `\b` is crossed exactly as `run_in` would.
+        let mut frontier = vec![(String::new(), self.start.clone(), false)];
+        for _ in 0..=max_len {
+            let mut next = Vec::new();
+            for (prefix, state, prev_word) in frontier {
+                // The end of the text is a non-word position.
+                let at_end = if prev_word {
+                    self.cross_boundary(&state)
+                } else {
+                    state.clone()
+                };
+                if self.accept_states.contains(&at_end) {
+                    found.push(prefix.clone());
+                    if found.len() == count {
+                        return found;
+                    }
+                }
+                let crossed = self.cross_boundary(&state);
+                let mut chars: Vec<char> = [&state, &crossed]
+                    .into_iter()
+                    .flat_map(|s| edges.get(s).into_iter().flatten().copied())
+                    .collect();
+                chars.sort_unstable();
+                chars.dedup();
+                for c in chars {
+                    let from = if prev_word != is_word_char(c) {
+                        &crossed
+                    } else {
+                        &state
+                    };
+                    let to = self.step(from, c);
+                    if self.is_dead(&to) {
+                        continue;
+                    }
+                    explored += 1;
+                    if explored > GENERATE_BUDGET {
+                        return found;
+                    }
+                    let mut extended = prefix.clone();
+                    extended.push(c);
+                    next.push((extended, to, is_word_char(c)));
+                }
+            }
+            frontier = next;
+        }
+        found
+    }
+
+    #[cfg(test)]
+    pub(crate) fn matches(&self, input: &str) -> bool {
+        self.accept_states.contains(&self.run(input))
+    }
+}
+
+/// What the matching loops need from a DFA, so the eager [`DFA`] and the
+/// on-demand [`LazyDFA`] share them.
+pub(crate) trait Automaton {
+    fn start_state(&self) -> DFAState;
+
+    /// Follows one transition. Characters outside the alphabet go to the dead state.
+    fn step(&self, state: &DFAState, c: char) -> DFAState;
+
+    /// Takes the `\b` transition out of `state`, if it has one.
+    fn cross_boundary(&self, state: &DFAState) -> DFAState;
+
+    fn is_accepting(&self, state: &DFAState) -> bool;
+
+    fn is_dead(&self, state: &DFAState) -> bool;
+
+    /// Runs the automaton over `text[range]`. The characters just outside `range`
+    /// decide whether its ends are word boundaries, so `\b` sees the whole text.
+    fn run_in(&self, text: &str, range: Range<usize>) -> DFAState {
+        let mut current = self.start_state();
+        let mut prev_word = text[..range.start]
+            .chars()
+            .next_back()
+            .is_some_and(is_word_char);
+        for c in text[range.clone()].chars() {
+            if prev_word != is_word_char(c) {
+                current = self.cross_boundary(&current);
+            }
+            current = self.step(&current, c);
+            // The dead state only loops back to itself, so stop early.
+            if self.is_dead(&current) {
+                return current;
+            }
+            prev_word = is_word_char(c);
+        }
+        let next_word = text[range.end..].chars().next().is_some_and(is_word_char);
+        if prev_word != next_word {
+            current = self.cross_boundary(&current);
+        }
+        current
+    }
+
+    /// Whether the automaton accepts `text[range]`, with `\b` judged against the whole text.
+    fn matches_in(&self, text: &str, range: Range<usize>) -> bool {
+        self.is_accepting(&self.run_in(text, range))
+    }
+
+    /// Runs from `start` in one pass, remembering the last offset where the
+    /// automaton accepts. Boundaries are crossed the same way `run_in` crosses them.
+    /// With `shortest_nonempty` it instead stops at the first accepting offset
+    /// past `start`.
+    fn match_end_from(&self, text: &str, start: usize, shortest_nonempty: bool) -> Option<usize> {
+        let mut current = self.start_state();
+        let mut prev_word = text[..start].chars().next_back().is_some_and(is_word_char);
+        let mut last_accept = None;
+        let mut chars = text[start..].char_indices().peekable();
+        loop {
+            let next = chars.peek().copied();
+            let next_word = next.is_some_and(|(_, c)| is_word_char(c));
+            if prev_word != next_word {
+                current = self.cross_boundary(&current);
+            }
+            if self.is_accepting(&current) {
+                let end = start + next.map_or(text.len() - start, |(i, _)| i);
+                if shortest_nonempty && end > start {
+                    return Some(end);
+                }
+                if !shortest_nonempty {
+                    last_accept = Some(end);
+                }
+            }
+            let Some((_, c)) = chars.next() else {
+                return last_accept;
+            };
+            current = self.step(&current, c);
+            if self.is_dead(&current) {
+                return last_accept;
+            }
+            prev_word = next_word;
+        }
+    }
+
+    /// `match_end_from` run backwards from `end`, for an automaton built from
+    /// the reversed pattern: the smallest offset where it accepts, i.e. where
+    /// the longest match ending at `end` starts.
+    fn match_start_before(&self, text: &str, end: usize) -> Option<usize> {
+        let mut current = self.start_state();
+        let mut prev_word = text[end..].chars().next().is_some_and(is_word_char);
+        let mut first_accept = None;
+        let mut chars = text[..end].char_indices().rev().peekable();
+        let mut pos = end;
+        loop {
+            let next = chars.peek().copied();
+            let next_word = next.is_some_and(|(_, c)| is_word_char(c));
+            if prev_word != next_word {
+                current = self.cross_boundary(&current);
+            }
+            if self.is_accepting(&current) {
+                first_accept = Some(pos);
+            }
+            let Some((i, c)) = chars.next() else {
+                return first_accept;
+            };
+            current = self.step(&current, c);
+            if self.is_dead(&current) {
+                return first_accept;
+            }
+            pos = i;
+            prev_word = next_word;
+        }
+    }
+
+    /// Whether some match starts at `start`: like `match_end_from`, but returns
+    /// as soon as the automaton accepts instead of looking for a longer match.
+    fn accepts_from(&self, text: &str, start: usize) -> bool {
+        let mut current = self.start_state();
+        let mut prev_word = text[..start].chars().next_back().is_some_and(is_word_char);
+        let mut chars = text[start..].chars().peekable();
+        loop {
+            let next_word = chars.peek().is_some_and(|&c| is_word_char(c));
+            if prev_word != next_word {
+                current = self.cross_boundary(&current);
+            }
+            if self.is_accepting(&current) {
+                return true;
+            }
+            let Some(c) = chars.next() else {
+                return false;
+            };
+            current = self.step(&current, c);
+            if self.is_dead(&current) {
                 return false;
             }
+            prev_word = next_word;
         }
-        self.accept_states.contains(&current)
     }
 }
 
+impl Automaton for DFA {
+    fn start_state(&self) -> DFAState {
+        self.start.clone()
+    }
+
+    fn step(&self, state: &DFAState, c: char) -> DFAState {
+        let other = || {
+            if c.is_ascii() {
+                None
+            } else {
+                self.other_transitions.get(state)
+            }
+        };
+        self.transitions
+            .get(&(state.clone(), c))
+            .or_else(other)
+            .unwrap_or(&self.dead)
+            .clone()
+    }
+
+    fn cross_boundary(&self, state: &DFAState) -> DFAState {
+        self.boundary_transitions
+            .get(state)
+            .unwrap_or(state)
+            .clone()
+    }
+
+    fn is_accepting(&self, state: &DFAState) -> bool {
+        self.accept_states.contains(state)
+    }
+
+    fn is_dead(&self, state: &DFAState) -> bool {
+        *state == self.dead
+    }
+}
+
+/// A DFA built while matching instead of up front: each subset state and its
+/// transitions are computed the first time a search reaches them and cached,
+/// the way regex-automata's hybrid engine works. It skips minimization, so its
+/// states are raw NFA subsets, but it accepts exactly what [`DFA`] does.
+pub(crate) struct LazyDFA {
+    nfa: NFA,
+    start: DFAState,
+    // Behind a lock so a shared `CustomRegex` can still fill it in.
+    cache: Mutex<LazyCache>,
+}
+
+#[derive(Default)]
+struct LazyCache {
+    transitions: HashMap<(DFAState, char), DFAState>,
+    boundary_transitions: HashMap<DFAState, DFAState>,
+}
+
+impl LazyDFA {
+    fn new(nfa: NFA) -> Self {
+        let start = DFAState {
+            nfa_states: epsilon_closure(&nfa.transitions, nfa.start.0),
+        };
+        Self {
+            nfa,
+            start,
+            cache: Mutex::new(LazyCache::default()),
+        }
+    }
+
+    /// How many distinct states the searches so far have reached.
+    #[cfg(test)]
+    pub(crate) fn materialized_states(&self) -> usize {
+        let cache = self.cache.lock().unwrap();
+        let mut states: BTreeSet<&DFAState> = BTreeSet::new();
+        states.insert(&self.start);
+        for ((src, _), dst) in &cache.transitions {
+            states.insert(src);
+            states.insert(dst);
+        }
+        for (src, dst) in &cache.boundary_transitions {
+            states.insert(src);
+            states.insert(dst);
+        }
+        states.len()
+    }
+}
+
+impl Automaton for LazyDFA {
+    fn start_state(&self) -> DFAState {
+        self.start.clone()
+    }
+
+    fn step(&self, state: &DFAState, c: char) -> DFAState {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(next) = cache.transitions.get(&(state.clone(), c)) {
+            return next.clone();
+        }
+        // The same subset construction as `DFA::from_nfa`, for one state and
+        // one character. No NFA state moving on `c` gives the empty dead set.
+        let mut nfa_states = BTreeSet::new();
+        for (src, sym, dst) in &self.nfa.transitions {
+            let moves = match sym {
+                NFASymbol::Char(x) => *x == c,
+                NFASymbol::Class(class) => class_contains(*class, c),
+                NFASymbol::Epsilon | NFASymbol::WordBoundary => false,
+            };
+            if moves && state.nfa_states.contains(&src.0) {
+                nfa_states.extend(epsilon_closure(&self.nfa.transitions, dst.0));
+            }
+        }
+        let next = DFAState { nfa_states };
+        cache.transitions.insert((state.clone(), c), next.clone());
+        next
+    }
+
+    fn cross_boundary(&self, state: &DFAState) -> DFAState {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(next) = cache.boundary_transitions.get(state) {
+            return next.clone();
+        }
+        let next = DFAState {
+            nfa_states: boundary_closure(&self.nfa.transitions, &state.nfa_states),
+        };
+        cache
+            .boundary_transitions
+            .insert(state.clone(), next.clone());
+        next
+    }
+
+    fn is_accepting(&self, state: &DFAState) -> bool {
+        state.nfa_states.contains(&self.nfa.accept.0)
+    }
+
+    fn is_dead(&self, state: &DFAState) -> bool {
+        state.nfa_states.is_empty()
+    }
+}
+
+/// How `--dump-dfa` and `--dot` label a transition on a non-ASCII character
+/// outside the alphabet.
+const OTHER_INPUT: &str = "other";
+
+/// Whether `c` is in the shorthand class named by `class`: `d` digits, `w`
+/// word characters, `s` whitespace, all ASCII; uppercase negates. Note `\b`
+/// uses the Unicode `is_word_char` instead.
+fn class_contains(class: char, c: char) -> bool {
+    let member = match class.to_ascii_lowercase() {
+        'd' => c.is_ascii_digit(),
+        'w' => c.is_ascii_alphanumeric() || c == '_',
+        's' => matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0b' | '\x0c'),
+        _ => unreachable!("the parser only builds \\d, \\w and \\s classes"),
+    };
+    member != class.is_ascii_uppercase()
+}
+
+/// The ASCII characters in `class`. Negated classes also take every non-ASCII
+/// character, which the DFA handles through `other_transitions`.
+fn class_members(class: char) -> impl Iterator<Item = char> {
+    (0..128u8)
+        .map(char::from)
+        .filter(move |&c| class_contains(class, c))
+}
+
+/// A state's block during minimization, then its target blocks on each
+/// character of the alphabet, across a word boundary and on other characters.
+type Signature = (usize, Vec<Option<usize>>, usize, usize);
+
+/// Upper bound on the partial strings `DFA::accepted_strings` builds.
+const GENERATE_BUDGET: usize = 100_000;
+
+/// Word characters for `\b`, the same set the builtin engine uses.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 fn epsilon_closure(transitions: &[(State, NFASymbol, State)], start: usize) -> BTreeSet<usize> {
     let mut stack = vec![start];
     let mut closure = BTreeSet::new();
@@ -326,17 +1349,400 @@ fn epsilon_closure(transitions: &[(State, NFASymbol, State)], start: usize) -> B
     closure
 }
 
-fn regex_to_dfa(pattern: &str) -> DFA {
+/// Everything reachable from `states` through ε and `\b` transitions, i.e. where
+/// the NFA can be once the current position is known to be a word boundary.
+fn boundary_closure(
+    transitions: &[(State, NFASymbol, State)],
+    states: &BTreeSet<usize>,
+) -> BTreeSet<usize> {
+    let mut stack: Vec<usize> = states.iter().copied().collect();
+    let mut closure = states.clone();
+    while let Some(s) = stack.pop() {
+        for (src, sym, dst) in transitions {
+            if src.0 == s
+                && matches!(sym, NFASymbol::Epsilon | NFASymbol::WordBoundary)
+                && !closure.contains(&dst.0)
+            {
+                closure.insert(dst.0);
+                stack.push(dst.0);
+            }
+        }
+    }
+    closure
+}
+
+/// Removes redundant nodes left over from parsing: `Concat(X, Empty)` and
+/// `Concat(Empty, X)` become `X`, and `Union(X, X)` becomes `X`.
+pub(crate) fn simplify(ast: RegexAST) -> RegexAST {
+    match ast {
+        RegexAST::Concat(a, b) => match (simplify(*a), simplify(*b)) {
+            (RegexAST::Empty, x) | (x, RegexAST::Empty) => x,
+            (a, b) => RegexAST::Concat(Box::new(a), Box::new(b)),
+        },
+        RegexAST::Union(a, b) => match (simplify(*a), simplify(*b)) {
+            (a, b) if a == b => a,
+            (a, b) => RegexAST::Union(Box::new(a), Box::new(b)),
+        },
+        RegexAST::Kleene(a) => RegexAST::Kleene(Box::new(simplify(*a))),
+        RegexAST::Optional(a) => RegexAST::Optional(Box::new(simplify(*a))),
+        RegexAST::Lazy(a) => RegexAST::Lazy(Box::new(simplify(*a))),
+        RegexAST::Group(a) => RegexAST::Group(Box::new(simplify(*a))),
+        leaf => leaf,
+    }
+}
+
+/// Flattens nested `Union`s into one list of alternatives and pulls common
+/// leading literals out of them, so `cat|car|can` becomes `ca(t|r|n)`. Only the
+/// shape changes; the tree still matches exactly the same strings.
+pub(crate) fn factor_alternatives(ast: RegexAST) -> RegexAST {
+    match ast {
+        RegexAST::Union(..) => {
+            let mut alternatives = Vec::new();
+            flatten_union(ast, &mut alternatives);
+            factor_sequences(alternatives.into_iter().map(concat_items).collect())
+        }
+        RegexAST::Concat(a, b) => RegexAST::Concat(
+            Box::new(factor_alternatives(*a)),
+            Box::new(factor_alternatives(*b)),
+        ),
+        RegexAST::Kleene(a) => RegexAST::Kleene(Box::new(factor_alternatives(*a))),
+        RegexAST::Optional(a) => RegexAST::Optional(Box::new(factor_alternatives(*a))),
+        RegexAST::Lazy(a) => RegexAST::Lazy(Box::new(factor_alternatives(*a))),
+        RegexAST::Group(a) => RegexAST::Group(Box::new(factor_alternatives(*a))),
+        leaf => leaf,
+    }
+}
+
+/// Collects the alternatives of a `Union` tree however it is nested, factoring
+/// each one on the way.
+fn flatten_union(ast: RegexAST, out: &mut Vec<RegexAST>) {
+    match ast {
+        RegexAST::Union(a, b) => {
+            flatten_union(*a, out);
+            flatten_union(*b, out);
+        }
+        other => {
+            let other = factor_alternatives(other);
+            if !out.contains(&other) {
+                out.push(other);
+            }
+        }
+    }
+}
+
+/// Splits a `Concat` tree into its items in order; `Empty` has none.
+fn concat_items(ast: RegexAST) -> Vec<RegexAST> {
+    match ast {
+        RegexAST::Concat(a, b) => {
+            let mut items = concat_items(*a);
+            items.extend(concat_items(*b));
+            items
+        }
+        RegexAST::Empty => Vec::new(),
+        other => vec![other],
+    }
+}
+
+/// Rebuilds a left-nested `Concat` (as the parser builds it) from `items`.
+fn concat_of(items: Vec<RegexAST>) -> RegexAST {
+    items
+        .into_iter()
+        .reduce(|a, b| RegexAST::Concat(Box::new(a), Box::new(b)))
+        .unwrap_or(RegexAST::Empty)
+}
+
+/// Unions the alternatives in `sequences`, grouping those that start with the
+/// same literal under a single copy of it. Groups keep the order in which their
+/// first alternative appeared.
+fn factor_sequences(sequences: Vec<Vec<RegexAST>>) -> RegexAST {
+    let mut groups: Vec<(Option<char>, Vec<Vec<RegexAST>>)> = Vec::new();
+    for sequence in sequences {
+        let head = match sequence.first() {
+            Some(RegexAST::Literal(c)) => Some(*c),
+            _ => None,
+        };
+        match groups
+            .iter_mut()
+            .find(|(h, _)| head.is_some() && *h == head)
+        {
+            Some((_, members)) => members.push(sequence),
+            None => groups.push((head, vec![sequence])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(head, mut members)| match head {
+            Some(c) if members.len() > 1 => {
+                let rests = members.drain(..).map(|m| m[1..].to_vec()).collect();
+                concat_of(vec![RegexAST::Literal(c), factor_sequences(rests)])
+            }
+            _ => concat_of(members.remove(0)),
+        })
+        .reduce(|a, b| RegexAST::Union(Box::new(a), Box::new(b)))
+        .unwrap_or(RegexAST::Empty)
+}
+
+/// Parses `pattern` and simplifies the tree, ready for NFA construction.
+pub(crate) fn parse_pattern(pattern: &str) -> Result<RegexAST, RegexError> {
     let mut parser = Parser::new(pattern);
-    let ast = parser.parse();
-    let nfa = NFA::from_ast(&ast);
-    DFA::from_nfa(&nfa)
+    Ok(simplify(parser.parse()?))
+}
+
+/// Parses `pattern` as written, before simplification.
+pub(crate) fn parse_tree(pattern: &str) -> Result<RegexAST, RegexError> {
+    Parser::new(pattern).parse()
+}
+
+/// Renders the parse tree the custom engine builds for `pattern`, for `--explain`.
+pub fn explain(pattern: &str) -> Result<String, RegexError> {
+    Ok(parse_tree(pattern)?.to_string())
+}
+
+/// The parse tree `explain` prints, as indented lines, for the TUI's `a` pane.
+pub fn ast_tree(pattern: &str) -> Result<Vec<String>, RegexError> {
+    Ok(parse_tree(pattern)?.tree_lines())
+}
+
+/// Rewrites `pattern` from its simplified parse tree, e.g. `(?:a)(?:aa*)` as
+/// `aa+`. The result parses to the same tree.
+pub fn normalize(pattern: &str) -> Result<String, RegexError> {
+    Ok(parse_pattern(pattern)?.to_pattern())
+}
+
+/// Graphviz DOT for the Thompson NFA the custom engine builds for `pattern`.
+pub fn nfa_dot(pattern: &str) -> Result<String, RegexError> {
+    Ok(NFA::from_ast(&parse_pattern(pattern)?).to_dot())
+}
+
+/// How many states the Thompson NFA for `ast` has.
+#[cfg(test)]
+pub(crate) fn nfa_state_count(ast: &RegexAST) -> usize {
+    NFA::from_ast(ast).state_count
+}
+
+/// Graphviz DOT for the minimized DFA the custom engine matches with, built
+/// with at most `max_states` states.
+pub fn dfa_dot(pattern: &str, max_states: usize) -> Result<String, RegexError> {
+    Ok(regex_to_dfa(pattern, max_states)?.to_dot())
+}
+
+/// The minimized DFA's transition table, for `--dump-dfa`.
+pub fn dfa_table(pattern: &str, max_states: usize) -> Result<String, RegexError> {
+    Ok(regex_to_dfa(pattern, max_states)?.to_table())
+}
+
+pub(crate) fn regex_to_dfa(pattern: &str, max_states: usize) -> Result<DFA, RegexError> {
+    let ast = factor_alternatives(parse_pattern(pattern)?);
+    ast_to_dfa(&ast, max_states)
+}
+
+/// The minimized DFA of the reversed tree, which reads text right to left.
+/// Built from the reversed Thompson NFA rather than by flipping the DFA's own
+/// transitions: the DFA keeps classes and non-ASCII input in separate maps
+/// that plain reversed edges can't express.
+pub(crate) fn reverse_dfa(ast: &RegexAST, max_states: usize) -> Result<DFA, RegexError> {
+    let nfa = NFA::from_ast(ast).reversed();
+    Ok(DFA::from_nfa(&nfa, max_states)?.minimize())
+}
+
+/// Builds the minimized DFA for an already parsed tree, or fails when subset
+/// construction needs more than `max_states` states.
+pub(crate) fn ast_to_dfa(ast: &RegexAST, max_states: usize) -> Result<DFA, RegexError> {
+    let nfa = NFA::from_ast(ast);
+    Ok(DFA::from_nfa(&nfa, max_states)?.minimize())
+}
+
+// ----- Leftmost-first matching -----
+
+/// Which match wins among several starting at the same leftmost position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchSemantics {
+    /// The longest one, as POSIX tools report.
+    LeftmostLongest,
+    /// The one reached through the earliest alternatives and greediest
+    /// quantifiers, as the `regex` crate and Perl report.
+    LeftmostFirst,
+}
+
+impl std::str::FromStr for MatchSemantics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "longest" => Ok(MatchSemantics::LeftmostLongest),
+            "first" => Ok(MatchSemantics::LeftmostFirst),
+            _ => Err(format!(
+                "Unknown match semantics '{}'. Valid values: longest, first",
+                s
+            )),
+        }
+    }
+}
+
+/// Runs the NFA directly, one thread per state, keeping threads in order of
+/// preference. Subset construction forgets which alternative a state came
+/// from, so the DFA can only find the longest match; following transitions in
+/// the order they were added finds the leftmost-first one.
+struct PikeVM {
+    // Each state's outgoing transitions, most preferred first.
+    edges: Vec<Vec<(NFASymbol, usize)>>,
+    start: usize,
+    accept: usize,
+}
+
+impl PikeVM {
+    fn new(nfa: &NFA) -> Self {
+        let mut edges = vec![Vec::new(); nfa.state_count];
+        for (src, sym, dst) in &nfa.transitions {
+            edges[src.0].push((sym.clone(), dst.0));
+        }
+        PikeVM {
+            edges,
+            start: nfa.start.0,
+            accept: nfa.accept.0,
+        }
+    }
+
+    /// Adds `state` and everything it reaches through ε transitions, and `\b`
+    /// ones when `at_boundary`, to `threads` in order of preference. States
+    /// already `seen` at this position belong to a more preferred thread.
+    fn add_thread(
+        &self,
+        threads: &mut Vec<(usize, usize)>,
+        seen: &mut [bool],
+        state: usize,
+        start: usize,
+        at_boundary: bool,
+    ) {
+        let mut stack = vec![state];
+        while let Some(s) = stack.pop() {
+            if seen[s] {
+                continue;
+            }
+            seen[s] = true;
+            threads.push((s, start));
+            for (sym, dst) in self.edges[s].iter().rev() {
+                if matches!(sym, NFASymbol::Epsilon)
+                    || (at_boundary && matches!(sym, NFASymbol::WordBoundary))
+                {
+                    stack.push(*dst);
+                }
+            }
+        }
+    }
+
+    /// The leftmost-first match starting at or after `from`.
+    fn find_at(&self, text: &str, from: usize) -> Option<Range<usize>> {
+        let mut prev_word = text[..from].chars().next_back().is_some_and(is_word_char);
+        // Threads that consumed the previous character: (state, match start).
+        let mut stepped: Vec<(usize, usize)> = Vec::new();
+        let mut threads = Vec::new();
+        let mut matched = None;
+        let mut pos = from;
+        loop {
+            let next = text[pos..].chars().next();
+            let next_word = next.is_some_and(is_word_char);
+            let at_boundary = prev_word != next_word;
+            let mut seen = vec![false; self.edges.len()];
+            threads.clear();
+            for &(state, start) in &stepped {
+                self.add_thread(&mut threads, &mut seen, state, start, at_boundary);
+            }
+            // A match starting here would be less preferred than any found so far.
+            if matched.is_none() {
+                self.add_thread(&mut threads, &mut seen, self.start, pos, at_boundary);
+            }
+            stepped.clear();
+            for &(state, start) in &threads {
+                if state == self.accept {
+                    // Every thread after this one is less preferred.
+                    matched = Some(start..pos);
+                    break;
+                }
+                let Some(c) = next else { continue };
+                for (sym, dst) in &self.edges[state] {
+                    let takes = match sym {
+                        NFASymbol::Char(x) => *x == c,
+                        NFASymbol::Class(class) => class_contains(*class, c),
+                        NFASymbol::Epsilon | NFASymbol::WordBoundary => false,
+                    };
+                    if takes {
+                        stepped.push((*dst, start));
+                    }
+                }
+            }
+            let Some(c) = next else {
+                return matched;
+            };
+            if stepped.is_empty() && matched.is_some() {
+                return matched;
+            }
+            pos += c.len_utf8();
+            prev_word = next_word;
+        }
+    }
 }
 
 // ----- CustomRegex definition -----
 
 pub struct CustomRegex {
-    dfa: DFA,
+    dfa: CompiledDFA,
+    // Set when the pattern has a lazy quantifier.
+    shortest: bool,
+    // The simplified parse tree, before factoring reorders alternatives, for
+    // leftmost-first matching.
+    ast: RegexAST,
+    // The reversed pattern's DFA for `match_ending_at`, built on first use.
+    // `None` when it would pass the state limit.
+    reverse: OnceLock<Option<DFA>>,
+    // The leftmost-first simulator for `find_iter_with`, built on first use.
+    pike_vm: OnceLock<PikeVM>,
+    // The state limit it was compiled with, for the DFAs built later.
+    max_states: usize,
+}
+
+/// The DFA a `CustomRegex` searches with, built up front or on demand.
+enum CompiledDFA {
+    Eager(DFA),
+    Lazy(LazyDFA),
+}
+
+impl Automaton for CompiledDFA {
+    fn start_state(&self) -> DFAState {
+        match self {
+            CompiledDFA::Eager(dfa) => dfa.start_state(),
+            CompiledDFA::Lazy(lazy) => lazy.start_state(),
+        }
+    }
+
+    fn step(&self, state: &DFAState, c: char) -> DFAState {
+        match self {
+            CompiledDFA::Eager(dfa) => dfa.step(state, c),
+            CompiledDFA::Lazy(lazy) => lazy.step(state, c),
+        }
+    }
+
+    fn cross_boundary(&self, state: &DFAState) -> DFAState {
+        match self {
+            CompiledDFA::Eager(dfa) => dfa.cross_boundary(state),
+            CompiledDFA::Lazy(lazy) => lazy.cross_boundary(state),
+        }
+    }
+
+    fn is_accepting(&self, state: &DFAState) -> bool {
+        match self {
+            CompiledDFA::Eager(dfa) => dfa.is_accepting(state),
+            CompiledDFA::Lazy(lazy) => lazy.is_accepting(state),
+        }
+    }
+
+    fn is_dead(&self, state: &DFAState) -> bool {
+        match self {
+            CompiledDFA::Eager(dfa) => dfa.is_dead(state),
+            CompiledDFA::Lazy(lazy) => lazy.is_dead(state),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -350,26 +1756,366 @@ impl std::fmt::Display for RegexError {
 
 impl std::error::Error for RegexError {}
 
+/// A budgeted search that ran out of DFA steps before finishing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted {
+    /// The budget it ran out of.
+    pub max_steps: usize,
+}
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Search interrupted after {} DFA steps", self.max_steps)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
 impl CustomRegex {
+    /// Compiles `pattern`. An empty pattern is valid and matches only the empty
+    /// string, so searching with it yields a zero-width match at every position,
+    /// the same as the builtin engine.
+    ///
+    /// Fails on a pattern whose DFA would need more than
+    /// `DEFAULT_MAX_DFA_STATES` states.
     pub fn new(pattern: &str) -> Result<CustomRegex, RegexError> {
-        if pattern.is_empty() {
-            return Err(RegexError("Empty pattern".to_string()));
+        Self::with_max_states(pattern, DEFAULT_MAX_DFA_STATES)
+    }
+
+    /// Like `new`, with an explicit limit on the DFA's states.
+    pub fn with_max_states(pattern: &str, max_states: usize) -> Result<CustomRegex, RegexError> {
+        let ast = parse_pattern(pattern)?;
+        let shortest = ast.has_lazy();
+        let dfa = CompiledDFA::Eager(ast_to_dfa(&factor_alternatives(ast.clone()), max_states)?);
+        Ok(CustomRegex {
+            dfa,
+            shortest,
+            ast,
+            reverse: OnceLock::new(),
+            pike_vm: OnceLock::new(),
+            max_states,
+        })
+    }
+
+    /// Like `new`, but builds DFA states only as searches reach them instead
+    /// of all up front. Matches the same strings; worth it when a pattern has
+    /// many states and searches visit few of them.
+    pub fn new_lazy(pattern: &str) -> Result<CustomRegex, RegexError> {
+        let ast = parse_pattern(pattern)?;
+        let shortest = ast.has_lazy();
+        let nfa = NFA::from_ast(&factor_alternatives(ast.clone()));
+        let dfa = CompiledDFA::Lazy(LazyDFA::new(nfa));
+        Ok(CustomRegex {
+            dfa,
+            shortest,
+            ast,
+            reverse: OnceLock::new(),
+            pike_vm: OnceLock::new(),
+            max_states: DEFAULT_MAX_DFA_STATES,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn has_pike_vm(&self) -> bool {
+        self.pike_vm.get().is_some()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn lazy_dfa(&self) -> Option<&LazyDFA> {
+        match &self.dfa {
+            CompiledDFA::Lazy(lazy) => Some(lazy),
+            CompiledDFA::Eager(_) => None,
         }
-        let dfa = regex_to_dfa(pattern);
-        Ok(CustomRegex { dfa })
     }
 
     pub fn find_iter<'a>(&'a self, text: &'a str) -> Vec<&'a str> {
-        // Naive substring search:
+        self.find_iter_up_to(text, None)
+    }
+
+    /// Like `find_iter`, but stops searching once `limit` matches are found.
+    pub fn find_iter_up_to<'a>(&'a self, text: &'a str, limit: Option<usize>) -> Vec<&'a str> {
+        self.find_ranges_up_to(text, limit)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    /// Byte ranges of every substring the DFA accepts, in the same order as `find_iter`.
+    pub fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.find_ranges_up_to(text, None)
+    }
+
+    /// Like `find_ranges`, but stops searching once `limit` matches are found.
+    ///
+    /// Normally every accepted substring is reported. A pattern with a lazy
+    /// quantifier (`*?`, `+?`, `??`) is searched leftmost-shortest instead: the
+    /// shortest match at the leftmost start, then the search resumes after it, so
+    /// `a+?` finds three `a`s in "aaa" where `a+` also finds `aa` and `aaa`. The
+    /// DFA has no notion of which quantifier a character belongs to, so this
+    /// applies to the whole pattern rather than only the lazy part.
+    pub fn find_ranges_up_to(&self, text: &str, limit: Option<usize>) -> Vec<Range<usize>> {
+        self.find_ranges_budgeted(text, limit, usize::MAX)
+            .expect("an unlimited budget never runs out")
+    }
+
+    /// The leftmost match, as `(start, end)` byte offsets: the longest match at
+    /// the first position where one starts, or the shortest for lazy patterns.
+    /// `None` when nothing matches. Stops at the first start that works.
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        if self.shortest {
+            return self
+                .find_ranges_up_to(text, Some(1))
+                .pop()
+                .map(|r| (r.start, r.end));
+        }
+        self.longest_at(text, 0).map(|r| (r.start, r.end))
+    }
+
+    /// The longest match at the first position from `from` on where one starts.
+    fn longest_at(&self, text: &str, from: usize) -> Option<Range<usize>> {
+        text[from..]
+            .char_indices()
+            .map(|(i, _)| from + i)
+            .chain(std::iter::once(text.len()))
+            .find_map(|start| Some(start..self.match_at(text, start)?))
+    }
+
+    /// Non-overlapping matches from left to right, with `semantics` picking
+    /// among the matches at each leftmost start, for `--match-semantics`. So
+    /// `a|ab` over "ab" finds `ab` leftmost-longest and `a` leftmost-first.
+    /// Lazy quantifiers only change leftmost-first matches. As with the
+    /// builtin engine, an empty match right where the previous match ended is
+    /// skipped.
+    pub fn find_iter_with(&self, text: &str, semantics: MatchSemantics) -> Vec<Range<usize>> {
+        self.find_iter_with_up_to(text, semantics, None)
+    }
+
+    /// Like `find_iter_with`, but stops once `limit` matches are found.
+    pub fn find_iter_with_up_to(
+        &self,
+        text: &str,
+        semantics: MatchSemantics,
+        limit: Option<usize>,
+    ) -> Vec<Range<usize>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let vm = match semantics {
+            MatchSemantics::LeftmostFirst => Some(
+                self.pike_vm
+                    .get_or_init(|| PikeVM::new(&NFA::from_ast(&self.ast))),
+            ),
+            MatchSemantics::LeftmostLongest => None,
+        };
+        let after = |pos: usize| pos + text[pos..].chars().next().map_or(1, char::len_utf8);
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let mut from = 0;
+        while from <= text.len() && ranges.len() < limit {
+            let found = match vm {
+                Some(vm) => vm.find_at(text, from),
+                None => self.longest_at(text, from),
+            };
+            let Some(found) = found else {
+                break;
+            };
+            if found.is_empty() {
+                from = after(found.end);
+                if ranges.last().is_some_and(|last| last.end == found.end) {
+                    continue;
+                }
+            } else {
+                from = found.end;
+            }
+            ranges.push(found);
+        }
+        ranges
+    }
+
+    /// Whether the pattern matches anywhere in `text`. Stops at the first
+    /// accepting state, so it never works out where a match ends.
+    pub fn is_match(&self, text: &str) -> bool {
+        text.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .any(|start| self.dfa.accepts_from(text, start))
+    }
+
+    /// The last match `find_ranges` would report, as `(start, end)` byte offsets:
+    /// the longest match at the rightmost position where one starts. `None` when
+    /// nothing matches. Scans backwards, so it stops at the first start that works.
+    pub fn rfind(&self, text: &str) -> Option<(usize, usize)> {
+        if self.shortest {
+            return self.find_ranges(text).pop().map(|r| (r.start, r.end));
+        }
+        text.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .rev()
+            .find_map(|start| Some((start, self.match_at(text, start)?)))
+    }
+
+    /// End offset of the longest match starting exactly at byte `start`, or
+    /// `None` when nothing matches there. Meant for tokenizers that match at a
+    /// known position instead of scanning the whole text. Also `None` when
+    /// `start` is past the end or not on a char boundary.
+    pub fn match_at(&self, text: &str, start: usize) -> Option<usize> {
+        if !text.is_char_boundary(start) {
+            return None;
+        }
+        self.dfa.match_end_from(text, start, false)
+    }
+
+    /// Start offset of the longest match ending exactly at byte `end`: the
+    /// smallest `start` such that `text[start..end]` matches, found by running
+    /// the reversed pattern's DFA leftwards from `end`. `None` when nothing
+    /// matches there, or when `end` is past the end or not on a char boundary.
+    pub fn match_ending_at(&self, text: &str, end: usize) -> Option<usize> {
+        if !text.is_char_boundary(end) {
+            return None;
+        }
+        let reverse = self
+            .reverse
+            .get_or_init(|| reverse_dfa(&self.ast, self.max_states).ok());
+        match reverse {
+            Some(reverse) => reverse.match_start_before(text, end),
+            // Too big to reverse: try each start, leftmost first.
+            None => text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(text.len()))
+                .take_while(|&start| start <= end)
+                .find(|&start| self.dfa.matches_in(text, start..end)),
+        }
+    }
+
+    /// Up to `count` distinct strings the whole pattern matches, shortest first,
+    /// none longer than `max_len` characters. For `--generate`.
+    pub fn generate(&self, count: usize, max_len: usize) -> Vec<String> {
+        match &self.dfa {
+            CompiledDFA::Eager(dfa) => dfa.accepted_strings(count, max_len),
+            // Listing strings walks every state anyway, so build them all.
+            // Nothing to list when that would pass the state limit.
+            CompiledDFA::Lazy(lazy) => DFA::from_nfa(&lazy.nfa, self.max_states)
+                .map(|dfa| dfa.accepted_strings(count, max_len))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// For `--overlapping`: at every start position, the shortest non-empty
+    /// match beginning there, even where it overlaps the previous one. Ordered
+    /// by start, so `aa` over "aaaa" gives 0..2, 1..3 and 2..4.
+    pub fn find_overlapping(&self, text: &str) -> Vec<Range<usize>> {
+        self.find_overlapping_up_to(text, None)
+    }
+
+    /// Like `find_overlapping`, but stops once `limit` matches are found.
+    pub fn find_overlapping_up_to(&self, text: &str, limit: Option<usize>) -> Vec<Range<usize>> {
+        text.char_indices()
+            .filter_map(|(start, _)| Some(start..self.dfa.match_end_from(text, start, true)?))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Like `find_iter`, but gives up with `Interrupted` once the DFA has
+    /// taken more than `max_steps` transitions, so a caller embedding the
+    /// engine can bound how long a search runs.
+    pub fn find_iter_budgeted<'a>(
+        &'a self,
+        text: &'a str,
+        max_steps: usize,
+    ) -> Result<Vec<&'a str>, Interrupted> {
+        Ok(self
+            .find_ranges_budgeted(text, None, max_steps)?
+            .into_iter()
+            .map(|range| &text[range])
+            .collect())
+    }
+
+    /// `find_ranges_up_to` under a budget of `max_steps` DFA transitions. The
+    /// DFA runs once per start over char boundaries, reporting each end where
+    /// it accepts, so multi-byte characters are never split.
+    pub fn find_ranges_budgeted(
+        &self,
+        text: &str,
+        limit: Option<usize>,
+        max_steps: usize,
+    ) -> Result<Vec<Range<usize>>, Interrupted> {
+        let mut steps = 0usize;
+        self.search(text, limit, || {
+            steps += 1;
+            steps <= max_steps
+        })
+        .ok_or(Interrupted { max_steps })
+    }
+
+    /// `find_ranges`, giving up with `None` once `cancel` is set. Checked on
+    /// every DFA transition, so another thread can stop a long search.
+    pub fn find_ranges_cancellable(
+        &self,
+        text: &str,
+        cancel: &AtomicBool,
+    ) -> Option<Vec<Range<usize>>> {
+        self.find_ranges_cancellable_up_to(text, None, cancel)
+    }
+
+    /// Like `find_ranges_cancellable`, but stops searching once `limit`
+    /// matches are found.
+    pub fn find_ranges_cancellable_up_to(
+        &self,
+        text: &str,
+        limit: Option<usize>,
+        cancel: &AtomicBool,
+    ) -> Option<Vec<Range<usize>>> {
+        self.search(text, limit, || !cancel.load(Ordering::Relaxed))
+    }
+
+    /// The one-pass search behind `find_ranges_budgeted`: `step` is asked
+    /// before every DFA transition and stops the search with `None` when it
+    /// returns false.
+    fn search(
+        &self,
+        text: &str,
+        limit: Option<usize>,
+        mut step: impl FnMut() -> bool,
+    ) -> Option<Vec<Range<usize>>> {
+        let limit = limit.unwrap_or(usize::MAX);
         let mut results = Vec::new();
-        for start in 0..text.len() {
-            for end in start..=text.len() {
-                let substring = &text[start..end];
-                if self.dfa.matches(substring) {
-                    results.push(substring);
+        let mut start = 0;
+        while start <= text.len() && results.len() < limit {
+            let mut next = start + text[start..].chars().next().map_or(1, char::len_utf8);
+            let mut current = self.dfa.start_state();
+            let mut prev_word = text[..start].chars().next_back().is_some_and(is_word_char);
+            let mut chars = text[start..].char_indices().peekable();
+            loop {
+                let peeked = chars.peek().copied();
+                let next_word = peeked.is_some_and(|(_, c)| is_word_char(c));
+                if prev_word != next_word {
+                    current = self.dfa.cross_boundary(&current);
+                }
+                if self.dfa.is_accepting(&current) {
+                    let end = start + peeked.map_or(text.len() - start, |(i, _)| i);
+                    results.push(start..end);
+                    // Lazy patterns take the shortest match and resume after it.
+                    if self.shortest {
+                        next = next.max(end);
+                        break;
+                    }
+                    if results.len() >= limit {
+                        break;
+                    }
+                }
+                let Some((_, c)) = chars.next() else {
+                    break;
+                };
+                if !step() {
+                    return None;
+                }
+                current = self.dfa.step(&current, c);
+                if self.dfa.is_dead(&current) {
+                    break;
                 }
+                prev_word = next_word;
             }
+            start = next;
         }
-        results
+        Some(results)
     }
 }
@@ -1,6 +1,18 @@
-use crate::custom_regex::CustomRegex;
-use regex::Regex;
+use crate::builder::{Regexer, RegexerBuilder};
+use crate::custom_regex::{
+    parse_tree, CustomRegex, MatchSemantics, RegexAST, RegexError, DEFAULT_MAX_DFA_STATES,
+};
+use encoding_rs::{DecoderResult, Encoding};
+use regex::{Regex, RegexSet};
+use regex_automata::hybrid::dfa::OverlappingState;
+use regex_automata::{hybrid, meta, Anchored, Input, MatchError, MatchKind};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EngineChoice {
     Builtin,
     Custom,
@@ -13,103 +25,1847 @@ pub enum EngineChoice {
     Custommeta, // New engine
 }
 
-pub fn parse_engine_choice(engine_str: &str) -> EngineChoice {
-    match engine_str {
-        "builtin" => EngineChoice::Builtin,
-        "custom" => EngineChoice::Custom,
-        "dfa" => EngineChoice::Dfa,
-        "hybrid" => EngineChoice::Hybrid,
-        "onepass" => EngineChoice::Onepass,
-        "boundedbacktracker" => EngineChoice::Boundedbacktracker,
-        "pikevm" => EngineChoice::Pikevm,
-        "meta" => EngineChoice::Meta,
-        "custommeta" => EngineChoice::Custommeta,
-        _ => EngineChoice::Builtin,
+/// Structured result of [`apply_pattern`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchOutcome {
+    /// Byte ranges of every match, in the order the engine reports them.
+    Matches(Vec<Range<usize>>),
+    NoMatches,
+    /// The pattern failed to compile; holds the engine's error message.
+    InvalidPattern(String),
+    /// The engine is still a placeholder and was not run.
+    NotImplemented,
+}
+
+impl MatchOutcome {
+    pub(crate) fn from_ranges(ranges: Vec<Range<usize>>) -> Self {
+        if ranges.is_empty() {
+            MatchOutcome::NoMatches
+        } else {
+            MatchOutcome::Matches(ranges)
+        }
+    }
+
+    /// How the outcome reads in the CLI and TUI, e.g. `Matches: ["ab"]` or
+    /// `Invalid pattern: ...`. `text` is the text the ranges index into.
+    pub fn describe(&self, text: &str) -> String {
+        self.describe_limited(text, None)
+    }
+
+    /// `describe` keeping the first `max_matches` matches and noting when there
+    /// were more.
+    pub(crate) fn describe_limited(&self, text: &str, max_matches: Option<usize>) -> String {
+        match self {
+            MatchOutcome::Matches(ranges) => {
+                let found: Vec<&str> = ranges.iter().map(|r| &text[r.clone()]).collect();
+                format_limited(found, max_matches)
+            }
+            MatchOutcome::NoMatches => format_matches(&[]),
+            MatchOutcome::InvalidPattern(e) => format!("Invalid pattern: {}", e),
+            MatchOutcome::NotImplemented => "Not evaluated (engine not implemented)".to_string(),
+        }
+    }
+}
+
+/// Every engine name accepted by `--engine`, in the order they are listed in `--help`.
+pub const ENGINE_NAMES: [&str; 9] = [
+    "builtin",
+    "custom",
+    "dfa",
+    "hybrid",
+    "onepass",
+    "boundedbacktracker",
+    "pikevm",
+    "meta",
+    "custommeta",
+];
+
+impl EngineChoice {
+    /// The `--engine` name of this engine.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EngineChoice::Builtin => "builtin",
+            EngineChoice::Custom => "custom",
+            EngineChoice::Dfa => "dfa",
+            EngineChoice::Hybrid => "hybrid",
+            EngineChoice::Onepass => "onepass",
+            EngineChoice::Boundedbacktracker => "boundedbacktracker",
+            EngineChoice::Pikevm => "pikevm",
+            EngineChoice::Meta => "meta",
+            EngineChoice::Custommeta => "custommeta",
+        }
+    }
+
+    /// The engine after this one in `ENGINE_NAMES` order, wrapping around at the end.
+    pub fn next(&self) -> EngineChoice {
+        let index = ENGINE_NAMES
+            .iter()
+            .position(|name| *name == self.name())
+            .unwrap_or(0);
+        known_engine(ENGINE_NAMES[(index + 1) % ENGINE_NAMES.len()])
+    }
+}
+
+/// What reported match positions count (`--offset-unit`): UTF-8 bytes, as the
+/// regex crate reports them, or characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OffsetUnit {
+    #[default]
+    Bytes,
+    Chars,
+}
+
+impl OffsetUnit {
+    /// `range`, a byte range into `text`, counted in this unit.
+    pub fn convert(self, text: &str, range: &Range<usize>) -> Range<usize> {
+        match self {
+            OffsetUnit::Bytes => range.clone(),
+            OffsetUnit::Chars => char_offset(text, range.start)..char_offset(text, range.end),
+        }
+    }
+}
+
+/// How many characters of `text` start before byte `offset`.
+fn char_offset(text: &str, offset: usize) -> usize {
+    text.char_indices().take_while(|&(i, _)| i < offset).count()
+}
+
+impl FromStr for OffsetUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(OffsetUnit::Bytes),
+            "chars" => Ok(OffsetUnit::Chars),
+            _ => Err(format!(
+                "Unknown offset unit '{}'. Valid values: bytes, chars",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for EngineChoice {
+    type Err = String;
+
+    /// Parses an `--engine` name, ignoring ASCII case. Unknown names are an
+    /// error listing the valid ones rather than falling back to `builtin`.
+    fn from_str(engine_str: &str) -> Result<Self, Self::Err> {
+        match engine_str.to_ascii_lowercase().as_str() {
+            "builtin" => Ok(EngineChoice::Builtin),
+            "custom" => Ok(EngineChoice::Custom),
+            "dfa" => Ok(EngineChoice::Dfa),
+            "hybrid" => Ok(EngineChoice::Hybrid),
+            "onepass" => Ok(EngineChoice::Onepass),
+            "boundedbacktracker" => Ok(EngineChoice::Boundedbacktracker),
+            "pikevm" => Ok(EngineChoice::Pikevm),
+            "meta" => Ok(EngineChoice::Meta),
+            "custommeta" => Ok(EngineChoice::Custommeta),
+            _ => Err(format!(
+                "Unknown engine '{}'. Valid engines: {}",
+                engine_str,
+                ENGINE_NAMES.join(", ")
+            )),
+        }
     }
 }
 
-pub fn apply_pattern(pattern: &str, text: &str, engine_choice: &EngineChoice) -> String {
+/// Looks up a name from `ENGINE_NAMES`, which always parses.
+fn known_engine(name: &str) -> EngineChoice {
+    name.parse().expect("ENGINE_NAMES only lists valid engines")
+}
+
+/// What an engine can do, for `--engine-info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineInfo {
+    /// False for placeholders that only echo their name.
+    pub implemented: bool,
+    /// Reports capture groups (`--groups`).
+    pub groups: bool,
+    /// Unicode-aware classes and case folding, not only non-ASCII literals.
+    pub unicode: bool,
+    pub description: &'static str,
+}
+
+/// The capability registry: one entry per engine.
+pub fn engine_info(engine_choice: &EngineChoice) -> EngineInfo {
+    const PLACEHOLDER: EngineInfo = EngineInfo {
+        implemented: false,
+        groups: false,
+        unicode: false,
+        description: "Placeholder; results are not evaluated",
+    };
     match engine_choice {
-        EngineChoice::Builtin => apply_pattern_builtin(pattern, text),
-        EngineChoice::Custom => apply_pattern_custom(pattern, text),
-        EngineChoice::Dfa => apply_pattern_builtin(pattern, text),
-        EngineChoice::Hybrid => "Hybrid (placeholder)".to_string(),
-        EngineChoice::Meta => "Meta (placeholder)".to_string(),
-        EngineChoice::Onepass => "One-pass (placeholder)".to_string(),
-        EngineChoice::Boundedbacktracker => "Bounded backtracking (placeholder)".to_string(),
-        EngineChoice::Pikevm => "Pikevm (placeholder)".to_string(),
-        EngineChoice::Custommeta => apply_pattern_custommeta(pattern, text),
+        EngineChoice::Builtin => EngineInfo {
+            implemented: true,
+            groups: true,
+            unicode: true,
+            description: "The regex crate's Regex",
+        },
+        EngineChoice::Custom => EngineInfo {
+            implemented: true,
+            groups: false,
+            unicode: false,
+            description: "Hand-written parser, Thompson NFA and minimized DFA",
+        },
+        EngineChoice::Dfa => EngineInfo {
+            implemented: true,
+            groups: false,
+            unicode: true,
+            description: "Currently runs the builtin engine",
+        },
+        EngineChoice::Custommeta => EngineInfo {
+            implemented: true,
+            groups: false,
+            unicode: true,
+            description: "Custom engine checked against builtin, falling back to it",
+        },
+        EngineChoice::Hybrid
+        | EngineChoice::Meta
+        | EngineChoice::Onepass
+        | EngineChoice::Boundedbacktracker
+        | EngineChoice::Pikevm => PLACEHOLDER,
     }
 }
 
-fn apply_pattern_builtin(pattern: &str, text: &str) -> String {
-    let regex = match Regex::new(pattern) {
-        Ok(r) => r,
-        Err(e) => return format!("Invalid pattern: {}", e),
+/// Formats the registry as a table with one row per engine, in `ENGINE_NAMES` order.
+pub fn engine_info_table() -> String {
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    let mut table = vec![format!(
+        "{:<20} {:<12} {:<7} {:<8} {}",
+        "engine", "implemented", "groups", "unicode", "description"
+    )];
+    for name in ENGINE_NAMES {
+        let info = engine_info(&known_engine(name));
+        table.push(format!(
+            "{:<20} {:<12} {:<7} {:<8} {}",
+            name,
+            yes_no(info.implemented),
+            yes_no(info.groups),
+            yes_no(info.unicode),
+            info.description
+        ));
+    }
+    table.join("\n")
+}
+
+/// Returns false for engines that are still placeholders and only echo their name.
+pub fn is_implemented(engine_choice: &EngineChoice) -> bool {
+    engine_info(engine_choice).implemented
+}
+
+/// Guards against searches that would take too long (`--max-matches`,
+/// `--max-input-bytes`, `--max-steps`, `--max-dfa-states`). The default
+/// imposes no limits beyond `DEFAULT_MAX_DFA_STATES`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    /// Stop searching once this many matches have been found.
+    pub max_matches: Option<usize>,
+    /// Refuse to search inputs longer than this many bytes.
+    pub max_input_bytes: Option<usize>,
+    /// Interrupt a custom engine search after this many DFA steps.
+    pub max_steps: Option<usize>,
+    /// Reject custom engine patterns whose DFA needs more states than this.
+    pub max_dfa_states: Option<usize>,
+}
+
+impl SearchLimits {
+    /// The DFA state limit the custom engine compiles with.
+    pub fn dfa_states(&self) -> usize {
+        self.max_dfa_states.unwrap_or(DEFAULT_MAX_DFA_STATES)
+    }
+
+    /// Returns the guard message when `text` is over `max_input_bytes`.
+    pub fn check_input(&self, text: &str) -> Result<(), String> {
+        self.check_len(text.len())
+    }
+
+    /// Like `check_input`, for input that has not been read yet.
+    pub fn check_len(&self, len: usize) -> Result<(), String> {
+        match self.max_input_bytes {
+            Some(max) if len > max => Err(format!(
+                "Input too large: {} bytes exceeds --max-input-bytes {}",
+                len, max
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Runs `pattern` over `text` with the engine and reports the match ranges,
+/// or why there are none.
+pub fn apply_pattern(pattern: &str, text: &str, engine_choice: &EngineChoice) -> MatchOutcome {
+    outcome(pattern, text, engine_choice, &SearchLimits::default(), None)
+}
+
+/// `apply_pattern` collecting at most `limit` matches.
+fn outcome(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    limit: Option<usize>,
+) -> MatchOutcome {
+    let ranges = match engine_choice {
+        EngineChoice::Builtin | EngineChoice::Dfa => Regex::new(pattern)
+            .map(|regex| builtin_limited(&regex, text, limit))
+            .map_err(|e| e.to_string()),
+        EngineChoice::Custom => compile_custom(pattern, limits)
+            .map(|cr| cr.find_ranges_up_to(text, limit))
+            .map_err(|e| e.to_string()),
+        EngineChoice::Custommeta => custommeta_ranges(pattern, text, limits, limit),
+        EngineChoice::Hybrid
+        | EngineChoice::Meta
+        | EngineChoice::Onepass
+        | EngineChoice::Boundedbacktracker
+        | EngineChoice::Pikevm => return MatchOutcome::NotImplemented,
     };
-    let mut all_matches = Vec::new();
-    for mat in regex.find_iter(text) {
-        all_matches.push(mat.as_str().to_string());
+    match ranges {
+        Ok(ranges) => MatchOutcome::from_ranges(ranges),
+        Err(e) => MatchOutcome::InvalidPattern(e),
     }
-    if all_matches.is_empty() {
-        "No matches found.".to_string()
+}
+
+/// `apply_pattern` as the CLI prints it, with search limits. An input over
+/// `max_input_bytes` returns the guard message without running the engine, and
+/// placeholder engines only echo their name.
+pub fn apply_pattern_limited(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    let max_matches = limits.max_matches;
+    if let (EngineChoice::Custom, Some(max_steps)) = (engine_choice, limits.max_steps) {
+        let ranges = compile_custom(pattern, limits)
+            .map_err(|e| MatchOutcome::InvalidPattern(e.to_string()).describe(text))
+            .and_then(|custom| {
+                custom
+                    .find_ranges_budgeted(text, probe_limit(max_matches), max_steps)
+                    .map_err(|e| e.to_string())
+            });
+        return match ranges {
+            Ok(ranges) => MatchOutcome::from_ranges(ranges).describe_limited(text, max_matches),
+            Err(message) => message,
+        };
+    }
+    outcome(
+        pattern,
+        text,
+        engine_choice,
+        limits,
+        probe_limit(max_matches),
+    )
+    .describe_limited(text, max_matches)
+}
+
+/// What the CLI prints instead of running a placeholder engine.
+fn placeholder_message(engine_choice: &EngineChoice) -> String {
+    match engine_choice {
+        EngineChoice::Hybrid => "Hybrid (placeholder)",
+        EngineChoice::Meta => "Meta (placeholder)",
+        EngineChoice::Onepass => "One-pass (placeholder)",
+        EngineChoice::Boundedbacktracker => "Bounded backtracking (placeholder)",
+        EngineChoice::Pikevm => "Pikevm (placeholder)",
+        EngineChoice::Builtin
+        | EngineChoice::Custom
+        | EngineChoice::Dfa
+        | EngineChoice::Custommeta => unreachable!("only placeholder engines are skipped"),
+    }
+    .to_string()
+}
+
+/// Like `apply_pattern_limited`, but lists each match with its offsets in
+/// `unit`, e.g. `Matches: ["abc"@[0..3]]`, for `--show-positions`.
+pub fn apply_pattern_with_positions(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    unit: OffsetUnit,
+) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    if let Err(e) = validate(pattern, engine_choice, limits) {
+        return format!("Invalid pattern: {}", e);
+    }
+    format_positions(
+        text,
+        match_ranges(
+            pattern,
+            text,
+            engine_choice,
+            limits,
+            probe_limit(limits.max_matches),
+        ),
+        limits,
+        unit,
+    )
+}
+
+/// Reports non-overlapping matches for `--match-semantics`, with offsets when
+/// `show_positions` is set. The custom engine honors both semantics; the
+/// builtin engine is always leftmost-first, so asking it for leftmost-longest
+/// is an error. custommeta uses the custom engine unless it rejects the pattern.
+pub fn apply_pattern_semantics(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    semantics: MatchSemantics,
+    limits: &SearchLimits,
+    show_positions: bool,
+    unit: OffsetUnit,
+) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    let custom = match engine_choice {
+        EngineChoice::Custom => match compile_custom(pattern, limits) {
+            Ok(custom) => Some(custom),
+            Err(e) => return MatchOutcome::InvalidPattern(e.to_string()).describe(text),
+        },
+        EngineChoice::Custommeta => compile_custom(pattern, limits).ok(),
+        _ => None,
+    };
+    let limit = probe_limit(limits.max_matches);
+    let ranges = match custom {
+        Some(custom) => custom.find_iter_with_up_to(text, semantics, limit),
+        None if semantics == MatchSemantics::LeftmostLongest => {
+            return format!(
+                "Engine {} only supports leftmost-first matching",
+                engine_choice.name()
+            )
+        }
+        None => match Regex::new(pattern) {
+            Ok(regex) => builtin_limited(&regex, text, limit),
+            Err(e) => return MatchOutcome::InvalidPattern(e.to_string()).describe(text),
+        },
+    };
+    if show_positions {
+        format_positions(text, ranges, limits, unit)
     } else {
-        format!("Matches: {:?}", all_matches)
+        MatchOutcome::from_ranges(ranges).describe_limited(text, limits.max_matches)
     }
 }
 
-fn apply_pattern_custom(pattern: &str, text: &str) -> String {
-    let cr = CustomRegex::new(pattern);
-    match cr {
-        Ok(parser) => {
-            let all_matches = parser.find_iter(text);
-            if all_matches.is_empty() {
-                "No matches found.".to_string()
-            } else {
-                format!("Matches: {:?}", all_matches)
+/// Reports every match for `--overlapping`, including ones that overlap, in
+/// start order with their offsets in `unit`.
+pub fn apply_pattern_overlapping(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    unit: OffsetUnit,
+) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    if let Err(e) = validate(pattern, engine_choice, limits) {
+        return format!("Invalid pattern: {}", e);
+    }
+    format_positions(
+        text,
+        overlapping_ranges(
+            pattern,
+            text,
+            engine_choice,
+            limits,
+            probe_limit(limits.max_matches),
+        ),
+        limits,
+        unit,
+    )
+}
+
+/// Plain substring search for `--fixed`: every non-overlapping occurrence of
+/// `needle`, metacharacters and all, with offsets in `unit`. It is the same
+/// scan whichever engine is selected.
+pub fn apply_fixed(needle: &str, text: &str, limits: &SearchLimits, unit: OffsetUnit) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    format_positions(text, fixed_ranges(needle, text), limits, unit)
+}
+
+/// Byte ranges of every non-overlapping occurrence of `needle` in `text`.
+pub fn fixed_ranges(needle: &str, text: &str) -> Vec<Range<usize>> {
+    text.match_indices(needle)
+        .map(|(start, found)| start..start + found.len())
+        .collect()
+}
+
+/// One match per start position, ordered by start: the shortest non-empty
+/// match beginning there, whichever engine runs, stopping after `limit`. The
+/// custom engine walks its DFA; the regex engines take the first non-empty end
+/// an anchored overlapping search of the lazy DFA reports.
+pub fn overlapping_ranges(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    limit: Option<usize>,
+) -> Vec<Range<usize>> {
+    let custom = match engine_choice {
+        EngineChoice::Custom | EngineChoice::Custommeta => compile_custom(pattern, limits).ok(),
+        _ => None,
+    };
+    if let Some(custom) = custom {
+        return custom.find_overlapping_up_to(text, limit);
+    }
+    let config = hybrid::dfa::Config::new()
+        .match_kind(MatchKind::All)
+        .unicode_word_boundary(true);
+    let Ok(dfa) = hybrid::dfa::DFA::builder().configure(config).build(pattern) else {
+        return Vec::new();
+    };
+    let mut cache = dfa.create_cache();
+    let mut fallback = None;
+    text.char_indices()
+        .filter_map(|(start, _)| {
+            match shortest_nonempty_at(&dfa, &mut cache, text, start) {
+                Ok(end) => end,
+                // The lazy DFA gives up on a Unicode `\b` next to non-ASCII text.
+                Err(_) => shortest_nonempty_by_span(
+                    fallback
+                        .get_or_insert_with(|| all_kind_regex(pattern))
+                        .as_ref()?,
+                    text,
+                    start,
+                ),
             }
+            .map(|end| start..end)
+        })
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// The end of the shortest non-empty match starting at `start`: the first end
+/// past `start` that an anchored overlapping search reports.
+fn shortest_nonempty_at(
+    dfa: &hybrid::dfa::DFA,
+    cache: &mut hybrid::dfa::Cache,
+    text: &str,
+    start: usize,
+) -> Result<Option<usize>, MatchError> {
+    let input = Input::new(text).range(start..).anchored(Anchored::Yes);
+    let mut state = OverlappingState::start();
+    loop {
+        dfa.try_search_overlapping_fwd(cache, &input, &mut state)?;
+        match state.get_match() {
+            None => return Ok(None),
+            Some(half) if half.offset() > start => return Ok(Some(half.offset())),
+            Some(_) => {}
+        }
+    }
+}
+
+/// `pattern` reporting its longest match, for `shortest_nonempty_by_span`.
+fn all_kind_regex(pattern: &str) -> Option<meta::Regex> {
+    meta::Regex::builder()
+        .configure(meta::Config::new().match_kind(MatchKind::All))
+        .build(pattern)
+        .ok()
+}
+
+/// `shortest_nonempty_at` for the patterns the lazy DFA cannot search: the
+/// first span `start..end` that holds a non-empty anchored match. Slower, as
+/// it searches once per end.
+fn shortest_nonempty_by_span(regex: &meta::Regex, text: &str, start: usize) -> Option<usize> {
+    (start + 1..=text.len())
+        .filter(|&end| text.is_char_boundary(end))
+        .find(|&end| {
+            let input = Input::new(text).range(start..end).anchored(Anchored::Yes);
+            regex.find(input).is_some_and(|m| !m.is_empty())
+        })
+}
+
+/// Formats `ranges` as `Matches: ["ab"@[0..2], ...]`, applying `--max-matches`.
+fn format_positions(
+    text: &str,
+    ranges: Vec<Range<usize>>,
+    limits: &SearchLimits,
+    unit: OffsetUnit,
+) -> String {
+    let truncated = limits.max_matches.is_some_and(|max| ranges.len() > max);
+    let positions: Vec<String> = ranges
+        .into_iter()
+        .take(limits.max_matches.unwrap_or(usize::MAX))
+        .map(|range| {
+            let offsets = unit.convert(text, &range);
+            format!("{:?}@[{}..{}]", &text[range], offsets.start, offsets.end)
+        })
+        .collect();
+    let result = if positions.is_empty() {
+        "No matches found.".to_string()
+    } else {
+        format!("Matches: [{}]", positions.join(", "))
+    };
+    if truncated {
+        format!("{} ... (truncated)", result)
+    } else {
+        result
+    }
+}
+
+/// The leftmost match only, for `--first`: the search stops at the first hit.
+/// The outcome holds at most one range.
+pub fn first_match(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> MatchOutcome {
+    if !is_implemented(engine_choice) {
+        return MatchOutcome::NotImplemented;
+    }
+    let custom = match engine_choice {
+        EngineChoice::Custom => match compile_custom(pattern, limits) {
+            Ok(custom) => Some(custom),
+            Err(e) => return MatchOutcome::InvalidPattern(e.to_string()),
+        },
+        // custommeta falls back to builtin whenever CustomRegex rejects a pattern.
+        EngineChoice::Custommeta => compile_custom(pattern, limits).ok(),
+        _ => None,
+    };
+    let first = match custom {
+        Some(custom) => custom.find(text).map(|(start, end)| start..end),
+        None => match Regex::new(pattern) {
+            Ok(regex) => regex.find(text).map(|m| m.range()),
+            Err(e) => return MatchOutcome::InvalidPattern(e.to_string()),
+        },
+    };
+    MatchOutcome::from_ranges(first.into_iter().collect())
+}
+
+/// Whether `pattern` matches anywhere in `text`, for `--quiet`. Stops at the
+/// first match; `Err` holds the message to print for an invalid pattern or
+/// an engine that is not implemented yet.
+pub fn is_match(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> Result<bool, String> {
+    Ok(line_matcher(pattern, engine_choice, limits)?.is_match(text))
+}
+
+/// Compiles `pattern` once for matching many texts, as `-n`, `-v`, `-c` and
+/// `--quiet` do. `Err` holds the message to print for an invalid pattern or an
+/// engine that is not implemented yet. custommeta falls back to builtin
+/// whenever CustomRegex rejects the pattern.
+fn line_matcher(
+    pattern: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> Result<Regexer, String> {
+    if !is_implemented(engine_choice) {
+        return Err(MatchOutcome::NotImplemented.describe(""));
+    }
+    RegexerBuilder::new(pattern)
+        .engine(*engine_choice)
+        .max_dfa_states(limits.dfa_states())
+        .build()
+        .map_err(|e| MatchOutcome::InvalidPattern(e).describe(""))
+}
+
+/// Reports only the final match for `--last`, e.g. `Last match: "a"@[2..3]`.
+pub fn apply_pattern_last(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    unit: OffsetUnit,
+) -> String {
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    if let Err(e) = validate(pattern, engine_choice, limits) {
+        return format!("Invalid pattern: {}", e);
+    }
+    let last = match engine_choice {
+        EngineChoice::Custom => compile_custom(pattern, limits)
+            .ok()
+            .and_then(|cr| cr.rfind(text))
+            .map(|(start, end)| start..end),
+        // The regex crate has no reverse search over all matches, so take the
+        // last of them.
+        _ => match_ranges(pattern, text, engine_choice, limits, None).pop(),
+    };
+    match last {
+        Some(range) => {
+            let offsets = unit.convert(text, &range);
+            format!(
+                "Last match: {:?}@[{}..{}]",
+                &text[range], offsets.start, offsets.end
+            )
         }
-        Err(e) => format!("Invalid pattern: {}", e),
-    }
-}
-
-fn apply_pattern_custommeta(pattern: &str, text: &str) -> String {
-    let cr = CustomRegex::new(pattern);
-    match cr {
-        Ok(parser) => {
-            let custom_matches = parser.find_iter(text);
-            if custom_matches.is_empty() {
-                eprintln!("customMeta: CustomRegex no matches, verify builtin.");
-                let builtin_result = apply_pattern_builtin(pattern, text);
-                if builtin_result.contains("No matches found")
-                    || builtin_result.contains("Invalid pattern")
-                {
-                    eprintln!("customMeta: Builtin also failed.");
-                    return builtin_result;
-                } else {
-                    // Return custom (no matches found) anyway:
-                    return "No matches found.".to_string();
+        None => "No matches found.".to_string(),
+    }
+}
+
+/// Prefixes the inline flags for `--multiline` (`(?m)`: `^` and `$` match at
+/// line boundaries) and `--dotall` (`(?s)`: `.` matches `\n`) in the builtin
+/// and regex-automata engines. The custom engine accepts both flags but has no
+/// anchors or `.` for them to change.
+pub fn pattern_with_flags(
+    pattern: &str,
+    multiline: bool,
+    dotall: bool,
+    ignore_case: bool,
+) -> String {
+    let flags: String = [(ignore_case, 'i'), (multiline, 'm'), (dotall, 's')]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect();
+    if flags.is_empty() {
+        return pattern.to_string();
+    }
+    format!("(?{}){}", flags, pattern)
+}
+
+/// Lists the matches as CSV for `--csv`: a `index,match,start,end` header, then
+/// one row per match with offsets in `unit`. `Err` holds the message to print
+/// instead, so it never ends up in the CSV. `--max-matches N` keeps the first
+/// N rows and, unlike the other outputs, adds no "... (truncated)" note, which
+/// would not be a valid row.
+pub fn matches_csv(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    unit: OffsetUnit,
+) -> Result<String, String> {
+    limits.check_input(text)?;
+    if !is_implemented(engine_choice) {
+        return Err(placeholder_message(engine_choice));
+    }
+    validate(pattern, engine_choice, limits).map_err(|e| format!("Invalid pattern: {}", e))?;
+    let mut rows = vec!["index,match,start,end".to_string()];
+    for (i, range) in match_ranges(pattern, text, engine_choice, limits, limits.max_matches)
+        .into_iter()
+        .enumerate()
+    {
+        let offsets = unit.convert(text, &range);
+        rows.push(format!(
+            "{},{},{},{}",
+            i,
+            csv_field(&text[range]),
+            offsets.start,
+            offsets.end
+        ));
+    }
+    Ok(rows.join("\n"))
+}
+
+/// A `--format` template such as `{index}: {match} @{start}`, rendered once per
+/// match. `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchTemplate {
+    pieces: Vec<TemplatePiece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePiece {
+    Literal(String),
+    /// The matched text.
+    Match,
+    /// Byte offset where the match starts.
+    Start,
+    /// Byte offset just past the match.
+    End,
+    /// Zero-based position in the list of matches, as in `--csv`.
+    Index,
+}
+
+impl MatchTemplate {
+    /// Parses a template, rejecting unknown placeholders and unbalanced braces.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
                 }
-            } else {
-                let builtin_result = apply_pattern_builtin(pattern, text);
-                if builtin_result.contains("Invalid pattern") {
-                    eprintln!("customMeta: fallback to builtin invalid pattern.");
-                    return builtin_result;
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(
+                            "Unclosed '{' in template; write '{{' for a literal brace".to_string()
+                        );
+                    }
+                    let piece = match name.as_str() {
+                        "match" => TemplatePiece::Match,
+                        "start" => TemplatePiece::Start,
+                        "end" => TemplatePiece::End,
+                        "index" => TemplatePiece::Index,
+                        _ => {
+                            return Err(format!(
+                                "Unknown placeholder '{{{}}}'; expected {{match}}, {{start}}, {{end}} or {{index}}",
+                                name
+                            ))
+                        }
+                    };
+                    if !literal.is_empty() {
+                        pieces.push(TemplatePiece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(piece);
                 }
+                '}' => {
+                    return Err(
+                        "Unmatched '}' in template; write '}}' for a literal brace".to_string()
+                    )
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(TemplatePiece::Literal(literal));
+        }
+        Ok(MatchTemplate { pieces })
+    }
+
+    /// Expands the template for the `index`th match, found at `range` in `text`,
+    /// with `{start}` and `{end}` counted in `unit`.
+    pub fn render(
+        &self,
+        index: usize,
+        text: &str,
+        range: Range<usize>,
+        unit: OffsetUnit,
+    ) -> String {
+        let offsets = unit.convert(text, &range);
+        self.pieces
+            .iter()
+            .map(|piece| match piece {
+                TemplatePiece::Literal(literal) => literal.clone(),
+                TemplatePiece::Match => text[range.clone()].to_string(),
+                TemplatePiece::Start => offsets.start.to_string(),
+                TemplatePiece::End => offsets.end.to_string(),
+                TemplatePiece::Index => index.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Renders every match through `template` for `--format`, one per line.
+/// `--max-matches N` renders the first N only, with no "... (truncated)"
+/// note, so every line is one the template produced.
+pub fn matches_formatted(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    template: &MatchTemplate,
+    unit: OffsetUnit,
+) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    if let Err(e) = validate(pattern, engine_choice, limits) {
+        return format!("Invalid pattern: {}", e);
+    }
+    let lines: Vec<String> = match_ranges(pattern, text, engine_choice, limits, limits.max_matches)
+        .into_iter()
+        .enumerate()
+        .map(|(i, range)| template.render(i, text, range, unit))
+        .collect();
+    if lines.is_empty() {
+        "No matches found.".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
 
-                if builtin_result.contains("No matches found") {
-                    eprintln!("customMeta: mismatch between custom and builtin. Using builtin.");
-                    return builtin_result;
+/// Counts each distinct matched string for `--unique`, like `uniq -c`: one
+/// `count match` line each, most frequent first and ties in string order.
+/// `--max-matches N` counts the first N matches only and ends with a
+/// "... (truncated)" line when there were more.
+pub fn unique_matches(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    if !is_implemented(engine_choice) {
+        return placeholder_message(engine_choice);
+    }
+    if let Err(e) = validate(pattern, engine_choice, limits) {
+        return format!("Invalid pattern: {}", e);
+    }
+    let ranges = match_ranges(
+        pattern,
+        text,
+        engine_choice,
+        limits,
+        probe_limit(limits.max_matches),
+    );
+    let truncated = limits.max_matches.is_some_and(|max| ranges.len() > max);
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for range in ranges
+        .into_iter()
+        .take(limits.max_matches.unwrap_or(usize::MAX))
+    {
+        *counts.entry(&text[range]).or_default() += 1;
+    }
+    if counts.is_empty() {
+        return "No matches found.".to_string();
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    let mut lines: Vec<String> = counts
+        .iter()
+        .map(|(matched, count)| format!("{:>7} {:?}", count, matched))
+        .collect();
+    if truncated {
+        lines.push("... (truncated)".to_string());
+    }
+    lines.join("\n")
+}
+
+/// Splits `text` on every match of `pattern` for `--split`, printed one per line,
+/// as `Regex::split` does: a delimiter at either end gives an empty first or
+/// last field, and consecutive delimiters give an empty field between them.
+/// `--max-matches N` splits on the first N delimiters only, leaving the rest
+/// in the last field. Builtin engine only. `Err` holds the message to print
+/// for an invalid pattern or an input over `max_input_bytes`.
+pub fn split_fields<'a>(
+    pattern: &str,
+    text: &'a str,
+    limits: &SearchLimits,
+) -> Result<Vec<&'a str>, String> {
+    limits.check_input(text)?;
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+    Ok(match limits.max_matches {
+        Some(max) => regex.splitn(text, max.saturating_add(1)).collect(),
+        None => regex.split(text).collect(),
+    })
+}
+
+/// Quotes a CSV field per RFC 4180 when it holds a comma, quote or line break,
+/// doubling any quotes inside.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `text` after a replace, with where each replacement landed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replaced {
+    pub text: String,
+    /// Byte ranges of the inserted replacements in `text`.
+    pub replaced: Vec<Range<usize>>,
+}
+
+/// Replaces every match of `pattern` with `replacement`, taken literally, for
+/// the TUI's replace preview. Fails with the compile error, or when the engine
+/// is a placeholder.
+pub fn replace_matches(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    replacement: &str,
+) -> Result<Replaced, String> {
+    if !is_implemented(engine_choice) {
+        return Err(format!(
+            "Engine {} is not implemented",
+            engine_choice.name()
+        ));
+    }
+    let limits = SearchLimits::default();
+    validate(pattern, engine_choice, &limits)?;
+    Ok(replace_ranges(
+        text,
+        &match_ranges(pattern, text, engine_choice, &limits, None),
+        replacement,
+    ))
+}
+
+/// Replaces `ranges` of `text` with `replacement`. Overlapping ranges (the
+/// custom engine reports every accepted substring) are resolved leftmost-longest,
+/// and like the regex crate, an empty match right after a replaced one is
+/// skipped.
+pub fn replace_ranges(text: &str, ranges: &[Range<usize>], replacement: &str) -> Replaced {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+    let mut result = Replaced {
+        text: String::with_capacity(text.len()),
+        replaced: Vec::new(),
+    };
+    let mut pos = 0;
+    let mut last_end = None;
+    for range in sorted {
+        if range.start < pos || (range.is_empty() && last_end == Some(range.start)) {
+            continue;
+        }
+        result.text.push_str(&text[pos..range.start]);
+        let start = result.text.len();
+        result.text.push_str(replacement);
+        result.replaced.push(start..result.text.len());
+        pos = range.end;
+        last_end = Some(range.end);
+    }
+    result.text.push_str(&text[pos..]);
+    result
+}
+
+/// Sorts match ranges and merges the overlapping ones (the custom engine reports
+/// every accepted substring). Empty ranges are dropped since there is nothing
+/// to highlight.
+pub fn merge_ranges(ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut sorted: Vec<Range<usize>> = ranges.iter().filter(|r| !r.is_empty()).cloned().collect();
+    sorted.sort_by_key(|r| r.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in sorted {
+        match merged.last_mut() {
+            Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Wraps the matched `ranges` of `text` in ANSI color codes for `--color`.
+pub fn colorize(text: &str, ranges: &[Range<usize>]) -> String {
+    let mut colored = String::new();
+    let mut pos = 0;
+    for range in merge_ranges(ranges) {
+        colored.push_str(&text[pos..range.start]);
+        colored.push_str("\x1b[1;31m");
+        colored.push_str(&text[range.clone()]);
+        colored.push_str("\x1b[0m");
+        pos = range.end;
+    }
+    colored.push_str(&text[pos..]);
+    colored
+}
+
+/// Every engine that is not a placeholder, with its `--engine` name.
+fn implemented_engines() -> impl Iterator<Item = (&'static str, EngineChoice)> {
+    ENGINE_NAMES
+        .iter()
+        .map(|name| (*name, known_engine(name)))
+        .filter(|(_, engine_choice)| is_implemented(engine_choice))
+}
+
+/// Min/median/max wall-clock time of one engine over `--bench-iterations` runs.
+pub struct BenchRow {
+    pub engine: &'static str,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+/// Runs every implemented engine `iterations` times on the same input for `--bench`.
+pub fn bench_engines(pattern: &str, text: &str, iterations: usize) -> Vec<BenchRow> {
+    let iterations = iterations.max(1);
+    implemented_engines()
+        .map(|(name, engine_choice)| {
+            let mut times: Vec<Duration> = (0..iterations)
+                .map(|_| timed(|| apply_pattern(pattern, text, &engine_choice)).1)
+                .collect();
+            times.sort();
+            BenchRow {
+                engine: name,
+                min: times[0],
+                median: times[times.len() / 2],
+                max: times[times.len() - 1],
+            }
+        })
+        .collect()
+}
+
+/// Formats `--bench` results as a table with one row per engine.
+pub fn bench_table(rows: &[BenchRow]) -> String {
+    let ms = |d: Duration| format!("{:.3}ms", d.as_secs_f64() * 1000.0);
+    let mut table = vec![format!(
+        "{:<12} {:>12} {:>12} {:>12}",
+        "engine", "min", "median", "max"
+    )];
+    for row in rows {
+        table.push(format!(
+            "{:<12} {:>12} {:>12} {:>12}",
+            row.engine,
+            ms(row.min),
+            ms(row.median),
+            ms(row.max)
+        ));
+    }
+    table.join("\n")
+}
+
+/// Decodes `bytes` from `encoding`, for `--input-encoding`. Fails at the first
+/// malformed sequence, naming its byte offset, instead of replacing it.
+pub fn decode(bytes: &[u8], encoding: &'static Encoding) -> Result<String, String> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(bytes.len());
+    let mut offset = 0;
+    loop {
+        let (result, read) =
+            decoder.decode_to_string_without_replacement(&bytes[offset..], &mut text, true);
+        offset += read;
+        match result {
+            DecoderResult::InputEmpty => return Ok(text),
+            DecoderResult::OutputFull => text.reserve(bytes.len() - offset + 4),
+            DecoderResult::Malformed(bad, after) => {
+                return Err(format!(
+                    "invalid {} at byte {}",
+                    encoding.name(),
+                    offset - after as usize - bad as usize
+                ))
+            }
+        }
+    }
+}
+
+/// Reads `path` and decodes it from `encoding`. Bytes that are not valid in
+/// that encoding are reported as `InvalidData`.
+pub fn read_decoded(path: &str, encoding: &'static Encoding) -> io::Result<String> {
+    decode(&std::fs::read(path)?, encoding)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reads a `--patterns-file`: one pattern per line, skipping blank lines and
+/// lines starting with `#`.
+pub fn load_patterns(path: &str) -> std::io::Result<Vec<String>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Reads a `--testcase` fixture: the first line is the pattern and everything
+/// after it is the text, kept verbatim. A file with only a pattern line has
+/// empty text.
+pub fn load_testcase(path: &str) -> std::io::Result<(String, String)> {
+    let contents = std::fs::read_to_string(path)?;
+    let (pattern, text) = contents.split_once('\n').unwrap_or((&contents, ""));
+    let pattern = pattern.strip_suffix('\r').unwrap_or(pattern);
+    Ok((pattern.to_string(), text.to_string()))
+}
+
+/// One `--expect` case: `pattern<TAB>text<TAB>expected_match_count`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectCase {
+    /// Line number in the fixture, for reporting.
+    pub line: usize,
+    pub pattern: String,
+    pub text: String,
+    pub expected: usize,
+}
+
+/// Reads an `--expect` fixture. Blank lines and lines starting with `#` are
+/// skipped; any other line without three tab-separated fields, or whose count
+/// is not a number, is an error.
+pub fn load_expectations(path: &str) -> io::Result<Vec<ExpectCase>> {
+    let invalid = |line: usize, what: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", path, line, what),
+        )
+    };
+    std::fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.strip_suffix('\r').unwrap_or(line)))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [pattern, text, expected] = fields[..] else {
+                return Err(invalid(
+                    line_no,
+                    "expected pattern<TAB>text<TAB>expected_match_count",
+                ));
+            };
+            let expected = expected
+                .trim()
+                .parse()
+                .map_err(|_| invalid(line_no, "the match count must be a number"))?;
+            Ok(ExpectCase {
+                line: line_no,
+                pattern: pattern.to_string(),
+                text: text.to_string(),
+                expected,
+            })
+        })
+        .collect()
+}
+
+/// Runs every case with the engine and reports one `PASS` or `FAIL` line per
+/// case, then a summary. Returns the report and whether every case passed.
+/// Invalid patterns and placeholder engines fail their case.
+pub fn run_expectations(cases: &[ExpectCase], engine_choice: &EngineChoice) -> (String, bool) {
+    let mut lines = Vec::new();
+    let mut failed = 0;
+    for case in cases {
+        let found = match apply_pattern(&case.pattern, &case.text, engine_choice) {
+            MatchOutcome::Matches(ranges) => Ok(ranges.len()),
+            MatchOutcome::NoMatches => Ok(0),
+            MatchOutcome::InvalidPattern(e) => Err(format!("invalid pattern: {}", e)),
+            MatchOutcome::NotImplemented => Err("engine not implemented".to_string()),
+        };
+        let case_name = format!("line {}: {:?} in {:?}", case.line, case.pattern, case.text);
+        lines.push(match found {
+            Ok(count) if count == case.expected => format!("PASS {}", case_name),
+            Ok(count) => {
+                failed += 1;
+                format!(
+                    "FAIL {}: expected {} matches, got {}",
+                    case_name, case.expected, count
+                )
+            }
+            Err(e) => {
+                failed += 1;
+                format!("FAIL {}: {}", case_name, e)
+            }
+        });
+    }
+    lines.push(format!(
+        "{} passed, {} failed ({})",
+        cases.len() - failed,
+        failed,
+        engine_choice.name()
+    ));
+    (lines.join("\n"), failed == 0)
+}
+
+/// Runs each pattern over the same text and prints one section per pattern.
+pub fn apply_patterns(
+    patterns: &[String],
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> String {
+    patterns
+        .iter()
+        .map(|pattern| {
+            format!(
+                "== Pattern: {} ==\n{}",
+                pattern,
+                apply_pattern_limited(pattern, text, engine_choice, limits)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks every pattern against `text` in one pass with a builtin `RegexSet`
+/// (`--set`). Only reports which patterns matched, not where, in the order they
+/// were given.
+pub fn apply_pattern_set(patterns: &[String], text: &str) -> String {
+    let set = match RegexSet::new(patterns) {
+        Ok(set) => set,
+        Err(e) => return format!("Invalid pattern: {}", e),
+    };
+    let matched: Vec<String> = set
+        .matches(text)
+        .into_iter()
+        .map(|i| format!("{}: {}", i, patterns[i]))
+        .collect();
+    if matched.is_empty() {
+        "No patterns matched.".to_string()
+    } else {
+        format!("Matched patterns:\n{}", matched.join("\n"))
+    }
+}
+
+/// One capture group of a match for `--groups`, labelled by its name when it
+/// has one and by its index otherwise. Groups that did not take part in the
+/// match have no range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMatch {
+    pub label: String,
+    pub range: Option<Range<usize>>,
+}
+
+/// The capture groups of every match of `pattern`, using the builtin engine
+/// (the only one with captures). Group 0 is the whole match.
+pub fn capture_groups(
+    pattern: &str,
+    text: &str,
+    max_matches: Option<usize>,
+) -> Result<Vec<Vec<GroupMatch>>, String> {
+    let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+    let labels: Vec<String> = regex
+        .capture_names()
+        .enumerate()
+        .map(|(i, name)| name.map_or_else(|| i.to_string(), str::to_string))
+        .collect();
+    Ok(regex
+        .captures_iter(text)
+        .take(max_matches.unwrap_or(usize::MAX))
+        .map(|caps| {
+            labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| GroupMatch {
+                    label: label.clone(),
+                    range: caps.get(i).map(|m| m.range()),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Lists each match's groups on their own lines, e.g. `  year: "2024"@[0..4]`.
+pub fn format_groups(matches: &[Vec<GroupMatch>], text: &str, unit: OffsetUnit) -> String {
+    if matches.is_empty() {
+        return "No matches found.".to_string();
+    }
+    let mut lines = Vec::new();
+    for (i, groups) in matches.iter().enumerate() {
+        lines.push(format!("Match {}:", i + 1));
+        for group in groups {
+            lines.push(match &group.range {
+                Some(range) => {
+                    let offsets = unit.convert(text, range);
+                    format!(
+                        "  {}: {:?}@[{}..{}]",
+                        group.label,
+                        &text[range.clone()],
+                        offsets.start,
+                        offsets.end
+                    )
                 }
+                None => format!("  {}: (no match)", group.label),
+            });
+        }
+    }
+    lines.join("\n")
+}
+
+/// `--groups --json`: an array with one object per match, mapping each group's
+/// label to `{"text", "start", "end"}`, or to `null` when it did not take part.
+pub fn groups_json(matches: &[Vec<GroupMatch>], text: &str, unit: OffsetUnit) -> String {
+    let matches: Vec<serde_json::Map<String, serde_json::Value>> = matches
+        .iter()
+        .map(|groups| {
+            groups
+                .iter()
+                .map(|group| {
+                    let value = match &group.range {
+                        Some(range) => {
+                            let offsets = unit.convert(text, range);
+                            serde_json::json!({
+                                "text": &text[range.clone()],
+                                "start": offsets.start,
+                                "end": offsets.end,
+                            })
+                        }
+                        None => serde_json::Value::Null,
+                    };
+                    (group.label.clone(), value)
+                })
+                .collect()
+        })
+        .collect();
+    serde_json::to_string_pretty(&matches).expect("JSON values always serialize")
+}
+
+/// `--extract`: one compact JSON object per line (JSON Lines) for each match,
+/// mapping every named group to its text, or to `null` when the group did not
+/// take part. Unnamed groups are left out; a pattern with no named groups is
+/// an error.
+pub fn extract_json(
+    pattern: &str,
+    text: &str,
+    max_matches: Option<usize>,
+) -> Result<String, String> {
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+    let names: Vec<&str> = regex.capture_names().flatten().collect();
+    if names.is_empty() {
+        return Err("--extract needs at least one named group, e.g. (?P<name>...)".to_string());
+    }
+    let lines: Vec<String> = regex
+        .captures_iter(text)
+        .take(max_matches.unwrap_or(usize::MAX))
+        .map(|caps| {
+            let object: serde_json::Map<String, serde_json::Value> = names
+                .iter()
+                .map(|&name| {
+                    let value = caps
+                        .name(name)
+                        .map_or(serde_json::Value::Null, |m| m.as_str().into());
+                    (name.to_string(), value)
+                })
+                .collect();
+            serde_json::Value::Object(object).to_string()
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// Runs every implemented engine on the same input for `--engine all`.
+/// Returns `(engine name, result)` pairs and whether all results agree.
+pub fn compare_engines(pattern: &str, text: &str) -> (Vec<(&'static str, String)>, bool) {
+    let results: Vec<(&'static str, String)> = implemented_engines()
+        .map(|(name, engine_choice)| {
+            (
+                name,
+                apply_pattern(pattern, text, &engine_choice).describe(text),
+            )
+        })
+        .collect();
+    let agreement = results.windows(2).all(|pair| pair[0].1 == pair[1].1);
+    (results, agreement)
+}
 
-                // Both found matches:
-                format!("Matches: {:?}", custom_matches)
+/// Runs `f` and returns its result along with the wall-clock time it took.
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// Formats a `--timing` report line, e.g. `Engine dfa took 1.23ms`.
+pub fn timing_line(engine_name: &str, elapsed: Duration) -> String {
+    format!(
+        "Engine {} took {:.2}ms",
+        engine_name,
+        elapsed.as_secs_f64() * 1000.0
+    )
+}
+
+/// Timing of a `--repeat` run.
+pub struct RepeatTiming {
+    pub iterations: usize,
+    pub per_iteration: Duration,
+    /// Set when `--reuse-compiled` compiled the pattern once, outside the loop.
+    pub compile: Option<Duration>,
+}
+
+/// Matches `pattern` over `text` `iterations` times for `--repeat`, discarding
+/// the results. Each iteration compiles the pattern again unless
+/// `reuse_compiled` is set, in which case a pattern that fails to compile is
+/// an error instead of a timing.
+pub fn repeat_pattern(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    iterations: usize,
+    reuse_compiled: bool,
+) -> Result<RepeatTiming, String> {
+    let iterations = iterations.max(1);
+    let (compile, (_, total)) = if reuse_compiled {
+        if !is_implemented(engine_choice) {
+            return Err(placeholder_message(engine_choice));
+        }
+        let (regexer, compile) = timed(|| {
+            RegexerBuilder::new(pattern)
+                .engine(*engine_choice)
+                .max_dfa_states(limits.dfa_states())
+                .build()
+        });
+        let regexer = regexer.map_err(|e| MatchOutcome::InvalidPattern(e).describe(text))?;
+        (
+            Some(compile),
+            timed(|| {
+                for _ in 0..iterations {
+                    describe_compiled(&regexer, text, limits);
+                }
+            }),
+        )
+    } else {
+        (
+            None,
+            timed(|| {
+                for _ in 0..iterations {
+                    apply_pattern_limited(pattern, text, engine_choice, limits);
+                }
+            }),
+        )
+    };
+    Ok(RepeatTiming {
+        iterations,
+        per_iteration: total.div_f64(iterations as f64),
+        compile,
+    })
+}
+
+/// `apply_pattern_limited` with an already compiled pattern.
+fn describe_compiled(regexer: &Regexer, text: &str, limits: &SearchLimits) -> String {
+    if let Err(message) = limits.check_input(text) {
+        return message;
+    }
+    match regexer.find_ranges_limited(text, limits) {
+        Ok(ranges) => MatchOutcome::from_ranges(ranges).describe_limited(text, limits.max_matches),
+        Err(message) => message,
+    }
+}
+
+/// Formats a `--timing` report line for `--repeat`, e.g.
+/// `Engine dfa took 1.23ms per iteration over 3 runs`.
+pub fn repeat_timing_line(engine_name: &str, timing: &RepeatTiming) -> String {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let mut line = format!(
+        "Engine {} took {:.2}ms per iteration over {} runs",
+        engine_name,
+        ms(timing.per_iteration),
+        timing.iterations
+    );
+    if let Some(compile) = timing.compile {
+        line.push_str(&format!(", after compiling once in {:.2}ms", ms(compile)));
+    }
+    line
+}
+
+/// Library entry point: `apply_pattern` taking the engine by value.
+pub fn match_all(pattern: &str, text: &str, engine_choice: EngineChoice) -> MatchOutcome {
+    apply_pattern(pattern, text, &engine_choice)
+}
+
+/// The alternation of two patterns, for `--or`. Each side is parsed on its
+/// own first, so two malformed halves can't pair up into a pattern that
+/// compiles, and `Err` names the side that failed. For the custom engine the
+/// result is `Union(Group(a), Group(b))` built from the two trees and written
+/// back as source. The builtin engines join the halves in non-capturing
+/// groups, leaving the capture numbering alone. custommeta takes the custom
+/// union when both sides parse there, and the builtin join otherwise.
+pub fn either_pattern(a: &str, b: &str, engine_choice: &EngineChoice) -> Result<String, String> {
+    let custom_union = || -> Result<String, String> {
+        let a = parse_tree(a).map_err(|e| format!("Invalid pattern: {}", e))?;
+        let b = parse_tree(b).map_err(|e| format!("Invalid --or pattern: {}", e))?;
+        let union = RegexAST::Union(
+            Box::new(RegexAST::Group(Box::new(a))),
+            Box::new(RegexAST::Group(Box::new(b))),
+        );
+        Ok(union.to_pattern())
+    };
+    let builtin_union = || -> Result<String, String> {
+        Regex::new(a).map_err(|e| format!("Invalid pattern: {}", e))?;
+        Regex::new(b).map_err(|e| format!("Invalid --or pattern: {}", e))?;
+        Ok(format!("(?:{})|(?:{})", a, b))
+    };
+    match engine_choice {
+        EngineChoice::Custom => custom_union(),
+        EngineChoice::Custommeta => custom_union().or_else(|_| builtin_union()),
+        _ => builtin_union(),
+    }
+}
+
+/// Checks whether `pattern` compiles for the engine without running a search.
+/// Placeholder engines are checked against the builtin syntax they will accept.
+pub fn compiles(pattern: &str, engine_choice: &EngineChoice) -> bool {
+    validate(pattern, engine_choice, &SearchLimits::default()).is_ok()
+}
+
+/// Compiles `pattern` for the engine without running a search and returns the
+/// compile error, if any, for `--validate`.
+pub fn validate(
+    pattern: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+) -> Result<(), String> {
+    match engine_choice {
+        EngineChoice::Custom => compile_custom(pattern, limits)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        // custommeta falls back to builtin whenever CustomRegex rejects a pattern.
+        EngineChoice::Custommeta => match compile_custom(pattern, limits) {
+            Ok(_) => Ok(()),
+            Err(_) => Regex::new(pattern).map(|_| ()).map_err(|e| e.to_string()),
+        },
+        EngineChoice::Builtin
+        | EngineChoice::Dfa
+        | EngineChoice::Hybrid
+        | EngineChoice::Meta
+        | EngineChoice::Onepass
+        | EngineChoice::Boundedbacktracker
+        | EngineChoice::Pikevm => Regex::new(pattern).map(|_| ()).map_err(|e| e.to_string()),
+    }
+}
+
+/// Byte ranges of the matches the engine reports, used for highlighting.
+/// Invalid patterns and placeholder engines report no ranges. With a `limit`
+/// the engines stop after that many matches.
+pub fn match_ranges(
+    pattern: &str,
+    text: &str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    limit: Option<usize>,
+) -> Vec<Range<usize>> {
+    match engine_choice {
+        EngineChoice::Builtin | EngineChoice::Dfa => builtin_ranges(pattern, text, limit),
+        EngineChoice::Custom => compile_custom(pattern, limits)
+            .map(|cr| cr.find_ranges_up_to(text, limit))
+            .unwrap_or_default(),
+        // Custom first, builtin when it errors or custommeta_choice picks it.
+        EngineChoice::Custommeta => match compile_custom(pattern, limits) {
+            Ok(cr) => {
+                let custom = cr.find_ranges_up_to(text, limit);
+                if Regex::new(pattern).is_err() {
+                    return custom;
+                }
+                custommeta_choice(text, custom, builtin_ranges(pattern, text, limit)).0
             }
+            Err(_) => builtin_ranges(pattern, text, limit),
+        },
+        EngineChoice::Hybrid
+        | EngineChoice::Meta
+        | EngineChoice::Onepass
+        | EngineChoice::Boundedbacktracker
+        | EngineChoice::Pikevm => Vec::new(),
+    }
+}
+
+/// Compiles `pattern` for the custom engine within `limits`' DFA state limit.
+fn compile_custom(pattern: &str, limits: &SearchLimits) -> Result<CustomRegex, RegexError> {
+    CustomRegex::with_max_states(pattern, limits.dfa_states())
+}
+
+fn builtin_ranges(pattern: &str, text: &str, limit: Option<usize>) -> Vec<Range<usize>> {
+    match Regex::new(pattern) {
+        Ok(regex) => builtin_limited(&regex, text, limit),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Runs the pattern against each line of `text` on its own, grep style.
+/// Returns `(line_number, line)` for every line with at least one match,
+/// or for every line without one when `invert` is set. Line numbers start at 1.
+/// `Err` holds the message to print for an invalid pattern or an engine that
+/// is not implemented yet, so `-v` never selects every line by mistake.
+pub fn matching_lines<'a>(
+    pattern: &str,
+    text: &'a str,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    invert: bool,
+) -> Result<Vec<(usize, &'a str)>, String> {
+    let matcher = line_matcher(pattern, engine_choice, limits)?;
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| matcher.is_match(line) != invert)
+        .map(|(i, line)| (i + 1, line))
+        .collect())
+}
+
+/// Groups the `selected` line numbers (1-based, ascending) of `text` with up
+/// to `before` and `after` lines around each, for `--context`. Windows that
+/// overlap or touch merge into one group, as in grep. Each entry is
+/// `(line_number, line, selected)`.
+pub fn context_groups<'a>(
+    text: &'a str,
+    selected: &[usize],
+    before: usize,
+    after: usize,
+) -> Vec<Vec<(usize, &'a str, bool)>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut windows: Vec<Range<usize>> = Vec::new();
+    for &line_no in selected {
+        let window = line_no.saturating_sub(before).max(1)..(line_no + after).min(lines.len()) + 1;
+        match windows.last_mut() {
+            Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+            _ => windows.push(window),
+        }
+    }
+    windows
+        .into_iter()
+        .map(|window| {
+            window
+                .map(|line_no| {
+                    let is_selected = selected.binary_search(&line_no).is_ok();
+                    (line_no, lines[line_no - 1], is_selected)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Like `matching_lines`, but reads `reader` one line at a time, so a large
+/// file never has to be held in memory whole. Lines are yielded as they are read.
+/// The pattern is compiled up front, so its error comes before any line is read.
+pub fn stream_matching_lines<'a, R: BufRead + 'a>(
+    pattern: &str,
+    reader: R,
+    engine_choice: &EngineChoice,
+    limits: &SearchLimits,
+    invert: bool,
+) -> Result<impl Iterator<Item = io::Result<(usize, String)>> + 'a, String> {
+    let matcher = line_matcher(pattern, engine_choice, limits)?;
+    Ok(reader
+        .lines()
+        .enumerate()
+        .filter_map(move |(i, line)| match line {
+            Ok(line) if matcher.is_match(&line) == invert => None,
+            Ok(line) => Some(Ok((i + 1, line))),
+            Err(e) => Some(Err(e)),
+        }))
+}
+
+fn builtin_limited(regex: &Regex, text: &str, limit: Option<usize>) -> Vec<Range<usize>> {
+    regex
+        .find_iter(text)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|mat| mat.range())
+        .collect()
+}
+
+/// One more than `max_matches`, so the engines collect just enough to tell
+/// whether the limit cut anything off.
+pub(crate) fn probe_limit(max_matches: Option<usize>) -> Option<usize> {
+    max_matches.map(|max| max.saturating_add(1))
+}
+
+/// `format_matches` for a list collected with `probe_limit`: keeps the first
+/// `max_matches` and notes when there were more.
+fn format_limited(mut matches: Vec<&str>, max_matches: Option<usize>) -> String {
+    match max_matches {
+        Some(max) if matches.len() > max => {
+            matches.truncate(max);
+            format!("{} ... (truncated)", format_matches(&matches))
         }
+        _ => format_matches(&matches),
+    }
+}
+
+pub(crate) fn format_matches(matches: &[&str]) -> String {
+    if matches.is_empty() {
+        "No matches found.".to_string()
+    } else {
+        format!("Matches: {:?}", matches)
+    }
+}
+
+/// custommeta's pick between the two engines' match ranges: the custom
+/// engine's when both report the same matches, otherwise the builtin engine's,
+/// along with the difference from `match_range_diff`.
+pub(crate) fn custommeta_choice(
+    text: &str,
+    custom: Vec<Range<usize>>,
+    builtin: Vec<Range<usize>>,
+) -> (Vec<Range<usize>>, Option<String>) {
+    match match_range_diff(text, &custom, &builtin) {
+        None => (custom, None),
+        diff => (builtin, diff),
+    }
+}
+
+/// Compares the match ranges of the custom and builtin engines, so a
+/// difference in count or position counts as much as one in substance.
+/// Returns the matches only one engine reported, or `None` when they agree.
+pub(crate) fn match_range_diff(
+    text: &str,
+    custom: &[Range<usize>],
+    builtin: &[Range<usize>],
+) -> Option<String> {
+    if custom == builtin {
+        return None;
+    }
+    let only = |ranges: &[Range<usize>], other: &[Range<usize>]| -> String {
+        let other: HashSet<&Range<usize>> = other.iter().collect();
+        ranges
+            .iter()
+            .filter(|range| !other.contains(range))
+            .map(|range| {
+                format!(
+                    "{:?}@[{}..{}]",
+                    &text[range.clone()],
+                    range.start,
+                    range.end
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    Some(format!(
+        "only custom: [{}], only builtin: [{}]",
+        only(custom, builtin),
+        only(builtin, custom)
+    ))
+}
+
+/// The matched substrings only one engine found, as `(only custom, only builtin)`.
+/// Each list is sorted and free of duplicates.
+pub fn match_set_split<'a>(
+    custom: &[&'a str],
+    builtin: &[&'a str],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let custom: BTreeSet<&str> = custom.iter().copied().collect();
+    let builtin: BTreeSet<&str> = builtin.iter().copied().collect();
+    (
+        custom.difference(&builtin).copied().collect(),
+        builtin.difference(&custom).copied().collect(),
+    )
+}
+
+/// Runs CustomRegex and verifies it against the builtin engine. When the match
+/// ranges differ, the diff goes to stderr and the builtin ranges win.
+fn custommeta_ranges(
+    pattern: &str,
+    text: &str,
+    limits: &SearchLimits,
+    limit: Option<usize>,
+) -> Result<Vec<Range<usize>>, String> {
+    let parser = match compile_custom(pattern, limits) {
+        Ok(parser) => parser,
         Err(e) => {
             eprintln!("customMeta: error: {}. fallback builtin.", e);
-            apply_pattern_builtin(pattern, text)
+            let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+            return Ok(builtin_limited(&regex, text, limit));
         }
+    };
+    let custom = parser.find_ranges_up_to(text, limit);
+    let regex = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            eprintln!(
+                "customMeta: builtin rejected the pattern ({}). Using custom.",
+                e
+            );
+            return Ok(custom);
+        }
+    };
+    let (ranges, diff) = custommeta_choice(text, custom, builtin_limited(&regex, text, limit));
+    if let Some(diff) = diff {
+        eprintln!(
+            "customMeta: mismatch between custom and builtin ({}). Using builtin.",
+            diff
+        );
     }
+    Ok(ranges)
 }
-
@@ -0,0 +1,13 @@
+//! Regex engines behind the `regexer` CLI/TUI, usable from other Rust code.
+
+pub mod app;
+pub mod builder;
+pub mod config;
+pub mod custom_regex;
+pub mod engines;
+#[cfg(test)]
+mod tests;
+
+pub use builder::{Regexer, RegexerBuilder};
+pub use custom_regex::{CustomRegex, Interrupted, MatchSemantics};
+pub use engines::{apply_pattern, match_all, EngineChoice, MatchOutcome};
@@ -1,24 +1,75 @@
-use clap::{Arg, ArgAction, Command};
+use clap::{parser::ValueSource, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use clap_complete::Shell;
 use color_eyre::Result;
-use std::process;
+use encoding_rs::{Encoding, UTF_8};
+use std::{
+    env, fs,
+    io::{self, BufReader, IsTerminal, Write},
+    process,
+};
 
-mod app;
-mod custom_regex;
-mod engines; // The custom regex module you already have
+use regexer::{
+    app::App,
+    config,
+    custom_regex::{self, MatchSemantics},
+    engines::{self, EngineChoice, MatchOutcome, OffsetUnit, SearchLimits},
+};
 
-use app::App;
+/// Longest string `--generate` will build, so `a*` stops instead of looping.
+const GENERATE_MAX_LEN: usize = 32;
 
-fn main() -> Result<()> {
-    color_eyre::install()?;
+/// Flags `--engine all` can't run with. It may come from .regexerrc, so clap
+/// can't reject these itself.
+const ENGINE_ALL_CONFLICTS: &[&str] = &[
+    "interactive",
+    "line-numbers",
+    "invert-match",
+    "count",
+    "csv",
+    "first",
+    "last",
+    "format",
+    "unique",
+    "overlapping",
+    "fixed",
+    "repeat",
+    "quiet",
+    "match-semantics",
+    "patterns-file",
+];
 
-    let matches = Command::new("regexer")
+/// Flags that only make sense for a single `-f` file; clap counts values but
+/// can't make a conflict depend on them.
+const MULTIPLE_FILES_CONFLICTS: &[&str] = &[
+    "interactive",
+    "csv",
+    "first",
+    "last",
+    "unique",
+    "overlapping",
+    "format",
+    "groups",
+    "context",
+    "before",
+    "after",
+    "repeat",
+    "quiet",
+    "extract",
+    "split",
+    "match-semantics",
+    "patterns-file",
+];
+
+/// The full command line definition, shared by argument parsing and `--completions`.
+fn cli() -> Command {
+    Command::new("regexer")
         .version("0.1.0")
         .about("A regex CLI/TUI tool for parsing and testing regular expressions.")
         .long_about(
 "regexer is a command-line/text-user interface tool for parsing and testing regular expressions.
 
 ...
-Use --engine to select the regex engine: (some are wip and are not implemented)
+Use --engine to select the regex engine: (some are wip and are not implemented; see --engine-info)
   - builtin
   - custom
   - dfa
@@ -28,6 +79,7 @@ Use --engine to select the regex engine: (some are wip and are not implemented)
   - pikevm 
   - meta
   - custommeta (tries CustomRegex first, verify with builtin, fallback to builtin on error)
+  - all (runs every implemented engine and reports whether they agree)
 "
         )
         .arg(
@@ -47,12 +99,36 @@ Use --engine to select the regex engine: (some are wip and are not implemented)
                 .help("Launch the interactive TUI mode")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("multiline")
+                .short('M')
+                .long("multiline")
+                .help("Let ^ and $ match at every line start and end, not just the ends of the text. With -f the whole file is one text, so ^ matches at each of its lines; -n, -v and -c already match line by line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dotall")
+                .short('s')
+                .long("dotall")
+                .help("Let . match newlines too")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ignore-case")
+                .long("ignore-case")
+                .help("Match letters regardless of case. Takes an optional true or false to override ignore_case in .regexerrc")
+                .value_name("BOOL")
+                .num_args(0..=1)
+                .default_missing_value("true")
+                .value_parser(clap::value_parser!(bool)),
+        )
         .arg(
             Arg::new("file")
                 .short('f')
                 .long("file")
-                .help("Read text from a file instead of standard input")
-                .value_name("FILE"),
+                .help("Read text from a file instead of standard input. Repeat to search several files; each result is then prefixed with its file name, and unreadable files are skipped with a warning")
+                .value_name("FILE")
+                .action(ArgAction::Append),
         )
         .arg(
             Arg::new("output")
@@ -61,25 +137,696 @@ Use --engine to select the regex engine: (some are wip and are not implemented)
                 .help("Write the output to a file instead of standard output")
                 .value_name("FILE"),
         )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .help("Load the TUI expression history from FILE and save it back on exit")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("patterns-file")
+                .long("patterns-file")
+                .help("Read one pattern per line from FILE and run each against the text (blank lines and lines starting with # are skipped); the only positional argument is then TEXT")
+                .value_name("FILE")
+                .conflicts_with_all(["pattern-env", "line-mode"]),
+        )
+        .arg(
+            Arg::new("pattern-env")
+                .long("pattern-env")
+                .help("Read PATTERN from the environment variable VAR, sparing it shell quoting; the only positional argument is then TEXT")
+                .value_name("VAR"),
+        )
+        .arg(
+            Arg::new("text-env")
+                .long("text-env")
+                .help("Read TEXT from the environment variable VAR")
+                .value_name("VAR"),
+        )
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .help("With --patterns-file, check all patterns at once with the builtin engine's RegexSet and list the ones that match, without match positions")
+                .action(ArgAction::SetTrue)
+                .requires("patterns-file"),
+        )
+        .arg(
+            Arg::new("or")
+                .long("or")
+                .help("Also match PATTERN2, as if PATTERN were PATTERN|PATTERN2")
+                .value_name("PATTERN2")
+                .conflicts_with("patterns-file"),
+        )
+        .arg(
+            Arg::new("testcase")
+                .long("testcase")
+                .help("Read the pattern from the first line of FILE and the text from the rest of it (empty if FILE has one line); takes no PATTERN, TEXT or -f")
+                .value_name("FILE")
+                .conflicts_with_all(["pattern", "text", "file", "patterns-file", "pattern-env", "text-env"]),
+        )
+        .arg(
+            Arg::new("expect")
+                .long("expect")
+                .help("Run each pattern<TAB>text<TAB>expected_match_count line of FILE with the selected engine, print PASS or FAIL per case and exit 1 if any failed; takes no PATTERN, TEXT or -f")
+                .value_name("FILE")
+                .conflicts_with_all(["pattern", "text", "file", "patterns-file", "pattern-env", "text-env", "testcase"]),
+        )
+        .arg(
+            Arg::new("line-numbers")
+                .short('n')
+                .long("line-numbers")
+                .help("Match each line separately and print LINENO:LINE for every matching line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("invert-match")
+                .short('v')
+                .long("invert-match")
+                .help("Match line by line and select the lines that do NOT match")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("count")
+                .short('c')
+                .long("count")
+                .help("Match line by line and print only the number of selected lines (non-matching lines with -v)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("show-positions")
+                .long("show-positions")
+                .help("Show the offsets of each match (see --offset-unit), e.g. \"abc\"@[0..3]")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("groups")
+                .long("groups")
+                .help("Print the capture groups of every match, labelled by name (?P<name>...) or index (builtin engine only)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("With --groups, print the groups as JSON: one object per match mapping each group to its text and offsets")
+                .action(ArgAction::SetTrue)
+                .requires("groups"),
+        )
+        .arg(
+            Arg::new("extract")
+                .long("extract")
+                .help("Print one JSON object per line for each match, mapping every named group (?P<name>...) to its text, or null when it did not take part (builtin engine only)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["interactive", "groups", "json", "csv", "first", "last", "unique", "overlapping", "fixed", "format", "repeat", "quiet", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("first")
+                .long("first")
+                .help("Report only the first match and its offsets, stopping the search there; exits 1 when nothing matches")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["last", "csv", "groups", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Print nothing and exit 0 if PATTERN matches anywhere, 1 otherwise, like grep -q; stops at the first match")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["interactive", "output", "csv", "first", "last", "unique", "overlapping", "format", "groups", "repeat", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("last")
+                .long("last")
+                .help("Report only the last match and its offsets")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["csv", "groups", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("csv")
+                .long("csv")
+                .help("Print the matches as CSV with an index,match,start,end header row")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["groups", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("context")
+                .short('C')
+                .long("context")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("With -n or -v, also print N lines around each selected line, with -- between groups"),
+        )
+        .arg(
+            Arg::new("before")
+                .short('B')
+                .long("before")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Like --context, but only N lines before each selected line"),
+        )
+        .arg(
+            Arg::new("after")
+                .short('A')
+                .long("after")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Like --context, but only N lines after each selected line"),
+        )
+        .arg(
+            Arg::new("generate")
+                .long("generate")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Print up to N distinct strings the whole PATTERN matches, shortest first (custom engine)"),
+        )
+        .arg(
+            Arg::new("fixed")
+                .short('F')
+                .long("fixed")
+                .help("Treat PATTERN as a literal string, not a regex, and report every occurrence with offsets (the same for every engine)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["csv", "first", "last", "unique", "overlapping", "format", "groups", "multiline", "dotall", "or", "patterns-file", "line-mode", "interactive"]),
+        )
+        .arg(
+            Arg::new("overlapping")
+                .long("overlapping")
+                .help("Report the shortest non-empty match at every start position, even where matches overlap, with offsets")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["csv", "first", "last", "unique", "format", "groups", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("unique")
+                .long("unique")
+                .help("Print each distinct match with how often it occurs, most frequent first")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["csv", "first", "last", "format", "groups", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("split")
+                .long("split")
+                .help("Use PATTERN as a delimiter and print the fields of the text between its matches, one per line; a delimiter at the start or end, or two in a row, gives an empty field (builtin engine only)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["interactive", "groups", "json", "extract", "csv", "first", "last", "unique", "overlapping", "fixed", "format", "repeat", "quiet", "match-semantics", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("TEMPLATE")
+                .help("Print each match through TEMPLATE, e.g. \"{index}: {match} @{start}\". Placeholders: {match}, {start}, {end}, {index}; {{ and }} are literal braces")
+                .conflicts_with_all(["csv", "first", "last", "groups", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .help("Highlight matches in color: auto (only when writing to a terminal), always or never")
+                .value_name("WHEN")
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("timing")
+                .long("timing")
+                .help("Print how long the selected engine took to stderr")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("repeat")
+                .long("repeat")
+                .help("Run the match N times and keep one result; with --timing, report the average time per iteration")
+                .value_name("N")
+                .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+                .conflicts_with_all(["csv", "first", "last", "unique", "overlapping", "fixed", "format", "groups", "patterns-file", "line-mode", "interactive"]),
+        )
+        .arg(
+            Arg::new("reuse-compiled")
+                .long("reuse-compiled")
+                .help("With --repeat, compile PATTERN once outside the loop so only matching is timed")
+                .action(ArgAction::SetTrue)
+                .requires("repeat"),
+        )
+        .arg(
+            Arg::new("validate")
+                .long("validate")
+                .help("Only check that PATTERN compiles with the selected engine: exit 0 silently if it does, otherwise print the error and exit 1")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .help("Print the custom engine's parse tree for PATTERN and exit (requires --engine custom or custommeta)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dot")
+                .long("dot")
+                .help("Print the custom engine's NFA or DFA for PATTERN as Graphviz DOT and exit")
+                .value_name("GRAPH")
+                .value_parser(["nfa", "dfa"]),
+        )
+        .arg(
+            Arg::new("engine-info")
+                .long("engine-info")
+                .help("Print which features each engine supports and exit")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-dfa")
+                .long("dump-dfa")
+                .help("Print the custom engine's minimized DFA for PATTERN as a transition table and exit")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("bench")
+                .long("bench")
+                .help("Benchmark every implemented engine on PATTERN and print min/median/max timings (uses generated text when no TEXT or -f FILE is given)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("bench-iterations")
+                .long("bench-iterations")
+                .help("How many times --bench runs each engine")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10"),
+        )
+        .arg(
+            Arg::new("max-matches")
+                .long("max-matches")
+                .help("Stop searching after N matches; the output ends with \"... (truncated)\" when more were found, except with --csv and --format")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("max-input-bytes")
+                .long("max-input-bytes")
+                .help("Refuse to search inputs larger than N bytes")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("max-steps")
+                .long("max-steps")
+                .help("Interrupt the custom engine's search after N DFA steps (--engine custom only)")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with_all(["show-positions", "match-semantics", "first", "last", "unique", "overlapping", "format", "csv", "repeat", "quiet", "interactive", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("max-dfa-states")
+                .long("max-dfa-states")
+                .help("Reject patterns whose DFA needs more than N states in the custom engine (default 10000)")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize)),
+        )
         .arg(
             Arg::new("engine")
                 .long("engine")
-                .help("Select the regex engine to use: builtin, custom, dfa, hybrid, onepass, boundedbacktracker, pikevm, meta, custommeta")
-                .value_parser(["builtin", "custom", "dfa", "hybrid", "onepass", "boundedbacktracker", "pikevm", "meta", "custommeta"])
+                .help("Select the regex engine to use: builtin, custom, dfa, hybrid, onepass, boundedbacktracker, pikevm, meta, custommeta, all")
+                .value_parser(["builtin", "custom", "dfa", "hybrid", "onepass", "boundedbacktracker", "pikevm", "meta", "custommeta", "all"])
                 .default_value("builtin")
         )
-        .get_matches();
+        .arg(
+            Arg::new("no-newline")
+                .short('z')
+                .long("no-newline")
+                .help("Leave out the newline after the result, on stdout and in -o FILE; newlines between result lines (-n, --format, ...) stay")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("match-semantics")
+                .long("match-semantics")
+                .help("List non-overlapping matches, picking the longest (longest) or the one from the earliest alternative (first) at each leftmost start; the custom engine honors both, builtin only first")
+                .value_name("SEMANTICS")
+                .value_parser(["longest", "first"])
+                .conflicts_with_all(["interactive", "csv", "first", "last", "unique", "overlapping", "fixed", "format", "groups", "repeat", "quiet", "patterns-file", "line-mode"]),
+        )
+        .arg(
+            Arg::new("offset-unit")
+                .long("offset-unit")
+                .help("Count reported match positions in UTF-8 bytes, as the regex crate does, or in characters")
+                .value_name("UNIT")
+                .value_parser(["bytes", "chars"])
+                .default_value("bytes"),
+        )
+        .arg(
+            Arg::new("input-encoding")
+                .long("input-encoding")
+                .help("Decode -f files from ENCODING, e.g. latin1 or utf-16le, instead of UTF-8; bytes invalid in it are an error")
+                .value_name("ENCODING")
+                .default_value("utf8"),
+        )
+        .arg(
+            Arg::new("completions")
+                .long("completions")
+                .help("Print a tab completion script for SHELL (bash, elvish, fish, powershell or zsh) and exit")
+                .value_name("SHELL")
+                .value_parser(clap::value_parser!(Shell)),
+        )
+        .group(
+            ArgGroup::new("line-mode")
+                .args(["line-numbers", "invert-match", "count"])
+                .multiple(true),
+        )
+        .group(
+            ArgGroup::new("selected-lines")
+                .args(["line-numbers", "invert-match"])
+                .multiple(true),
+        )
+        .group(
+            ArgGroup::new("context-lines")
+                .args(["context", "before", "after"])
+                .multiple(true)
+                .requires("selected-lines")
+                .conflicts_with_all(["count", "interactive"]),
+        )
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let matches = cli().get_matches();
+
+    if let Some(&shell) = matches.get_one::<Shell>("completions") {
+        let mut command = cli();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+        return Ok(());
+    }
+
+    let config = config::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    // A .regexerrc setting applies unless the flag was given on the command line.
+    let from_cli = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
 
     let interactive = matches.get_flag("interactive");
-    let file = matches.get_one::<String>("file");
+    let line_numbers = matches.get_flag("line-numbers");
+    let invert_match = matches.get_flag("invert-match");
+    let count = matches.get_flag("count");
+    let set = matches.get_flag("set");
+    let timing = matches.get_flag("timing");
+    let repeat = matches.get_one::<usize>("repeat").copied();
+    let reuse_compiled = matches.get_flag("reuse-compiled");
+    let show_positions = matches.get_flag("show-positions");
+    let groups = matches.get_flag("groups");
+    let json = matches.get_flag("json");
+    let extract = matches.get_flag("extract");
+    let split = matches.get_flag("split");
+    let csv = matches.get_flag("csv");
+    let first = matches.get_flag("first");
+    let quiet = matches.get_flag("quiet");
+    let newline = !matches.get_flag("no-newline");
+    let semantics = matches
+        .get_one::<String>("match-semantics")
+        .map(|s| s.parse::<MatchSemantics>().unwrap());
+    let last = matches.get_flag("last");
+    let unit: OffsetUnit = matches
+        .get_one::<String>("offset-unit")
+        .unwrap()
+        .parse()
+        .unwrap();
+    let unique = matches.get_flag("unique");
+    let overlapping = matches.get_flag("overlapping");
+    let fixed = matches.get_flag("fixed");
+    let context = matches.get_one::<usize>("context").copied();
+    let before = matches.get_one::<usize>("before").copied().or(context);
+    let after = matches.get_one::<usize>("after").copied().or(context);
+    let has_context = before.is_some() || after.is_some();
+    let format = matches.get_one::<String>("format").map(|template| {
+        engines::MatchTemplate::parse(template).unwrap_or_else(|e| {
+            eprintln!("Invalid --format template: {}", e);
+            process::exit(1);
+        })
+    });
+    let color = match &config.color {
+        Some(color) if !from_cli("color") => color,
+        _ => matches.get_one::<String>("color").unwrap(),
+    };
+    let use_color = match color.as_str() {
+        "always" => true,
+        "never" => false,
+        // auto: only color what goes straight to a terminal.
+        _ => matches.get_one::<String>("output").is_none() && io::stdout().is_terminal(),
+    };
+    let validate = matches.get_flag("validate");
+    let explain = matches.get_flag("explain");
+    let dot = matches.get_one::<String>("dot");
+    let dump_dfa = matches.get_flag("dump-dfa");
+    let bench = matches.get_flag("bench");
+    let bench_iterations = *matches.get_one::<usize>("bench-iterations").unwrap();
+    let limits = SearchLimits {
+        max_matches: matches.get_one::<usize>("max-matches").copied(),
+        max_input_bytes: matches.get_one::<usize>("max-input-bytes").copied(),
+        max_steps: matches.get_one::<usize>("max-steps").copied(),
+        max_dfa_states: matches.get_one::<usize>("max-dfa-states").copied(),
+    };
+    let encoding_label = matches.get_one::<String>("input-encoding").unwrap();
+    let encoding = Encoding::for_label(encoding_label.as_bytes()).unwrap_or_else(|| {
+        eprintln!(
+            "Unknown input encoding '{}'. See --help for usage.",
+            encoding_label
+        );
+        process::exit(1);
+    });
+    let files: Vec<&String> = matches
+        .get_many::<String>("file")
+        .map(|files| files.collect())
+        .unwrap_or_default();
+    let file = files.first().copied();
+    let multiple_files = files.len() > 1;
     let output = matches.get_one::<String>("output");
+    let history = matches.get_one::<String>("history");
     let pattern = matches.get_one::<String>("pattern");
     let text = matches.get_one::<String>("text");
-    let engine_str = matches.get_one::<String>("engine").unwrap();
-    let engine_choice = engines::parse_engine_choice(engine_str);
+    let pattern_env = env_arg(&matches, "pattern-env");
+    let text_env = env_arg(&matches, "text-env");
+    // Positional arguments fill in whatever the environment doesn't, in order.
+    let (pattern, text) = {
+        let mut positionals = [pattern, text].into_iter().flatten();
+        let pattern = pattern_env.as_ref().or_else(|| positionals.next());
+        let text = text_env.as_ref().or_else(|| positionals.next());
+        if positionals.next().is_some() {
+            eprintln!("--pattern-env and --text-env replace the PATTERN and TEXT arguments, which cannot be given as well. See --help for usage.");
+            process::exit(1);
+        }
+        (pattern, text)
+    };
+    let multiline = matches.get_flag("multiline");
+    let dotall = matches.get_flag("dotall");
+    let ignore_case = matches
+        .get_one::<bool>("ignore-case")
+        .copied()
+        .or(config.ignore_case)
+        .unwrap_or(false);
+    let patterns = match matches.get_one::<String>("patterns-file") {
+        Some(path) => Some(
+            engines::load_patterns(path)?
+                .iter()
+                .map(|p| engines::pattern_with_flags(p, multiline, dotall, ignore_case))
+                .collect::<Vec<_>>(),
+        ),
+        None => None,
+    };
+    // With --patterns-file there is no PATTERN argument, so a lone positional is the TEXT.
+    let (pattern, text) = match (&patterns, pattern, text) {
+        (Some(_), Some(_), Some(_)) => {
+            eprintln!(
+                "--patterns-file takes TEXT as its only positional argument. See --help for usage."
+            );
+            process::exit(1);
+        }
+        (Some(_), p, t) => (None, t.or(p)),
+        (None, p, t) => (p, t),
+    };
+    let testcase = match matches.get_one::<String>("testcase") {
+        Some(path) => Some(engines::load_testcase(path)?),
+        None => None,
+    };
+    // A testcase fixture supplies both the pattern and the text.
+    let (pattern, text) = match &testcase {
+        Some((p, t)) => (Some(p), Some(t)),
+        None => (pattern, text),
+    };
+    let engine_str: &str = match &config.engine {
+        Some(engine) if !from_cli("engine") => engine,
+        _ => matches.get_one::<String>("engine").unwrap(),
+    };
+    // `all` runs every engine; the rest of the setup validates with builtin.
+    let engine_choice = if engine_str == "all" {
+        EngineChoice::Builtin
+    } else {
+        engine_str.parse().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        })
+    };
+    let either;
+    let pattern = match (pattern, matches.get_one::<String>("or")) {
+        (Some(p), Some(other)) => {
+            either = engines::either_pattern(p, other, &engine_choice).unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            });
+            Some(&either)
+        }
+        (None, Some(_)) => {
+            eprintln!("--or adds to PATTERN and cannot be used without it. See --help for usage.");
+            process::exit(1);
+        }
+        (p, None) => p,
+    };
+    let flagged_pattern =
+        pattern.map(|p| engines::pattern_with_flags(p, multiline, dotall, ignore_case));
+    let pattern = flagged_pattern.as_ref();
+
+    if matches.get_flag("engine-info") {
+        println!("{}", engines::engine_info_table());
+        return Ok(());
+    }
+
+    // The TUI shows the placeholder result itself; elsewhere it would pass for output.
+    if !interactive && engine_str != "all" && !engines::is_implemented(&engine_choice) {
+        eprintln!(
+            "Warning: engine '{}' is not yet implemented; results are placeholders. See --engine-info.",
+            engine_choice.name()
+        );
+        process::exit(1);
+    }
+
+    // No TEXT is needed to check that a pattern compiles.
+    if validate {
+        let Some(pattern) = pattern else {
+            eprintln!("--validate requires a PATTERN. See --help for usage.");
+            process::exit(1);
+        };
+        if let Err(e) = engines::validate(pattern, &engine_choice, &limits) {
+            eprintln!("Invalid pattern: {}", e);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if explain {
+        if !matches!(
+            engine_choice,
+            EngineChoice::Custom | EngineChoice::Custommeta
+        ) {
+            eprintln!("--explain shows the custom engine's parse tree and needs --engine custom or custommeta. See --help for usage.");
+            process::exit(1);
+        }
+        let Some(pattern) = pattern else {
+            eprintln!("--explain requires a PATTERN. See --help for usage.");
+            process::exit(1);
+        };
+        match custom_regex::explain(pattern) {
+            Ok(tree) => println!("{}", tree),
+            Err(e) => {
+                eprintln!("Invalid pattern: {}", e);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(graph) = dot {
+        let Some(pattern) = pattern else {
+            eprintln!("--dot requires a PATTERN. See --help for usage.");
+            process::exit(1);
+        };
+        let rendered = if graph == "nfa" {
+            custom_regex::nfa_dot(pattern)
+        } else {
+            custom_regex::dfa_dot(pattern, limits.dfa_states())
+        };
+        match rendered {
+            Ok(dot) => print!("{}", dot),
+            Err(e) => {
+                eprintln!("Invalid pattern: {}", e);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(count) = matches.get_one::<usize>("generate") {
+        let Some(pattern) = pattern else {
+            eprintln!("--generate requires a PATTERN. See --help for usage.");
+            process::exit(1);
+        };
+        if !matches!(
+            engine_choice,
+            EngineChoice::Custom | EngineChoice::Custommeta
+        ) {
+            eprintln!("--generate walks the custom engine's DFA and needs --engine custom or custommeta. See --help for usage.");
+            process::exit(1);
+        }
+        match custom_regex::CustomRegex::with_max_states(pattern, limits.dfa_states()) {
+            Ok(regex) => {
+                for generated in regex.generate(*count, GENERATE_MAX_LEN) {
+                    println!("{}", generated);
+                }
+            }
+            Err(e) => {
+                eprintln!("Invalid pattern: {}", e);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if dump_dfa {
+        let Some(pattern) = pattern else {
+            eprintln!("--dump-dfa requires a PATTERN. See --help for usage.");
+            process::exit(1);
+        };
+        match custom_regex::dfa_table(pattern, limits.dfa_states()) {
+            Ok(table) => print!("{}", table),
+            Err(e) => {
+                eprintln!("Invalid pattern: {}", e);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = matches.get_one::<String>("expect") {
+        if engine_str == "all" {
+            eprintln!("--expect runs one engine at a time and cannot be used with --engine all. See --help for usage.");
+            process::exit(1);
+        }
+        let cases = engines::load_expectations(path).unwrap_or_else(|e| {
+            eprintln!("Cannot read --expect fixture: {}", e);
+            process::exit(1);
+        });
+        let (report, passed) = engines::run_expectations(&cases, &engine_choice);
+        write_output(output, &report, newline)?;
+        if !passed {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if bench {
+        let Some(pattern) = pattern else {
+            eprintln!("--bench requires a PATTERN. See --help for usage.");
+            process::exit(1);
+        };
+        let text = match (file, text) {
+            (Some(file_name), _) => read_file(file_name, encoding),
+            (None, Some(t)) => t.to_string(),
+            // The custom engine scans every substring, so keep generated input modest.
+            (None, None) => "the quick brown fox jumps over the lazy dog ".repeat(6),
+        };
+        let rows = engines::bench_engines(pattern, &text, bench_iterations);
+        return write_output(output, &engines::bench_table(&rows), newline);
+    }
 
-    let no_args_provided =
-        !interactive && file.is_none() && output.is_none() && pattern.is_none() && text.is_none();
+    let no_args_provided = !interactive
+        && file.is_none()
+        && output.is_none()
+        && pattern.is_none()
+        && patterns.is_none()
+        && text.is_none();
     if no_args_provided {
         eprintln!("No arguments provided. See --help for usage.");
         process::exit(1);
@@ -87,35 +834,120 @@ Use --engine to select the regex engine: (some are wip and are not implemented)
 
     if !interactive {
         if file.is_some() {
-            if pattern.is_none() || text.is_some() {
+            if (pattern.is_none() && patterns.is_none()) || text.is_some() {
                 eprintln!("When using -f FILE, you must provide PATTERN and must not provide TEXT. See --help for usage.");
                 process::exit(1);
             }
         } else {
-            if pattern.is_none() || text.is_none() {
+            if (pattern.is_none() && patterns.is_none()) || text.is_none() {
                 eprintln!("Non-interactive mode requires both PATTERN and TEXT if not using -f FILE. See --help for usage.");
                 process::exit(1);
             }
         }
     }
 
-    println!("Running regexer with the following options:");
-    if interactive {
-        println!("  - Running in interactive mode");
+    if interactive && encoding != UTF_8 {
+        eprintln!("--input-encoding only applies to -f outside the TUI and cannot be combined with -i. See --help for usage.");
+        process::exit(1);
+    }
+
+    if engine_str == "all" {
+        if let Some(flag) = given_flag(&matches, ENGINE_ALL_CONFLICTS) {
+            eprintln!("--engine all only compares whole-text matches and cannot be combined with {}. See --help for usage.", flag);
+            process::exit(1);
+        }
     }
-    if let Some(file_name) = file {
-        println!("  - Using file input: {}", file_name);
+
+    if multiple_files {
+        let flag = given_flag(&matches, MULTIPLE_FILES_CONFLICTS)
+            .or_else(|| (engine_str == "all").then(|| "--engine all".to_string()));
+        if let Some(flag) = flag {
+            eprintln!("Several -f files are searched one after another and cannot be combined with {}. See --help for usage.", flag);
+            process::exit(1);
+        }
     }
-    if let Some(output_file) = output {
-        println!("  - Output file: {}", output_file);
+
+    if groups && !engines::engine_info(&engine_choice).groups {
+        eprintln!("--groups needs the builtin engine. See --help for usage.");
+        process::exit(1);
     }
-    if let Some(p) = pattern {
-        println!("  - Pattern: {}", p);
+
+    if extract && !matches!(engine_choice, EngineChoice::Builtin) {
+        eprintln!("--extract needs the builtin engine. See --help for usage.");
+        process::exit(1);
     }
-    if let Some(t) = text {
-        println!("  - Text: {}", t);
+
+    if split && !matches!(engine_choice, EngineChoice::Builtin) {
+        eprintln!("--split needs the builtin engine. See --help for usage.");
+        process::exit(1);
+    }
+
+    if set && (engine_str == "all" || !matches!(engine_choice, EngineChoice::Builtin)) {
+        eprintln!("--set needs the builtin engine. See --help for usage.");
+        process::exit(1);
+    }
+
+    if limits.max_steps.is_some() && !matches!(engine_choice, EngineChoice::Custom) {
+        eprintln!("--max-steps budgets the custom engine's whole-text search and needs --engine custom. See --help for usage.");
+        process::exit(1);
+    }
+
+    if semantics == Some(MatchSemantics::LeftmostLongest)
+        && !matches!(
+            engine_choice,
+            EngineChoice::Custom | EngineChoice::Custommeta
+        )
+    {
+        eprintln!("--match-semantics longest needs --engine custom or custommeta; the other engines are leftmost-first. See --help for usage.");
+        process::exit(1);
+    }
+
+    // Nothing is printed; the exit code says whether anything matched.
+    if quiet {
+        let pattern = pattern.map(String::as_str).unwrap_or_default();
+        let text = match file {
+            Some(file_name) => read_file(file_name, encoding),
+            None => text.unwrap().to_string(),
+        };
+        if let Err(message) = limits.check_len(text.len()) {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+        let found = if fixed {
+            text.contains(pattern)
+        } else {
+            engines::is_match(pattern, &text, &engine_choice, &limits).unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            })
+        };
+        if !found {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Keep stdout pure JSON, CSV or --format output so it can be piped into other tools.
+    if !json && !extract && !split && !csv && format.is_none() {
+        println!("Running regexer with the following options:");
+        if interactive {
+            println!("  - Running in interactive mode");
+        }
+        if !files.is_empty() {
+            let names: Vec<&str> = files.iter().map(|f| f.as_str()).collect();
+            println!("  - Using file input: {}", names.join(", "));
+        }
+        if let Some(output_file) = output {
+            println!("  - Output file: {}", output_file);
+        }
+        if let Some(p) = pattern {
+            println!("  - Pattern: {}", p);
+        }
+        if let Some(t) = text {
+            println!("  - Text: {}", t);
+        }
+        println!("  - Engine: {}", engine_str);
     }
-    println!("  - Engine: {}", engine_str);
 
     if interactive {
         let mut app = App::new(engine_choice);
@@ -129,6 +961,25 @@ Use --engine to select the regex engine: (some are wip and are not implemented)
         if file.is_some() {
             app.set_file(file.map(|f| f.to_string()));
         }
+        if let Some(history_file) = history {
+            app.set_history_file(history_file);
+        }
+        if let Some(output_file) = output {
+            app.set_output_file(output_file);
+        }
+        // Seed the expressions list with every pattern from --patterns-file.
+        if let Some(patterns) = &patterns {
+            let seed_text = match file {
+                Some(file_name) => engines::read_decoded(file_name, encoding).ok(),
+                None => text.cloned(),
+            };
+            if let Some(seed_text) = seed_text {
+                for p in patterns {
+                    app.set_pattern(p);
+                    app.add_expression(seed_text.clone());
+                }
+            }
+        }
 
         if app.pattern_is_empty() && app.has_file() {
             app.enter_pattern_mode();
@@ -139,9 +990,369 @@ Use --engine to select the regex engine: (some are wip and are not implemented)
         ratatui::restore();
         app_result
     } else {
-        // Non-interactive: just run pattern on text/file if needed
-        // For now, we do nothing except print out matches if desired.
+        // Validated above: pattern is set unless --patterns-file supplies them,
+        // and exactly one of text/file is.
+        let pattern = pattern.map(String::as_str).unwrap_or_default();
+        let line_mode = line_numbers || invert_match || count;
+
+        // Like grep with several files: every result line starts with the name
+        // of the file it came from.
+        if multiple_files {
+            let mut results = Vec::new();
+            let mut failed = false;
+            let (_, elapsed) = engines::timed(|| {
+                for file_name in &files {
+                    let text = match engines::read_decoded(file_name, encoding) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            eprintln!("Cannot read file {}: {}. Skipping it.", file_name, e);
+                            continue;
+                        }
+                    };
+                    if let Err(message) = limits.check_len(text.len()) {
+                        eprintln!("{}: {}. Skipping it.", file_name, message);
+                        continue;
+                    }
+                    if !line_mode {
+                        let result = if show_positions {
+                            engines::apply_pattern_with_positions(
+                                pattern,
+                                &text,
+                                &engine_choice,
+                                &limits,
+                                unit,
+                            )
+                        } else {
+                            engines::apply_pattern_limited(pattern, &text, &engine_choice, &limits)
+                        };
+                        results.push(format!("{}:{}", file_name, result));
+                        continue;
+                    }
+                    let lines = match engines::matching_lines(
+                        pattern,
+                        &text,
+                        &engine_choice,
+                        &limits,
+                        invert_match,
+                    ) {
+                        Ok(lines) => lines,
+                        // The other files' results still get written; the
+                        // exit status reports this one.
+                        Err(message) => {
+                            eprintln!("{}: {}. Skipping it.", file_name, message);
+                            failed = true;
+                            continue;
+                        }
+                    };
+                    if count {
+                        results.push(format!("{}:{}", file_name, lines.len()));
+                        continue;
+                    }
+                    for (line_no, line) in lines {
+                        results.push(if line_numbers {
+                            format!("{}:{}:{}", file_name, line_no, line)
+                        } else {
+                            format!("{}:{}", file_name, line)
+                        });
+                    }
+                }
+            });
+            write_output(output, &results.join("\n"), newline)?;
+            if timing {
+                eprintln!("{}", engines::timing_line(engine_str, elapsed));
+            }
+            if failed {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+
+        let streamed = line_mode && !has_context && encoding == UTF_8;
+        let (text, input_len) = match file {
+            // Line modes stream UTF-8 files below instead of reading them whole,
+            // except with context, which needs the lines around each result.
+            Some(file_name) if streamed => (
+                String::new(),
+                open_file(file_name).metadata()?.len() as usize,
+            ),
+            Some(file_name) => {
+                let text = read_file(file_name, encoding);
+                let len = text.len();
+                (text, len)
+            }
+            None => (text.unwrap().to_string(), text.unwrap().len()),
+        };
+        if let Err(message) = limits.check_len(input_len) {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+
+        // -n, -v and -c all switch to line-by-line matching. -v selects the
+        // non-matching lines instead, and -c reports how many lines were selected.
+        // Only the engine work is timed; reading input and writing output are not,
+        // except for streamed files, where reading and matching are interleaved.
+        let whole_text = || {
+            let result = if let Some(semantics) = semantics {
+                engines::apply_pattern_semantics(
+                    pattern,
+                    &text,
+                    &engine_choice,
+                    semantics,
+                    &limits,
+                    show_positions,
+                    unit,
+                )
+            } else if show_positions {
+                engines::apply_pattern_with_positions(pattern, &text, &engine_choice, &limits, unit)
+            } else {
+                engines::apply_pattern_limited(pattern, &text, &engine_choice, &limits)
+            };
+            // With color, follow the result with the text and its matches highlighted.
+            // Highlighting searches again without a step budget, so --max-steps skips it.
+            if !use_color || limits.max_steps.is_some() {
+                return result;
+            }
+            let ranges =
+                engines::match_ranges(pattern, &text, &engine_choice, &limits, limits.max_matches);
+            if ranges.is_empty() {
+                result
+            } else {
+                format!("{}\n{}", result, engines::colorize(&text, &ranges))
+            }
+        };
+        let mut repeat_timing = None;
+        // --first exits 1 when there is nothing to report.
+        let mut no_match = false;
+        let (result, elapsed) = if engine_str == "all" {
+            let ((results, agreement), elapsed) =
+                engines::timed(|| engines::compare_engines(pattern, &text));
+            let mut table: Vec<String> = results
+                .iter()
+                .map(|(name, result)| format!("{:<12} {}", name, result))
+                .collect();
+            table.push(format!(
+                "AGREEMENT: {}",
+                if agreement { "yes" } else { "no" }
+            ));
+            (table.join("\n"), elapsed)
+        } else if let Some(patterns) = &patterns {
+            if set {
+                engines::timed(|| engines::apply_pattern_set(patterns, &text))
+            } else {
+                engines::timed(|| engines::apply_patterns(patterns, &text, &engine_choice, &limits))
+            }
+        } else if line_mode {
+            let (lines, elapsed) = match file {
+                Some(file_name) if streamed => {
+                    let reader = BufReader::new(open_file(file_name));
+                    engines::timed(|| {
+                        engines::stream_matching_lines(
+                            pattern,
+                            reader,
+                            &engine_choice,
+                            &limits,
+                            invert_match,
+                        )
+                        .map(|lines| lines.collect::<io::Result<Vec<_>>>())
+                    })
+                }
+                // Otherwise the file was read whole above.
+                _ => engines::timed(|| {
+                    engines::matching_lines(pattern, &text, &engine_choice, &limits, invert_match)
+                        .map(|lines| {
+                            Ok(lines
+                                .into_iter()
+                                .map(|(line_no, line)| (line_no, line.to_string()))
+                                .collect())
+                        })
+                }),
+            };
+            let lines = lines.unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            })?;
+            let format_line = |line_no: usize, line: &str, selected: bool| {
+                // Inverted lines have no matches to color.
+                let line = if use_color && selected && !invert_match {
+                    let ranges =
+                        engines::match_ranges(pattern, line, &engine_choice, &limits, None);
+                    engines::colorize(line, &ranges)
+                } else {
+                    line.to_string()
+                };
+                // grep marks context lines with `-` instead of `:`.
+                match (line_numbers, selected) {
+                    (true, true) => format!("{}:{}", line_no, line),
+                    (true, false) => format!("{}-{}", line_no, line),
+                    (false, _) => line,
+                }
+            };
+            let result = if count {
+                lines.len().to_string()
+            } else if has_context {
+                let selected: Vec<usize> = lines.iter().map(|(line_no, _)| *line_no).collect();
+                engines::context_groups(&text, &selected, before.unwrap_or(0), after.unwrap_or(0))
+                    .iter()
+                    .map(|group| {
+                        group
+                            .iter()
+                            .map(|&(line_no, line, selected)| format_line(line_no, line, selected))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n--\n")
+            } else {
+                lines
+                    .iter()
+                    .map(|(line_no, line)| format_line(*line_no, line, true))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            (result, elapsed)
+        } else if groups {
+            engines::timed(
+                || match engines::capture_groups(pattern, &text, limits.max_matches) {
+                    Ok(found) if json => engines::groups_json(&found, &text, unit),
+                    Ok(found) => engines::format_groups(&found, &text, unit),
+                    Err(e) => format!("Invalid pattern: {}", e),
+                },
+            )
+        } else if extract {
+            let (lines, elapsed) =
+                engines::timed(|| engines::extract_json(pattern, &text, limits.max_matches));
+            let lines = lines.unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            });
+            (lines, elapsed)
+        } else if split {
+            let (fields, elapsed) =
+                engines::timed(|| engines::split_fields(pattern, &text, &limits));
+            let fields = fields.unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            });
+            (fields.join("\n"), elapsed)
+        } else if fixed {
+            engines::timed(|| engines::apply_fixed(pattern, &text, &limits, unit))
+        } else if first {
+            let (outcome, elapsed) =
+                engines::timed(|| engines::first_match(pattern, &text, &engine_choice, &limits));
+            no_match = !matches!(outcome, MatchOutcome::Matches(_));
+            let result = match &outcome {
+                MatchOutcome::Matches(ranges) => {
+                    let range = &ranges[0];
+                    let offsets = unit.convert(&text, range);
+                    format!(
+                        "First match: {:?}@[{}..{}]",
+                        &text[range.clone()],
+                        offsets.start,
+                        offsets.end
+                    )
+                }
+                other => other.describe(&text),
+            };
+            (result, elapsed)
+        } else if last {
+            engines::timed(|| {
+                engines::apply_pattern_last(pattern, &text, &engine_choice, &limits, unit)
+            })
+        } else if overlapping {
+            engines::timed(|| {
+                engines::apply_pattern_overlapping(pattern, &text, &engine_choice, &limits, unit)
+            })
+        } else if unique {
+            engines::timed(|| engines::unique_matches(pattern, &text, &engine_choice, &limits))
+        } else if let Some(template) = &format {
+            engines::timed(|| {
+                engines::matches_formatted(pattern, &text, &engine_choice, &limits, template, unit)
+            })
+        } else if csv {
+            let (rows, elapsed) = engines::timed(|| {
+                engines::matches_csv(pattern, &text, &engine_choice, &limits, unit)
+            });
+            let rows = rows.unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            });
+            (rows, elapsed)
+        } else if let Some(iterations) = repeat {
+            let run = engines::repeat_pattern(
+                pattern,
+                &text,
+                &engine_choice,
+                &limits,
+                iterations,
+                reuse_compiled,
+            )
+            .unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                process::exit(1);
+            });
+            let elapsed = run.per_iteration;
+            repeat_timing = Some(run);
+            (whole_text(), elapsed)
+        } else {
+            engines::timed(whole_text)
+        };
+
+        write_output(output, &result, newline)?;
+        if timing {
+            match &repeat_timing {
+                Some(run) => eprintln!("{}", engines::repeat_timing_line(engine_str, run)),
+                None => eprintln!("{}", engines::timing_line(engine_str, elapsed)),
+            }
+        }
+        if no_match {
+            process::exit(1);
+        }
         Ok(())
     }
 }
 
+/// Reads `-f FILE`, or reports why it can't be read and exits.
+fn read_file(path: &str, encoding: &'static Encoding) -> String {
+    engines::read_decoded(path, encoding).unwrap_or_else(|e| file_error(path, e))
+}
+
+/// Opens `-f FILE` for streaming, or reports why it can't be and exits.
+fn open_file(path: &str) -> fs::File {
+    fs::File::open(path).unwrap_or_else(|e| file_error(path, e))
+}
+
+/// `--ID` for the first of `ids` given on the command line.
+fn given_flag(matches: &ArgMatches, ids: &[&str]) -> Option<String> {
+    ids.iter()
+        .find(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .map(|id| format!("--{}", id))
+}
+
+/// The value of the environment variable named by `--ID VAR`, if that option
+/// was given. An unset or non-UTF-8 variable is reported and exits.
+fn env_arg(matches: &ArgMatches, id: &str) -> Option<String> {
+    let name = matches.get_one::<String>(id)?;
+    Some(env::var(name).unwrap_or_else(|e| {
+        eprintln!("--{} {}: {}", id, name, e);
+        process::exit(1);
+    }))
+}
+
+fn file_error(path: &str, e: io::Error) -> ! {
+    eprintln!("Cannot read file {}: {}", path, e);
+    process::exit(1);
+}
+
+/// Writes the result to `-o FILE` or stdout, followed by a newline unless
+/// `--no-newline` is set.
+fn write_output(output: Option<&String>, result: &str, newline: bool) -> Result<()> {
+    let end = if newline { "\n" } else { "" };
+    match output {
+        Some(output_file) => fs::write(output_file, format!("{}{}", result, end))?,
+        None => {
+            print!("{}{}", result, end);
+            io::stdout().flush()?;
+        }
+    }
+    Ok(())
+}
@@ -1,67 +1,2140 @@
-#[cfg(test)]
-mod tests {
-    use super::engines::{apply_pattern, EngineChoice};
+use crate::app::{
+    cursor_column, highlight_matches, list_directory, load_history, save_history, App, FilePicker,
+    InputMode,
+};
+use crate::builder::RegexerBuilder;
+use crate::config::{find_config, Config, CONFIG_FILE};
+use crate::custom_regex::{
+    ast_to_dfa, ast_tree, dfa_dot, dfa_table, explain, factor_alternatives, nfa_dot,
+    nfa_state_count, normalize, parse_pattern, regex_to_dfa, simplify, Automaton, CustomRegex,
+    MatchSemantics, RegexAST, DEFAULT_MAX_DFA_STATES,
+};
+use crate::engines::{
+    apply_pattern, apply_pattern_last, apply_pattern_limited, apply_pattern_set,
+    apply_pattern_with_positions, apply_patterns, bench_engines, bench_table, capture_groups,
+    compare_engines, compiles, context_groups, either_pattern, engine_info_table, extract_json,
+    first_match, format_groups, groups_json, is_implemented, is_match, load_patterns,
+    load_testcase, match_range_diff, match_ranges, match_set_split, matching_lines,
+    overlapping_ranges, pattern_with_flags, repeat_pattern, replace_matches, replace_ranges,
+    split_fields, stream_matching_lines, timed, timing_line, validate, EngineChoice, MatchOutcome,
+    MatchTemplate, OffsetUnit, SearchLimits, ENGINE_NAMES,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    style::{Color, Style},
+    text::Span,
+};
 
-    #[test]
-    fn test_builtin_engine_valid_pattern() {
-        let pattern = "ab.";
-        let text = "abc abx aby";
-        let result = apply_pattern(pattern, text, &EngineChoice::Builtin);
-        assert!(
-            result.contains("Matches: [\"abc\", \"abx\", \"aby\"]"),
-            "Expected three matches for 'ab.'"
+#[test]
+fn test_builtin_engine_valid_pattern() {
+    let pattern = "ab.";
+    let text = "abc abx aby";
+    let result = apply_pattern(pattern, text, &EngineChoice::Builtin);
+    assert_eq!(
+        result,
+        MatchOutcome::Matches(vec![0..3, 4..7, 8..11]),
+        "Expected three matches for 'ab.'"
+    );
+    assert_eq!(
+        result.describe(text),
+        "Matches: [\"abc\", \"abx\", \"aby\"]"
+    );
+}
+
+#[test]
+fn test_builtin_engine_invalid_pattern() {
+    let pattern = "("; // invalid pattern
+    let text = "abc";
+    let result = apply_pattern(pattern, text, &EngineChoice::Builtin);
+    assert!(
+        matches!(result, MatchOutcome::InvalidPattern(_)),
+        "Expected invalid pattern error."
+    );
+}
+
+#[test]
+fn test_custom_engine_with_valid_pattern() {
+    let pattern = "a";
+    let text = "abc a";
+    let result = apply_pattern(pattern, text, &EngineChoice::Custom);
+    assert!(
+        matches!(result, MatchOutcome::Matches(_)),
+        "Expected at least one match from CustomRegex."
+    );
+}
+
+#[test]
+fn test_custom_engine_with_invalid_pattern() {
+    let pattern = "(a"; // unmatched group is invalid in CustomRegex
+    let text = "abc";
+    let result = apply_pattern(pattern, text, &EngineChoice::Custom);
+    assert!(
+        matches!(result, MatchOutcome::InvalidPattern(_)),
+        "Expected invalid pattern error from CustomRegex."
+    );
+}
+
+#[test]
+fn test_custommeta_engine_fallback() {
+    let pattern = "z";
+    let text = "abc";
+    let result = apply_pattern(pattern, text, &EngineChoice::Custommeta);
+    assert_eq!(
+        result,
+        MatchOutcome::NoMatches,
+        "Expected no matches found on fallback."
+    );
+
+    let pattern2 = "a";
+    let text2 = "abca";
+    let result2 = apply_pattern(pattern2, text2, &EngineChoice::Custommeta);
+    assert_eq!(
+        result2,
+        MatchOutcome::Matches(vec![0..1, 3..4]),
+        "Expected matches from customMeta engine."
+    );
+}
+
+#[test]
+fn test_matching_lines_reports_line_numbers() {
+    let pattern = "ab";
+    let text = "abc\nxyz\nzab";
+    let result = matching_lines(
+        pattern,
+        text,
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        false,
+    );
+    assert_eq!(
+        result,
+        Ok(vec![(1, "abc"), (3, "zab")]),
+        "Expected only lines 1 and 3 to match."
+    );
+}
+
+#[test]
+fn test_is_implemented_flags_placeholders() {
+    assert!(is_implemented(&EngineChoice::Builtin));
+    assert!(is_implemented(&EngineChoice::Custom));
+    assert!(is_implemented(&EngineChoice::Custommeta));
+    assert!(
+        !is_implemented(&EngineChoice::Meta),
+        "Expected meta to be reported as a placeholder."
+    );
+    assert!(!is_implemented(&EngineChoice::Pikevm));
+}
+
+#[test]
+fn test_matching_lines_inverted() {
+    let pattern = "ab";
+    let text = "abc\nxyz\nzab\nqrs";
+    let result = matching_lines(
+        pattern,
+        text,
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        true,
+    );
+    assert_eq!(
+        result,
+        Ok(vec![(2, "xyz"), (4, "qrs")]),
+        "Expected only the non-matching lines 2 and 4."
+    );
+    let limits = SearchLimits::default();
+    assert!(
+        matching_lines("a(", text, &EngineChoice::Builtin, &limits, true).is_err(),
+        "Expected an invalid pattern to be an error, not to select every line."
+    );
+    assert!(matching_lines("a", text, &EngineChoice::Meta, &limits, true).is_err());
+}
+
+#[test]
+fn test_timing_keeps_match_output() {
+    let pattern = "ab.";
+    let text = "abc abx";
+    let (result, elapsed) = timed(|| apply_pattern(pattern, text, &EngineChoice::Dfa));
+    assert_eq!(
+        result,
+        apply_pattern(pattern, text, &EngineChoice::Dfa),
+        "Timing should not change the match output."
+    );
+    let line = timing_line("dfa", elapsed);
+    assert!(
+        line.starts_with("Engine dfa took ") && line.ends_with("ms"),
+        "Expected a timing line, got {line}"
+    );
+}
+
+#[test]
+fn test_compare_engines_builtin_and_custom_agree() {
+    let pattern = "ab";
+    let text = "xab ab";
+    let (results, agreement) = compare_engines(pattern, text);
+    let result_for = |engine: &str| {
+        results
+            .iter()
+            .find(|(name, _)| *name == engine)
+            .map(|(_, result)| result.clone())
+            .expect("engine missing from comparison")
+    };
+    assert_eq!(
+        result_for("builtin"),
+        result_for("custom"),
+        "Expected builtin and custom to agree on a literal pattern."
+    );
+    assert!(
+        results.iter().all(|(name, _)| *name != "meta"),
+        "Placeholder engines should be skipped."
+    );
+    assert!(agreement, "Expected all engines to agree.");
+}
+
+#[test]
+fn test_minimized_dfa_for_kleene_star() {
+    let dfa = regex_to_dfa("a*", DEFAULT_MAX_DFA_STATES).unwrap();
+    assert_eq!(
+        dfa.state_count(),
+        2,
+        "Expected a* to minimize to one accepting state plus the dead state."
+    );
+    for input in ["", "a", "aaa"] {
+        assert!(dfa.matches(input), "Expected a* to match {input:?}");
+    }
+    assert!(!dfa.matches("b"));
+}
+
+#[test]
+fn test_dfa_unseen_char_lands_in_dead_state() {
+    let dfa = regex_to_dfa("ab", DEFAULT_MAX_DFA_STATES).unwrap();
+    assert!(
+        dfa.is_dead(&dfa.run("z")),
+        "Expected a character outside the alphabet to reach the dead state."
+    );
+    assert!(dfa.is_dead(&dfa.run("azb")));
+    assert!(!dfa.is_dead(&dfa.run("a")));
+    assert!(dfa.matches("ab"));
+    assert!(!dfa.matches("abz"));
+}
+
+#[test]
+fn test_expression_selection_clamps_to_bounds() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.move_selection(1);
+    assert_eq!(
+        app.list_state.selected(),
+        None,
+        "Empty list has no selection."
+    );
+
+    app.set_pattern("a");
+    for text in ["a", "b", "c"] {
+        app.add_expression(text.to_string());
+    }
+    assert_eq!(
+        app.list_state.selected(),
+        Some(2),
+        "Newest entry is selected."
+    );
+
+    app.move_selection(1);
+    assert_eq!(
+        app.list_state.selected(),
+        Some(2),
+        "Selection stops at the end."
+    );
+
+    app.move_selection(-10);
+    assert_eq!(
+        app.list_state.selected(),
+        Some(0),
+        "Selection stops at the top."
+    );
+
+    app.move_selection(-1);
+    assert_eq!(app.list_state.selected(), Some(0));
+}
+
+#[test]
+fn test_delete_selected_expression_keeps_index_in_bounds() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("a");
+    for text in ["a", "b", "c"] {
+        app.add_expression(text.to_string());
+    }
+
+    // Deleting the last entry moves the selection up to the new last entry.
+    app.delete_selected_expression();
+    assert_eq!(app.expressions.len(), 2);
+    assert_eq!(app.list_state.selected(), Some(1));
+
+    app.move_selection(-1);
+    app.delete_selected_expression();
+    assert_eq!(app.expressions.len(), 1);
+    assert_eq!(app.list_state.selected(), Some(0));
+
+    app.delete_selected_expression();
+    assert!(app.expressions.is_empty());
+    assert_eq!(
+        app.list_state.selected(),
+        None,
+        "Empty list has no selection."
+    );
+}
+
+#[test]
+fn test_clear_expressions_keeps_pattern_and_input() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("a");
+    app.add_expression("abc".to_string());
+    app.add_expression("cab".to_string());
+    app.set_text("draft");
+
+    app.clear_expressions();
+    assert!(
+        app.expressions.is_empty(),
+        "Expected all expressions removed."
+    );
+    assert_eq!(app.list_state.selected(), None);
+    assert_eq!(app.pattern, "a");
+    assert_eq!(app.input, "draft");
+}
+
+#[test]
+fn test_highlight_matches_styles_matched_ranges() {
+    let text = "abcab";
+    let ranges = match_ranges(
+        "ab",
+        text,
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        None,
+    );
+    assert_eq!(ranges, vec![0..2, 3..5]);
+
+    let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let line = highlight_matches(text, &ranges);
+    assert_eq!(
+        line.spans,
+        vec![
+            Span::styled("ab", highlight),
+            Span::raw("c"),
+            Span::styled("ab", highlight),
+        ]
+    );
+}
+
+#[test]
+fn test_live_preview_reads_the_file_once() {
+    let path = std::env::temp_dir().join("regexer_test_preview_cache.txt");
+    std::fs::write(&path, "abcab").unwrap();
+    let mut app = App::new(EngineChoice::Custom);
+    app.set_file(Some(path.to_string_lossy().into_owned()));
+    app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('a')));
+    std::fs::remove_file(&path).unwrap();
+    app.handle_key(KeyEvent::from(KeyCode::Char('b')));
+    assert_eq!(
+        app.preview.unwrap().outcome,
+        Some(MatchOutcome::Matches(vec![0..2, 3..5]))
+    );
+}
+
+#[test]
+fn test_live_preview_skips_very_large_inputs() {
+    let mut app = App::new(EngineChoice::Custom);
+    app.set_text(&"ab".repeat(1 << 20));
+    app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('a')));
+    let preview = app.preview.clone().unwrap();
+    assert_eq!(preview.outcome, None);
+    assert!(preview.summary.contains("too large to preview"));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('(')));
+    assert!(matches!(
+        app.preview.unwrap().outcome,
+        Some(MatchOutcome::InvalidPattern(_))
+    ));
+}
+
+#[test]
+fn test_live_preview_limit_is_lower_for_the_custom_engines() {
+    let text = "a".repeat(8 << 10);
+    for (engine, previewed) in [
+        (EngineChoice::Builtin, true),
+        (EngineChoice::Custom, false),
+        (EngineChoice::Custommeta, false),
+    ] {
+        let mut app = App::new(engine);
+        app.set_text(&text);
+        app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+        app.handle_key(KeyEvent::from(KeyCode::Char('a')));
+        let preview = app.preview.unwrap();
+        assert_eq!(preview.outcome.is_some(), previewed, "{engine:?}");
+        if !previewed {
+            assert!(preview.summary.contains("over 4 KiB"), "{engine:?}");
+        }
+    }
+}
+
+#[test]
+fn test_live_preview_shows_invalid_pattern_error() {
+    let mut app = App::new(EngineChoice::Custom);
+    app.set_text("abcab");
+    app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+    for c in "(ab".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    let preview = app.preview.clone().unwrap();
+    assert!(
+        matches!(preview.outcome, Some(MatchOutcome::InvalidPattern(_))),
+        "Expected the preview to show the compile error, got {preview:?}"
+    );
+    assert!(preview.summary.starts_with("Invalid pattern:"));
+
+    app.handle_key(KeyEvent::from(KeyCode::Char(')')));
+    let preview = app.preview.clone().unwrap();
+    assert_eq!(
+        preview.outcome,
+        Some(MatchOutcome::Matches(vec![0..2, 3..5])),
+        "Expected a match once the group is closed, got {preview:?}"
+    );
+}
+
+#[test]
+fn test_compiles_checks_pattern_without_searching() {
+    assert!(compiles("abc", &EngineChoice::Builtin));
+    assert!(!compiles("(", &EngineChoice::Builtin));
+    assert!(compiles("abc", &EngineChoice::Custom));
+    assert!(
+        !compiles("(", &EngineChoice::Custom),
+        "Expected an unmatched group to fail in the custom engine."
+    );
+}
+
+#[test]
+fn test_word_wise_cursor_movement() {
+    let ctrl = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+    let mut app = App::new(EngineChoice::Builtin);
+    app.handle_key(KeyEvent::from(KeyCode::Char('t')));
+    for c in "foo   bar  baz".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    assert_eq!(app.character_index, 14);
+
+    app.handle_key(ctrl(KeyCode::Left));
+    assert_eq!(app.character_index, 11, "Expected the start of 'baz'.");
+    app.handle_key(ctrl(KeyCode::Left));
+    assert_eq!(app.character_index, 6, "Expected the start of 'bar'.");
+    app.handle_key(ctrl(KeyCode::Left));
+    app.handle_key(ctrl(KeyCode::Left));
+    assert_eq!(app.character_index, 0, "Expected to stop at the start.");
+
+    app.handle_key(ctrl(KeyCode::Right));
+    assert_eq!(app.character_index, 3, "Expected the end of 'foo'.");
+    app.handle_key(ctrl(KeyCode::Right));
+    assert_eq!(app.character_index, 9, "Expected the end of 'bar'.");
+
+    app.handle_key(KeyEvent::from(KeyCode::Home));
+    assert_eq!(app.character_index, 0);
+    app.handle_key(KeyEvent::from(KeyCode::End));
+    assert_eq!(app.character_index, 14);
+}
+
+#[test]
+fn test_ctrl_w_and_ctrl_u_delete_before_cursor() {
+    let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    let mut app = App::new(EngineChoice::Builtin);
+    app.handle_key(KeyEvent::from(KeyCode::Char('t')));
+    for c in "foo bar  baz".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+
+    app.handle_key(ctrl('w'));
+    assert_eq!(app.input, "foo bar  ");
+    assert_eq!(app.character_index, 9);
+
+    // Trailing whitespace goes with the word before it.
+    app.handle_key(ctrl('w'));
+    assert_eq!(app.input, "foo ");
+    assert_eq!(app.character_index, 4);
+
+    // Ctrl+U only removes what is left of the cursor.
+    app.handle_key(ctrl('u'));
+    assert_eq!(app.input, "");
+    for c in "abc def".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    app.handle_key(KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL));
+    app.handle_key(ctrl('u'));
+    assert_eq!(app.input, "def");
+    assert_eq!(app.character_index, 0);
+}
+
+#[test]
+fn test_engine_cycle_wraps_around() {
+    assert_eq!(EngineChoice::Builtin.next().name(), "custom");
+    assert_eq!(
+        EngineChoice::Custommeta.next().name(),
+        "builtin",
+        "Expected the last engine to wrap back to the first."
+    );
+
+    let mut engine = EngineChoice::Builtin;
+    for _ in 0..ENGINE_NAMES.len() {
+        engine = engine.next();
+    }
+    assert_eq!(engine.name(), "builtin");
+}
+
+#[test]
+fn test_history_round_trip() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("ab");
+    app.add_expression("abc".to_string());
+    app.add_expression("xyz".to_string());
+
+    let path = std::env::temp_dir().join("regexer_test_history.json");
+    let path = path.to_str().unwrap();
+    save_history(path, &app.expressions).unwrap();
+    let loaded = load_history(path);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded, app.expressions, "Expected history to round-trip.");
+}
+
+#[test]
+fn test_malformed_history_starts_empty() {
+    let path = std::env::temp_dir().join("regexer_test_malformed_history.json");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, "not json").unwrap();
+    let loaded = load_history(path);
+    std::fs::remove_file(path).unwrap();
+
+    assert!(loaded.is_empty());
+}
+
+#[test]
+fn test_history_drops_entries_with_invalid_ranges() {
+    let path = std::env::temp_dir().join("regexer_test_invalid_ranges_history.json");
+    let path = path.to_str().unwrap();
+    let entry = |text: &str, start: usize, end: usize| {
+        format!(
+            r#"{{"pattern":"a","text":"{text}","matches":"","ranges":[{{"start":{start},"end":{end}}}]}}"#
+        )
+    };
+    let entries = [
+        entry("ab", 0, 1),
+        entry("ab", 1, 5),
+        entry("é", 0, 1),
+        entry("ab", 2, 1),
+    ];
+    std::fs::write(path, format!("[{}]", entries.join(","))).unwrap();
+    let loaded = load_history(path);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.len(), 1, "Expected only the in-bounds entry kept.");
+}
+
+#[test]
+fn test_explain_renders_parse_tree() {
+    assert_eq!(explain("a|b").unwrap(), "Union(Literal(a), Literal(b))");
+    assert_eq!(
+        explain("ab*").unwrap(),
+        "Concat(Literal(a), Kleene(Literal(b)))",
+        "Expected * to bind tighter than concatenation."
+    );
+}
+
+#[test]
+fn test_dot_export_for_literal() {
+    let nfa = nfa_dot("a").unwrap();
+    assert!(nfa.contains("start -> 0;"), "Expected the NFA start node.");
+    assert!(
+        nfa.contains("1 [shape=doublecircle];"),
+        "Expected the NFA accept node."
+    );
+    assert!(
+        nfa.contains("0 -> 1 [label=\"a\"];"),
+        "Expected the a-labeled edge."
+    );
+
+    let dfa = dfa_dot("a", DEFAULT_MAX_DFA_STATES).unwrap();
+    assert!(dfa.contains("start -> q"), "Expected the DFA start node.");
+    assert!(
+        dfa.contains("[shape=doublecircle];"),
+        "Expected a DFA accept node."
+    );
+    assert!(
+        dfa.contains("[label=\"a\"];"),
+        "Expected the a-labeled edge."
+    );
+}
+
+#[test]
+fn test_custom_engine_handles_non_ascii_text() {
+    let result = apply_pattern("é", "café", &EngineChoice::Custom);
+    assert_eq!(result.describe("café"), "Matches: [\"é\"]");
+    assert_eq!(
+        match_ranges(
+            "é",
+            "café",
+            &EngineChoice::Custom,
+            &SearchLimits::default(),
+            None
+        ),
+        vec![3..5]
+    );
+
+    let result = apply_pattern("a", "naïve à la", &EngineChoice::Custom);
+    assert_eq!(
+        result,
+        MatchOutcome::Matches(vec![1..2, 11..12]),
+        "Expected ASCII patterns to work over non-ASCII text."
+    );
+}
+
+#[test]
+fn test_simplify_collapses_redundant_nodes() {
+    let lit = |c| Box::new(RegexAST::Literal(c));
+    let empty = || Box::new(RegexAST::Empty);
+
+    assert_eq!(
+        simplify(RegexAST::Concat(lit('a'), empty())),
+        RegexAST::Literal('a')
+    );
+    assert_eq!(
+        simplify(RegexAST::Concat(empty(), lit('a'))),
+        RegexAST::Literal('a')
+    );
+    assert_eq!(
+        simplify(RegexAST::Union(lit('a'), lit('a'))),
+        RegexAST::Literal('a')
+    );
+    // Nested redundancy collapses bottom-up: (a|a)* becomes a*.
+    assert_eq!(
+        simplify(RegexAST::Kleene(Box::new(RegexAST::Union(
+            lit('a'),
+            lit('a')
+        )))),
+        RegexAST::Kleene(lit('a'))
+    );
+    assert_eq!(
+        simplify(RegexAST::Union(lit('a'), lit('b'))),
+        RegexAST::Union(lit('a'), lit('b'))
+    );
+}
+
+#[test]
+fn test_empty_alternative_matches_empty_string() {
+    let dfa = regex_to_dfa("a|", DEFAULT_MAX_DFA_STATES).unwrap();
+    assert!(dfa.matches("a"));
+    assert!(
+        dfa.matches(""),
+        "Expected the empty alternative to accept ε."
+    );
+    assert!(!dfa.matches("aa"));
+}
+
+#[test]
+fn test_bench_prints_one_row_per_engine() {
+    let rows = bench_engines("ab", "abcab", 2);
+    let implemented = ENGINE_NAMES
+        .iter()
+        .filter(|name| is_implemented(&name.parse().unwrap()))
+        .count();
+    assert_eq!(
+        rows.len(),
+        implemented,
+        "Expected placeholders to be skipped."
+    );
+
+    let table = bench_table(&rows);
+    assert_eq!(
+        table.lines().count(),
+        implemented + 1,
+        "Header plus one row per engine."
+    );
+    assert!(table.lines().any(|line| line.starts_with("custom ")));
+    assert!(rows
+        .iter()
+        .all(|row| row.min <= row.median && row.median <= row.max));
+}
+
+#[test]
+fn test_search_limits_guard_input_size_and_match_count() {
+    let limits = SearchLimits {
+        max_matches: None,
+        max_input_bytes: Some(4),
+        max_steps: None,
+        max_dfa_states: None,
+    };
+    let result = apply_pattern_limited("a", "aaaaa", &EngineChoice::Custom, &limits);
+    assert!(
+        result.starts_with("Input too large:"),
+        "Expected the guard message instead of a search, got {result:?}"
+    );
+
+    let limits = SearchLimits {
+        max_matches: Some(2),
+        max_input_bytes: None,
+        max_steps: None,
+        max_dfa_states: None,
+    };
+    for engine in [
+        EngineChoice::Builtin,
+        EngineChoice::Custom,
+        EngineChoice::Custommeta,
+    ] {
+        let result = apply_pattern_limited("a", "aaaaa", &engine, &limits);
+        assert_eq!(result, "Matches: [\"a\", \"a\"] ... (truncated)");
+        assert_eq!(
+            match_ranges("a", "aaaaa", &engine, &limits, Some(3)),
+            vec![0..1, 1..2, 2..3]
+        );
+        let result =
+            apply_pattern_with_positions("a", "aaaaa", &engine, &limits, OffsetUnit::Bytes);
+        assert_eq!(
+            result,
+            "Matches: [\"a\"@[0..1], \"a\"@[1..2]] ... (truncated)"
         );
     }
+    // Exactly at the limit nothing was cut off.
+    let result = apply_pattern_limited("a", "xaxa", &EngineChoice::Builtin, &limits);
+    assert_eq!(result, "Matches: [\"a\", \"a\"]");
+}
 
-    #[test]
-    fn test_builtin_engine_invalid_pattern() {
-        let pattern = "("; // invalid pattern
-        let text = "abc";
-        let result = apply_pattern(pattern, text, &EngineChoice::Builtin);
-        assert!(
-            result.contains("Invalid pattern:"),
-            "Expected invalid pattern error."
+#[test]
+fn test_patterns_file_prints_a_section_per_pattern() {
+    let path = std::env::temp_dir().join("regexer_test_patterns.txt");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, "# comment\nab\n\nxy\n").unwrap();
+    let patterns = load_patterns(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(
+        patterns,
+        vec!["ab", "xy"],
+        "Expected comments and blanks skipped."
+    );
+
+    let output = apply_patterns(
+        &patterns,
+        "abc xyz",
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+    );
+    assert!(output.contains("== Pattern: ab ==\nMatches: [\"ab\"]"));
+    assert!(output.contains("== Pattern: xy ==\nMatches: [\"xy\"]"));
+}
+
+#[test]
+fn test_builtin_match_positions() {
+    assert_eq!(
+        match_ranges(
+            "a",
+            "aba",
+            &EngineChoice::Builtin,
+            &SearchLimits::default(),
+            None
+        ),
+        vec![0..1, 2..3],
+        "Expected matches starting at 0 and 2."
+    );
+    let result = apply_pattern_with_positions(
+        "a",
+        "aba",
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        OffsetUnit::Bytes,
+    );
+    assert_eq!(result, "Matches: [\"a\"@[0..1], \"a\"@[2..3]]");
+}
+
+#[test]
+fn test_empty_pattern_matches_zero_width_everywhere() {
+    let expected = "Matches: [\"\", \"\", \"\", \"\"]";
+    for engine in [
+        EngineChoice::Builtin,
+        EngineChoice::Custom,
+        EngineChoice::Custommeta,
+    ] {
+        assert_eq!(
+            apply_pattern("", "abc", &engine).describe("abc"),
+            expected,
+            "Expected a zero-width match at each of the 4 positions in \"abc\"."
+        );
+        assert_eq!(
+            match_ranges("", "abc", &engine, &SearchLimits::default(), None),
+            vec![0..0, 1..1, 2..2, 3..3]
         );
     }
+}
 
-    #[test]
-    fn test_custom_engine_with_valid_pattern() {
-        let pattern = "a";
-        let text = "abc a";
-        let result = apply_pattern(pattern, text, &EngineChoice::Custom);
-        assert!(
-            result.contains("Matches:"),
-            "Expected at least one match from CustomRegex."
+#[test]
+fn test_custommeta_uses_builtin_when_match_sets_differ() {
+    // CustomRegex reports every accepted substring ("a" and "ab"), while the
+    // builtin engine stops at the leftmost-first alternative ("a").
+    let pattern = "a|ab";
+    let text = "ab";
+    let custom = apply_pattern(pattern, text, &EngineChoice::Custom);
+    let builtin = apply_pattern(pattern, text, &EngineChoice::Builtin);
+    assert_ne!(custom, builtin, "Expected the engines to disagree.");
+
+    let diff = match_range_diff(text, &[0..1, 0..2], &[0..1]);
+    assert_eq!(
+        diff,
+        Some("only custom: [\"ab\"@[0..2]], only builtin: []".to_string())
+    );
+    assert_eq!(
+        apply_pattern(pattern, text, &EngineChoice::Custommeta),
+        builtin,
+        "Expected custommeta to fall back to the builtin result."
+    );
+    assert_eq!(
+        match_ranges(
+            pattern,
+            text,
+            &EngineChoice::Custommeta,
+            &SearchLimits::default(),
+            None
+        ),
+        vec![0..1]
+    );
+}
+
+#[test]
+fn test_custommeta_keeps_custom_result_when_ranges_agree() {
+    assert_eq!(
+        match_range_diff("abcab", &[0..2, 3..5], &[0..2, 3..5]),
+        None
+    );
+    assert_eq!(
+        apply_pattern("ab", "abcab", &EngineChoice::Custommeta),
+        apply_pattern("ab", "abcab", &EngineChoice::Custom)
+    );
+
+    // The same substrings at different positions are still a disagreement.
+    assert_eq!(
+        match_range_diff("aaaa", &[0..2, 1..3, 2..4], &[0..2, 2..4]),
+        Some("only custom: [\"aa\"@[1..3]], only builtin: []".to_string())
+    );
+    assert_eq!(
+        apply_pattern("aa", "aaaa", &EngineChoice::Custommeta),
+        MatchOutcome::Matches(vec![0..2, 2..4])
+    );
+}
+
+#[test]
+fn test_word_boundary_only_matches_whole_words() {
+    let regex = CustomRegex::new("\\bcat\\b").unwrap();
+    assert_eq!(regex.find_ranges("a cat sat"), vec![2..5]);
+    assert!(
+        regex.find_iter("category").is_empty(),
+        "Expected no match inside a longer word."
+    );
+    assert_eq!(regex.find_iter("cat"), vec!["cat"]);
+    assert_eq!(
+        apply_pattern("\\bcat\\b", "a cat, category", &EngineChoice::Custommeta),
+        apply_pattern("\\bcat\\b", "a cat, category", &EngineChoice::Builtin),
+        "Expected custommeta to agree with builtin."
+    );
+    assert!(CustomRegex::new("\\q").is_err());
+}
+
+#[test]
+fn test_factor_alternatives_pulls_out_common_prefixes() {
+    let factored = factor_alternatives(parse_pattern("cat|car|can").unwrap());
+    assert_eq!(
+        factored.to_string(),
+        "Concat(Literal(c), Concat(Literal(a), Union(Union(Literal(t), Literal(r)), Literal(n))))"
+    );
+}
+
+#[test]
+fn test_factor_alternatives_preserves_matches() {
+    let inputs = [
+        "", "c", "ca", "cat", "car", "can", "cab", "cats", "dog", "do", "catcar", "x", "ab", "abab",
+    ];
+    for pattern in [
+        "cat|car|can",
+        "cat|c|ca",
+        "a|ab|(ab)*|b",
+        "(dog|do)*|cat",
+        "ab|ab|x",
+    ] {
+        let ast = parse_pattern(pattern).unwrap();
+        let original = ast_to_dfa(&ast, DEFAULT_MAX_DFA_STATES).unwrap();
+        let factored = ast_to_dfa(&factor_alternatives(ast), DEFAULT_MAX_DFA_STATES).unwrap();
+        for input in inputs {
+            assert_eq!(
+                original.matches(input),
+                factored.matches(input),
+                "Factoring {pattern:?} changed whether {input:?} matches."
+            );
+        }
+    }
+}
+
+#[test]
+fn test_dfa_table_lists_every_state_and_transition() {
+    let table = dfa_table("ab", DEFAULT_MAX_DFA_STATES).unwrap();
+    let mut lines = table.lines();
+    assert_eq!(lines.next(), Some("start: q1"));
+    assert_eq!(lines.next(), Some("accept: q3"));
+    assert_eq!(lines.next(), Some("dead: q0"));
+    assert!(lines.next().unwrap().starts_with("state"));
+    let rows: Vec<Vec<&str>> = lines.map(|l| l.split_whitespace().collect()).collect();
+    // start, after `a`, after `ab` and dead, each with a row for `a` and `b`.
+    assert_eq!(rows.len(), 8, "Unexpected table:\n{table}");
+    let states: std::collections::BTreeSet<&str> = rows.iter().map(|r| r[0]).collect();
+    assert_eq!(states.len(), 4);
+    assert!(
+        rows.contains(&vec!["q1", "a", "q2"]),
+        "Unexpected table:\n{table}"
+    );
+}
+
+#[test]
+fn test_load_testcase_splits_pattern_from_text() {
+    let path = std::env::temp_dir().join("regexer_test_testcase.txt");
+    std::fs::write(&path, "a|b\r\nab\nba\n").unwrap();
+    assert_eq!(
+        load_testcase(path.to_str().unwrap()).unwrap(),
+        ("a|b".to_string(), "ab\nba\n".to_string())
+    );
+    std::fs::write(&path, "a|b").unwrap();
+    assert_eq!(
+        load_testcase(path.to_str().unwrap()).unwrap(),
+        ("a|b".to_string(), String::new()),
+        "Expected a pattern-only fixture to have empty text."
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_stream_matching_lines_reads_line_by_line() {
+    let text: String = (1..=1000)
+        .map(|i| {
+            if i % 250 == 0 {
+                format!("line {i} has ab\n")
+            } else {
+                format!("line {i}\n")
+            }
+        })
+        .collect();
+    let streamed: Vec<(usize, String)> = stream_matching_lines(
+        "ab",
+        std::io::Cursor::new(text.as_bytes()),
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        false,
+    )
+    .unwrap()
+    .collect::<std::io::Result<_>>()
+    .unwrap();
+    assert_eq!(
+        streamed.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+        vec![250, 500, 750, 1000]
+    );
+    let buffered: Vec<(usize, String)> = matching_lines(
+        "ab",
+        &text,
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        false,
+    )
+    .unwrap()
+    .into_iter()
+    .map(|(n, line)| (n, line.to_string()))
+    .collect();
+    assert_eq!(streamed, buffered, "Expected streaming to match buffered.");
+
+    let inverted = stream_matching_lines(
+        "ab",
+        std::io::Cursor::new(text.as_bytes()),
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        true,
+    )
+    .unwrap()
+    .count();
+    assert_eq!(inverted, 996);
+}
+
+#[test]
+fn test_compiled_regexer_is_cached_until_the_pattern_changes() {
+    let mut app = App::new(EngineChoice::Custom);
+    app.set_pattern("ab");
+    let first = app.compiled_regexer().unwrap();
+    app.add_expression("xab".to_string());
+    let second = app.compiled_regexer().unwrap();
+    assert!(
+        std::sync::Arc::ptr_eq(&first, &second),
+        "Expected the unchanged pattern to reuse the compiled matcher."
+    );
+    assert_eq!(app.expressions.len(), 1);
+
+    app.set_pattern("b");
+    let third = app.compiled_regexer().unwrap();
+    assert!(!std::sync::Arc::ptr_eq(&second, &third));
+    app.set_pattern("(");
+    assert!(app.compiled_regexer().is_none());
+}
+
+#[test]
+fn test_apply_pattern_set_lists_only_matching_patterns() {
+    let patterns = vec!["ab".to_string(), "z+".to_string(), "c$".to_string()];
+    assert_eq!(
+        apply_pattern_set(&patterns, "xabc"),
+        "Matched patterns:\n0: ab\n2: c$"
+    );
+    assert_eq!(apply_pattern_set(&patterns, "q"), "No patterns matched.");
+    assert!(apply_pattern_set(&["(".to_string()], "q").starts_with("Invalid pattern:"));
+}
+
+#[test]
+fn test_question_mark_toggles_help_overlay() {
+    let mut app = App::new(EngineChoice::Builtin);
+    let key = |c| KeyEvent::from(KeyCode::Char(c));
+    assert!(!app.handle_key(key('?')));
+    assert!(app.show_help);
+    // Other keys are ignored while the overlay is open, including q.
+    assert!(!app.handle_key(key('q')));
+    assert!(!app.handle_key(key('p')));
+    assert!(app.show_help);
+    assert!(app.input.is_empty());
+    app.handle_key(key('?'));
+    assert!(!app.show_help);
+
+    app.handle_key(key('?'));
+    assert!(!app.handle_key(KeyEvent::from(KeyCode::Esc)));
+    assert!(
+        !app.show_help,
+        "Expected Esc to close the overlay, not exit."
+    );
+}
+
+#[test]
+fn test_lazy_quantifiers_match_leftmost_shortest() {
+    let greedy = CustomRegex::new("a+").unwrap();
+    assert!(greedy.find_iter("aaa").contains(&"aaa"));
+    let lazy = CustomRegex::new("a+?").unwrap();
+    assert_eq!(lazy.find_iter("aaa"), vec!["a", "a", "a"]);
+    assert_eq!(
+        CustomRegex::new("ba*?").unwrap().find_iter("baab"),
+        vec!["b", "b"]
+    );
+    assert_eq!(
+        apply_pattern("a+?", "aaa", &EngineChoice::Custom),
+        apply_pattern("a+?", "aaa", &EngineChoice::Builtin),
+        "Expected a+? to agree with the builtin engine."
+    );
+    assert_eq!(explain("a??").unwrap(), "Lazy(Optional(Literal(a)))");
+    assert!(CustomRegex::new("b?a")
+        .unwrap()
+        .find_iter("a")
+        .contains(&"a"));
+}
+
+#[test]
+fn test_capture_groups_are_labelled_by_name() {
+    let text = "2024-01 1999-";
+    let found = capture_groups(r"(?P<year>\d{4})-(\d\d)?", text, None).unwrap();
+    assert_eq!(found.len(), 2);
+    let formatted = format_groups(&found, text, OffsetUnit::Bytes);
+    assert!(
+        formatted.contains("  year: \"2024\"@[0..4]"),
+        "Expected the named group by name, got {formatted:?}"
+    );
+    assert!(formatted.contains("  2: (no match)"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&groups_json(&found, text, OffsetUnit::Bytes)).unwrap();
+    assert_eq!(json[0]["year"]["text"], "2024");
+    assert_eq!(json[0]["year"]["start"], 0);
+    assert_eq!(json[1]["2"], serde_json::Value::Null);
+    assert!(capture_groups("(", text, None).is_err());
+}
+
+#[test]
+fn test_engine_info_table_has_a_row_per_engine() {
+    let table = engine_info_table();
+    assert_eq!(table.lines().count(), ENGINE_NAMES.len() + 1);
+    for name in ENGINE_NAMES {
+        let row = table
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(name))
+            .unwrap_or_else(|| panic!("Expected a row for {name}"));
+        let implemented = row.split_whitespace().nth(1).unwrap();
+        assert_eq!(
+            implemented == "yes",
+            is_implemented(&name.parse().unwrap()),
+            "Row disagrees with is_implemented: {row:?}"
         );
     }
+}
 
-    #[test]
-    fn test_custom_engine_with_invalid_pattern() {
-        let pattern = ""; // empty pattern is considered invalid in CustomRegex
-        let text = "abc";
-        let result = apply_pattern(pattern, text, &EngineChoice::Custom);
-        assert!(
-            result.contains("Invalid pattern:"),
-            "Expected invalid pattern error from CustomRegex."
+#[test]
+fn test_match_set_split_lists_matches_unique_to_each_engine() {
+    let (only_custom, only_builtin) = match_set_split(&["a", "aa", "a", "b"], &["aa", "c", "c"]);
+    assert_eq!(only_custom, vec!["a", "b"]);
+    assert_eq!(only_builtin, vec!["c"]);
+    assert_eq!(match_set_split(&["a"], &["a"]), (vec![], vec![]));
+}
+
+#[test]
+fn test_v_toggles_diff_view_for_selected_expression() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("a*");
+    app.add_expression("aa".to_string());
+    app.handle_key(KeyEvent::from(KeyCode::Char('v')));
+    let diff = app.diff_view.clone().unwrap();
+    assert_eq!(diff.pattern, "a*");
+    assert_eq!(diff.builtin, Ok(vec!["aa".to_string()]));
+    assert!(diff.custom.unwrap().contains(&"a".to_string()));
+    app.handle_key(KeyEvent::from(KeyCode::Char('v')));
+    assert!(app.diff_view.is_none());
+}
+
+#[test]
+fn test_groups_match_like_their_contents() {
+    let grouped = CustomRegex::new("(ab)*c").unwrap();
+    let plain = CustomRegex::new("ab*c").unwrap();
+    assert_eq!(grouped.find_iter("ababc"), vec!["ababc", "abc", "c"]);
+    assert_eq!(plain.find_iter("abbc"), vec!["abbc"]);
+    assert_eq!(
+        CustomRegex::new("(ab)").unwrap().find_iter("xab"),
+        CustomRegex::new("ab").unwrap().find_iter("xab")
+    );
+}
+
+#[test]
+fn test_handle_event_routes_keys_and_ignores_resizes() {
+    use ratatui::crossterm::event::Event;
+    let mut app = App::new(EngineChoice::Builtin);
+    assert!(!app.handle_event(Event::Resize(80, 24)));
+    assert!(!app.handle_event(Event::FocusGained));
+    assert!(!app.handle_event(Event::Key(KeyEvent::from(KeyCode::Char('?')))));
+    assert!(app.show_help, "Expected the key to reach handle_key.");
+    assert!(app.handle_event(Event::Key(KeyEvent::new(
+        KeyCode::Char('c'),
+        KeyModifiers::CONTROL
+    ))));
+}
+
+#[test]
+fn test_status_line_reports_engine_history_and_last_match_count() {
+    let mut app = App::new(EngineChoice::Builtin);
+    assert_eq!(
+        app.status_line(),
+        "Engine: builtin | Expressions: 0 | Last matches: -"
+    );
+    app.set_pattern("a");
+    app.add_expression("xyz".to_string());
+    app.add_expression("aba".to_string());
+    assert_eq!(
+        app.status_line(),
+        "Engine: builtin | Expressions: 2 | Last matches: 2"
+    );
+}
+
+#[test]
+fn test_either_pattern_matches_what_neither_pattern_does_alone() {
+    let text = "cat dog";
+    for engine in [EngineChoice::Builtin, EngineChoice::Custom] {
+        assert_eq!(
+            apply_pattern("cat", text, &engine).describe(text),
+            "Matches: [\"cat\"]"
+        );
+        assert_eq!(
+            apply_pattern("dog", text, &engine).describe(text),
+            "Matches: [\"dog\"]"
+        );
+        let either = either_pattern("cat", "dog", &engine).unwrap();
+        assert_eq!(
+            apply_pattern(&either, text, &engine),
+            MatchOutcome::Matches(vec![0..3, 4..7])
+        );
+    }
+    assert_eq!(
+        explain(&either_pattern("a|b", "c", &EngineChoice::Custom).unwrap()).unwrap(),
+        format!(
+            "Union(Group({}), Group({}))",
+            explain("a|b").unwrap(),
+            explain("c").unwrap()
+        )
+    );
+}
+
+#[test]
+fn test_either_pattern_rejects_halves_that_only_compile_together() {
+    let custom = either_pattern("c", "a)(b", &EngineChoice::Custom).unwrap_err();
+    assert!(custom.starts_with("Invalid --or pattern"), "Got {custom:?}");
+    let custom = either_pattern("a(", "c", &EngineChoice::Custom).unwrap_err();
+    assert!(custom.starts_with("Invalid pattern"), "Got {custom:?}");
+    let builtin = either_pattern("c", "a)|(?:b", &EngineChoice::Builtin).unwrap_err();
+    assert!(
+        builtin.starts_with("Invalid --or pattern"),
+        "Got {builtin:?}"
+    );
+    // custommeta falls back to the builtin syntax when the custom parser rejects a side.
+    assert_eq!(
+        either_pattern("[ab]", "c", &EngineChoice::Custommeta),
+        Ok("(?:[ab])|(?:c)".to_string())
+    );
+}
+
+#[test]
+fn test_list_directory_puts_parent_and_directories_first() {
+    let dir = std::env::temp_dir().join("regexer_test_picker");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("b.txt"), "xab").unwrap();
+    std::fs::write(dir.join("a.txt"), "").unwrap();
+
+    let entries = list_directory(&dir).unwrap();
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["..", "sub", "a.txt", "b.txt"]);
+    assert!(entries[1].is_dir && !entries[2].is_dir);
+    assert!(list_directory(&dir.join("missing")).is_err());
+
+    // Picking b.txt sets the file and re-runs the pattern on it.
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("ab");
+    app.file_picker = Some(FilePicker::open(dir.clone()));
+    for code in [KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Enter] {
+        app.handle_key(KeyEvent::from(code));
+    }
+    assert!(app.file_picker.is_none());
+    assert_eq!(app.file.as_deref(), dir.join("b.txt").to_str());
+    wait_for_pending(&mut app);
+    assert_eq!(
+        app.status_line(),
+        "Engine: builtin | Expressions: 1 | Last matches: 1"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_unreadable_file_shows_in_status_bar() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_file(Some("/nonexistent/regexer-input.txt".to_string()));
+    app.handle_key(KeyEvent::from(KeyCode::Char('p')));
+    app.handle_key(KeyEvent::from(KeyCode::Char('a')));
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert!(app.expressions.is_empty());
+    assert!(
+        app.status_line()
+            .ends_with("| Cannot read file /nonexistent/regexer-input.txt: No such file or directory (os error 2)"),
+        "Unexpected status: {}",
+        app.status_line()
+    );
+}
+
+#[test]
+fn test_inline_case_insensitive_flag() {
+    let regex = CustomRegex::new("(?i)abc").unwrap();
+    assert_eq!(regex.find_iter("ABC abc aBc"), vec!["ABC", "abc", "aBc"]);
+
+    // A scoped flag only covers its own group.
+    let scoped = CustomRegex::new("(?i:a)b").unwrap();
+    assert_eq!(scoped.find_iter("Ab AB ab"), vec!["Ab", "ab"]);
+    let rest_of_group = CustomRegex::new("(a(?i)b)c").unwrap();
+    assert_eq!(rest_of_group.find_iter("aBc aBC"), vec!["aBc"]);
+
+    // An uppercase literal also matches its lowercase form.
+    let upper = CustomRegex::new("(?i)É").unwrap();
+    assert_eq!(upper.find_iter("é É"), vec!["é", "É"]);
+    assert_eq!(normalize("(?i)É").unwrap(), "(?i:É)");
+
+    assert!(CustomRegex::new("(?ms)ab").is_ok());
+    let err = CustomRegex::new("(?x)ab").err().unwrap();
+    assert!(err.to_string().contains("Unknown flag 'x'"), "{err}");
+    assert!(CustomRegex::new("(?i").is_err());
+}
+
+#[test]
+fn test_rfind_returns_the_last_match() {
+    let regex = CustomRegex::new("ab*").unwrap();
+    assert_eq!(regex.rfind("abb xab"), Some((5, 7)));
+    assert_eq!(
+        regex.rfind("abb xab"),
+        regex.find_ranges("abb xab").pop().map(|r| (r.start, r.end))
+    );
+    assert_eq!(regex.rfind("xyz"), None);
+    assert_eq!(
+        apply_pattern_last(
+            "z",
+            "abc",
+            &EngineChoice::Builtin,
+            &SearchLimits::default(),
+            OffsetUnit::Bytes
+        ),
+        "No matches found."
+    );
+}
+
+#[test]
+fn test_match_at_returns_longest_match_end() {
+    let regex = CustomRegex::new("a*").unwrap();
+    assert_eq!(regex.match_at("aaab", 0), Some(3));
+    assert_eq!(regex.match_at("aaab", 3), Some(3));
+    assert_eq!(regex.match_at("aaab", 4), Some(4));
+    assert_eq!(regex.match_at("aaab", 5), None);
+
+    let regex = CustomRegex::new("ab|abc").unwrap();
+    assert_eq!(regex.match_at("xabcd", 1), Some(4));
+    assert_eq!(regex.match_at("xabcd", 0), None);
+
+    let regex = CustomRegex::new("\\bab\\b").unwrap();
+    assert_eq!(regex.match_at("ab abc", 0), Some(2));
+    assert_eq!(regex.match_at("ab abc", 3), None);
+}
+
+#[test]
+fn test_engine_choice_from_str() {
+    assert_eq!("custom".parse(), Ok(EngineChoice::Custom));
+    assert_eq!("CustomMeta".parse(), Ok(EngineChoice::Custommeta));
+    for name in ENGINE_NAMES {
+        assert_eq!(name.parse::<EngineChoice>().map(|e| e.name()), Ok(name));
+    }
+    let err = "bultin".parse::<EngineChoice>().unwrap_err();
+    assert!(err.contains("'bultin'"), "Expected the bad name in {err:?}");
+    assert!(
+        err.contains("builtin, custom"),
+        "Expected the valid names in {err:?}"
+    );
+}
+
+#[test]
+fn test_match_template_parsing() {
+    let template = MatchTemplate::parse("{{{index}}} {match}").unwrap();
+    assert_eq!(template.render(2, "xab", 1..3, OffsetUnit::Bytes), "{2} ab");
+    assert!(MatchTemplate::parse("{match").is_err());
+    assert!(MatchTemplate::parse("match}").is_err());
+    assert!(MatchTemplate::parse("{Match}").is_err());
+}
+
+#[test]
+fn test_selected_matches_is_what_y_copies() {
+    let mut app = App::new(EngineChoice::Builtin);
+    assert_eq!(app.selected_matches(), None);
+    app.set_pattern("a");
+    app.add_expression("abca".to_string());
+    app.add_expression("xyz".to_string());
+    assert_eq!(app.selected_matches(), Some("No matches found."));
+    app.move_selection(-1);
+    assert_eq!(app.selected_matches(), Some("Matches: [\"a\", \"a\"]"));
+}
+
+#[test]
+fn test_pattern_with_flags() {
+    assert_eq!(pattern_with_flags("a.b", false, false, false), "a.b");
+    assert_eq!(pattern_with_flags("^a", true, false, false), "(?m)^a");
+    assert_eq!(pattern_with_flags("a.b", true, true, false), "(?ms)a.b");
+    assert_eq!(pattern_with_flags("a.b", true, true, true), "(?ims)a.b");
+    assert!(CustomRegex::new(&pattern_with_flags("ab", true, true, false)).is_ok());
+    assert!(CustomRegex::new(&pattern_with_flags("ab", true, true, true)).is_ok());
+}
+
+#[test]
+fn test_find_overlapping_takes_the_shortest_match_per_start() {
+    let regex = CustomRegex::new("ab*").unwrap();
+    assert_eq!(regex.find_overlapping("abbab"), vec![0..1, 3..4]);
+    let regex = CustomRegex::new("a*").unwrap();
+    assert_eq!(regex.find_overlapping("aab"), vec![0..1, 1..2]);
+}
+
+#[test]
+fn test_overlapping_ranges_agree_across_engines() {
+    for engine in [
+        EngineChoice::Builtin,
+        EngineChoice::Dfa,
+        EngineChoice::Custom,
+        EngineChoice::Custommeta,
+    ] {
+        let limits = SearchLimits::default();
+        assert_eq!(
+            overlapping_ranges("ab*", "abbab", &engine, &limits, None),
+            vec![0..1, 3..4],
+            "{engine:?}"
+        );
+        assert_eq!(
+            overlapping_ranges("aa", "aaaa", &engine, &limits, None),
+            vec![0..2, 1..3, 2..4],
+            "{engine:?}"
+        );
+        assert_eq!(
+            overlapping_ranges("aa", "aaaa", &engine, &limits, Some(2)),
+            vec![0..2, 1..3],
+            "{engine:?}"
+        );
+    }
+    // A Unicode `\b` next to non-ASCII text, which the lazy DFA cannot search.
+    assert_eq!(
+        overlapping_ranges(
+            r"\bé",
+            "éa é",
+            &EngineChoice::Builtin,
+            &SearchLimits::default(),
+            None
+        ),
+        vec![0..2, 4..6]
+    );
+}
+
+#[test]
+fn test_background_match_result_arrives_over_the_channel() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("ab");
+    app.start_expression("xabab".to_string());
+    assert!(app.status_line().contains("Matching... (Esc to cancel)"));
+    wait_for_pending(&mut app);
+    assert_eq!(app.selected_matches(), Some("Matches: [\"ab\", \"ab\"]"));
+
+    app.start_expression("ab".to_string());
+    app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert!(app.pending.is_none());
+    assert!(app.status_line().ends_with("| Match cancelled"));
+    assert_eq!(app.expressions.len(), 1, "A cancelled match adds nothing.");
+}
+
+#[test]
+fn test_submissions_are_rejected_while_a_match_runs() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.set_pattern("ab");
+    app.start_expression("xabab".to_string());
+    app.start_expression("ab".to_string());
+    assert!(app
+        .status_line()
+        .ends_with("| A match is still running (Esc to cancel)"));
+    wait_for_pending(&mut app);
+    assert_eq!(app.expressions.len(), 1);
+    assert_eq!(app.selected_matches(), Some("Matches: [\"ab\", \"ab\"]"));
+}
+
+#[test]
+fn test_find_iter_with_builds_the_pike_vm_once() {
+    let regex = CustomRegex::new("a|ab").unwrap();
+    assert!(!regex.has_pike_vm());
+    regex.find_iter_with("ab", MatchSemantics::LeftmostLongest);
+    assert!(!regex.has_pike_vm(), "Leftmost-longest needs no PikeVM.");
+    let first = regex.find_iter_with("abab", MatchSemantics::LeftmostFirst);
+    assert!(regex.has_pike_vm());
+    assert_eq!(first, vec![0..1, 2..3]);
+    assert_eq!(
+        regex.find_iter_with("abab", MatchSemantics::LeftmostFirst),
+        first
+    );
+    assert_eq!(
+        regex.find_iter_with_up_to("abab", MatchSemantics::LeftmostFirst, Some(1)),
+        vec![0..1]
+    );
+}
+
+#[test]
+fn test_cancelled_searches_give_up() {
+    let cancel = std::sync::atomic::AtomicBool::new(true);
+    let text = "ab".repeat(100);
+    let custom = CustomRegex::new("(a|b)*").unwrap();
+    assert_eq!(custom.find_ranges_cancellable(&text, &cancel), None);
+    for engine in [
+        EngineChoice::Builtin,
+        EngineChoice::Custom,
+        EngineChoice::Custommeta,
+    ] {
+        let regexer = RegexerBuilder::new("ab").engine(engine).build().unwrap();
+        assert_eq!(
+            regexer.find_ranges_cancellable(&text, &cancel),
+            None,
+            "{engine:?}"
+        );
+        let running = std::sync::atomic::AtomicBool::new(false);
+        assert_eq!(
+            regexer.find_ranges_cancellable(&text, &running),
+            Some(regexer.find_ranges(&text)),
+            "{engine:?}"
+        );
+    }
+}
+
+/// Polls until the app's background match has reported back.
+fn wait_for_pending(app: &mut App) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while app.pending.is_some() && std::time::Instant::now() < deadline {
+        app.poll_pending();
+        std::thread::yield_now();
+    }
+    assert!(app.pending.is_none(), "Expected the worker to report back.");
+}
+
+#[test]
+fn test_to_pattern_round_trips() {
+    for pattern in [
+        "",
+        "abc",
+        "a(bc)",
+        "ab|cd|ef",
+        "a|(b|c)",
+        "(a|b)*c",
+        "a+b?",
+        "a*?b",
+        "a??",
+        "(ab)+?",
+        "a*??",
+        "\\bfoo\\b",
+        "(?i)ab",
+        "a(?i:b)c",
+        "((a))",
+        "x(?:yz)w",
+        "é*|ü",
+        "a|",
+    ] {
+        let ast = parse_pattern(pattern).unwrap();
+        let source = ast.to_pattern();
+        assert_eq!(
+            parse_pattern(&source).unwrap(),
+            ast,
+            "{pattern:?} came back as {source:?}"
+        );
+    }
+    assert_eq!(parse_pattern("a|bc*").unwrap().to_pattern(), "a|bc*");
+    assert_eq!(parse_pattern("(?:a|b)c").unwrap().to_pattern(), "(?:a|b)c");
+    assert_eq!(normalize("(?:a)(?:aa*)").unwrap(), "aa+");
+    assert_eq!(normalize("a|").unwrap(), "a|(?)");
+}
+
+#[test]
+fn test_context_groups_merge_overlapping_windows() {
+    let text = "a\nb\nc\nd\ne\nf\ng\nh";
+    let groups = context_groups(text, &[2, 4, 8], 1, 1);
+    let numbers: Vec<Vec<usize>> = groups
+        .iter()
+        .map(|group| group.iter().map(|(line_no, _, _)| *line_no).collect())
+        .collect();
+    assert_eq!(numbers, vec![vec![1, 2, 3, 4, 5], vec![7, 8]]);
+    assert_eq!(groups[1], vec![(7, "g", false), (8, "h", true)]);
+}
+
+#[test]
+fn test_generate_is_bounded_and_respects_boundaries() {
+    let regex = CustomRegex::new("a*").unwrap();
+    assert_eq!(regex.generate(3, 10), vec!["", "a", "aa"]);
+    assert_eq!(regex.generate(100, 2), vec!["", "a", "aa"]);
+    let regex = CustomRegex::new("\\ba(b|\\b)").unwrap();
+    assert_eq!(regex.generate(5, 5), vec!["a", "ab"]);
+}
+
+#[test]
+fn test_lazy_dfa_only_builds_the_states_it_visits() {
+    let regex = CustomRegex::new_lazy("ab|cd|ef").unwrap();
+    let lazy = regex.lazy_dfa().unwrap();
+    assert_eq!(lazy.materialized_states(), 1);
+    assert_eq!(regex.match_at("ab", 0), Some(2));
+    // The start state, then one after `a` and one after `ab`; `c` and `e`
+    // were never followed.
+    assert_eq!(lazy.materialized_states(), 3);
+    assert!(
+        ast_to_dfa(&parse_pattern("ab|cd|ef").unwrap(), DEFAULT_MAX_DFA_STATES)
+            .unwrap()
+            .state_count()
+            > 3
+    );
+}
+
+#[test]
+fn test_lazy_dfa_matches_like_the_eager_one() {
+    let text = "ab abb cab, a_b ba";
+    for pattern in ["ab*", "a|b", "(a|b)*", "\\bab", "b\\b", "a*?b", ""] {
+        assert_eq!(
+            CustomRegex::new_lazy(pattern).unwrap().find_ranges(text),
+            CustomRegex::new(pattern).unwrap().find_ranges(text),
+            "Lazy and eager DFAs disagree on {pattern:?}"
+        );
+    }
+}
+
+#[test]
+fn test_shorthand_classes() {
+    let digits = CustomRegex::new("\\d\\d").unwrap();
+    assert_eq!(digits.find_ranges("42"), vec![0..2]);
+    assert!(digits.find_ranges("4a").is_empty());
+    let word = CustomRegex::new("\\w").unwrap();
+    for text in ["q", "7", "_"] {
+        assert_eq!(
+            word.find_ranges(text),
+            vec![0..1],
+            "\\w should match {text:?}"
         );
     }
+    assert!(word.find_ranges("-").is_empty());
+    assert_eq!(
+        CustomRegex::new("\\s").unwrap().find_ranges("a\tb"),
+        vec![1..2]
+    );
+}
+
+#[test]
+fn test_negated_classes_take_non_ascii() {
+    let regex = CustomRegex::new("a\\Wb").unwrap();
+    assert_eq!(regex.find_ranges("a-b aéb a_b"), vec![0..3, 4..8]);
+    assert_eq!(
+        CustomRegex::new("\\D").unwrap().find_ranges("1é2"),
+        vec![1..3]
+    );
+    assert!(CustomRegex::new("\\S")
+        .unwrap()
+        .find_ranges(" \n")
+        .is_empty());
+    assert_eq!(parse_pattern("\\d").unwrap().to_pattern(), "\\d");
+}
 
-    #[test]
-    fn test_custommeta_engine_fallback() {
-        let pattern = "z";
-        let text = "abc";
-        let result = apply_pattern(pattern, text, &EngineChoice::Custommeta);
-        assert!(
-            result.contains("No matches found."),
-            "Expected no matches found on fallback."
+#[test]
+fn test_lazy_dfa_agrees_on_classes() {
+    let text = "ab1 c_2, é9 x";
+    for pattern in ["\\w\\d", "\\W", "\\D*", "\\s\\S", "a|\\d"] {
+        assert_eq!(
+            CustomRegex::new_lazy(pattern).unwrap().find_ranges(text),
+            CustomRegex::new(pattern).unwrap().find_ranges(text),
+            "Lazy and eager DFAs disagree on {pattern:?}"
         );
+    }
+}
+
+#[test]
+fn test_tree_lines_indents_children_under_parents() {
+    let ast = RegexAST::Concat(
+        Box::new(RegexAST::Literal('a')),
+        Box::new(RegexAST::Kleene(Box::new(RegexAST::Group(Box::new(
+            RegexAST::Union(
+                Box::new(RegexAST::Literal('b')),
+                Box::new(RegexAST::Class('d')),
+            ),
+        ))))),
+    );
+    assert_eq!(
+        ast.tree_lines(),
+        vec![
+            "Concat",
+            "  Literal('a')",
+            "  Kleene",
+            "    Group",
+            "      Union",
+            "        Literal('b')",
+            "        Class(\\d)",
+        ]
+    );
+}
+
+#[test]
+fn test_a_toggles_the_parse_tree_pane_and_follows_the_edited_pattern() {
+    let mut app = App::new(EngineChoice::Custom);
+    let key = |c| KeyEvent::from(KeyCode::Char(c));
+    app.set_pattern("ab");
+    app.handle_key(key('a'));
+    assert!(app.show_ast);
+    assert_eq!(app.ast_pattern(), "ab");
+    app.handle_key(key('p'));
+    app.handle_key(key('x'));
+    assert_eq!(app.ast_pattern(), "abx");
+    assert!(ast_tree("(x").is_err());
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    app.handle_key(key('a'));
+    assert!(!app.show_ast);
+}
+
+#[test]
+fn test_replace_ranges_substitutes_leftmost_longest() {
+    let replaced = replace_ranges("a cat, a cab", &[2..5, 9..12], "dog");
+    assert_eq!(replaced.text, "a dog, a dog");
+    assert_eq!(replaced.replaced, vec![2..5, 9..12]);
+    // The custom engine reports every accepted substring; the longest wins.
+    let ranges = CustomRegex::new("ab*").unwrap().find_ranges("xabbx");
+    assert_eq!(replace_ranges("xabbx", &ranges, "_").text, "x_x");
+    // Like the regex crate, no empty match right after a replaced one.
+    let ranges = match_ranges(
+        "a*",
+        "baac",
+        &EngineChoice::Builtin,
+        &SearchLimits::default(),
+        None,
+    );
+    assert_eq!(replace_ranges("baac", &ranges, "-").text, "-b-c-");
+    assert_eq!(
+        replace_matches("(", "abc", &EngineChoice::Builtin, "x").map(|r| r.text),
+        Err(validate("(", &EngineChoice::Builtin, &SearchLimits::default()).unwrap_err())
+    );
+}
+
+#[test]
+fn test_replace_mode_previews_live_and_applies_on_enter() {
+    let mut app = App::new(EngineChoice::Builtin);
+    let key = |c| KeyEvent::from(KeyCode::Char(c));
+    app.set_pattern("o+");
+    app.set_text("foo boo");
+    app.handle_key(key('r'));
+    assert!(matches!(app.input_mode, InputMode::EditingReplacement));
+    app.handle_key(key('0'));
+    let preview = app.replace_preview.clone().unwrap().unwrap();
+    assert_eq!(preview.text, "f0 b0");
+    assert_eq!(preview.replaced, vec![1..2, 4..5]);
+    app.handle_key(KeyEvent::from(KeyCode::Enter));
+    assert!(app.replace_preview.is_none());
+    assert_eq!(app.replacement, "0");
+    assert_eq!(app.last_text, "f0 b0");
+    assert_eq!(app.message.as_deref(), Some("Replaced 2 matches"));
+}
+
+#[test]
+fn test_concat_with_a_larger_right_side_matches() {
+    // `(bc|de)*` has more NFA states than `a`, so b's states must be offset by
+    // exactly a's count.
+    let regex = CustomRegex::new("a(bc|de)*").unwrap();
+    let whole = regex::Regex::new("^a(bc|de)*$").unwrap();
+    for text in [
+        "a", "abc", "ade", "abcde", "adebcbc", "ab", "abd", "bc", "abcdex",
+    ] {
+        assert_eq!(
+            regex.find_ranges(text).contains(&(0..text.len())),
+            whole.is_match(text),
+            "Whole-text match of {text:?} differs from builtin"
+        );
+    }
+    let text = "xabcdez ade";
+    assert_eq!(
+        CustomRegex::new_lazy("a(bc|de)*")
+            .unwrap()
+            .find_ranges(text),
+        regex.find_ranges(text)
+    );
+    assert_eq!(regex.rfind(text), Some((8, 11)));
+}
 
-        let pattern2 = "a";
-        let text2 = "abc";
-        let result2 = apply_pattern(pattern2, text2, &EngineChoice::Custommeta);
-        assert!(
-            result2.contains("Matches:"),
-            "Expected matches from customMeta engine."
+#[test]
+fn test_cursor_column_counts_terminal_cells() {
+    assert_eq!(cursor_column("abc", 2), 2);
+    // 日 and 本 are two cells wide.
+    assert_eq!(cursor_column("a日本b", 3), 5);
+    assert_eq!(cursor_column("a日本b", 4), 6);
+    // The combining acute accent takes no cell of its own.
+    assert_eq!(cursor_column("e\u{301}x", 2), 1);
+    assert_eq!(cursor_column("ab", 10), 2);
+}
+
+#[test]
+fn test_find_returns_the_longest_match_at_the_leftmost_start() {
+    let regex = CustomRegex::new("ab*").unwrap();
+    assert_eq!(regex.find("xabbab"), Some((1, 4)));
+    assert_eq!(CustomRegex::new("ab*?").unwrap().find("xabb"), Some((1, 2)));
+    assert_eq!(regex.find("xyz"), None);
+    assert_eq!(
+        first_match(
+            "ab*",
+            "xabbab",
+            &EngineChoice::Builtin,
+            &SearchLimits::default()
+        ),
+        first_match(
+            "ab*",
+            "xabbab",
+            &EngineChoice::Custom,
+            &SearchLimits::default()
+        )
+    );
+}
+
+#[test]
+fn test_whitespace_escapes_match_control_characters() {
+    let regex = CustomRegex::new("a\\tb").unwrap();
+    assert_eq!(regex.find_ranges("a\tb"), vec![0..3]);
+    assert!(regex.find_ranges("atb").is_empty());
+    assert_eq!(
+        CustomRegex::new("\\r\\n").unwrap().find_ranges("x\r\ny"),
+        vec![1..3]
+    );
+    assert_eq!(explain("\\n").unwrap(), "Literal(\\n)");
+    assert_eq!(normalize("a\\tb").unwrap(), "a\\tb");
+    assert!(dfa_table("\\t", DEFAULT_MAX_DFA_STATES)
+        .unwrap()
+        .contains("\\t"));
+}
+
+#[test]
+fn test_escaped_metacharacters_match_literally() {
+    let regex = CustomRegex::new("a\\.b").unwrap();
+    assert_eq!(regex.find_ranges("a.b"), vec![0..3]);
+    assert!(regex.find_ranges("axb").is_empty());
+    assert_eq!(
+        CustomRegex::new("a\\*").unwrap().find_iter("a*"),
+        vec!["a*"]
+    );
+    assert_eq!(normalize("a\\.b").unwrap(), "a\\.b");
+}
+
+#[test]
+fn test_is_match_agrees_with_find() {
+    for (pattern, text) in [
+        ("b+", "abbc"),
+        ("z", "abbc"),
+        ("a*", ""),
+        (r"\bcat\b", "concat cat"),
+        (r"\bcat\b", "concat"),
+        ("(ab|cd)*e", "xxcdabe"),
+    ] {
+        let regex = CustomRegex::new(pattern).unwrap();
+        assert_eq!(
+            regex.is_match(text),
+            regex.find(text).is_some(),
+            "{pattern} in {text:?}"
         );
     }
+    assert_eq!(
+        is_match("b", "abc", &EngineChoice::Custom, &SearchLimits::default()),
+        Ok(true)
+    );
+    assert_eq!(
+        is_match("z", "abc", &EngineChoice::Builtin, &SearchLimits::default()),
+        Ok(false)
+    );
+    assert!(is_match("(", "abc", &EngineChoice::Custom, &SearchLimits::default()).is_err());
+}
+
+#[test]
+fn test_dfa_state_limit_rejects_large_patterns() {
+    let pattern = "(a|b)*a(a|b)(a|b)(a|b)";
+    let err = CustomRegex::with_max_states(pattern, 4).err().unwrap();
+    assert!(
+        err.to_string().contains("DFA too large (>4 states)"),
+        "{err}"
+    );
+    assert!(CustomRegex::with_max_states(pattern, DEFAULT_MAX_DFA_STATES).is_ok());
+}
+
+#[test]
+fn test_histogram_data_counts_matches_per_expression() {
+    let mut app = App::new(EngineChoice::Builtin);
+    app.handle_key(KeyEvent::from(KeyCode::Char('h')));
+    assert!(app.show_histogram);
+    assert!(app.histogram_data().is_empty());
+    app.set_pattern("a");
+    app.add_expression("banana".to_string());
+    app.add_expression("xyz".to_string());
+    app.set_pattern("(");
+    app.add_expression("a".to_string());
+    assert_eq!(
+        app.histogram_data(),
+        vec![
+            ("0".to_string(), 3),
+            ("1".to_string(), 0),
+            ("2".to_string(), 0)
+        ]
+    );
+}
+
+#[test]
+fn test_match_semantics_pick_between_alternatives() {
+    use MatchSemantics::{LeftmostFirst, LeftmostLongest};
+    let found = |pattern: &str, text: &'static str, semantics| -> Vec<&'static str> {
+        CustomRegex::new(pattern)
+            .unwrap()
+            .find_iter_with(text, semantics)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    };
+    assert_eq!(found("a|ab", "ab", LeftmostLongest), ["ab"]);
+    assert_eq!(found("a|ab", "ab", LeftmostFirst), ["a"]);
+    // Lazy quantifiers only matter leftmost-first.
+    assert_eq!(found("a+?", "aa", LeftmostFirst), ["a", "a"]);
+    assert_eq!(found("a+?", "aa", LeftmostLongest), ["aa"]);
+    assert_eq!("first".parse(), Ok(LeftmostFirst));
+    assert!("shortest".parse::<MatchSemantics>().is_err());
+}
+
+#[test]
+fn test_undo_and_redo_restore_edits() {
+    let mut app = App::new(EngineChoice::Builtin);
+    let key = |c| KeyEvent::from(KeyCode::Char(c));
+    let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    app.handle_key(key('t'));
+    for c in "abc def".chars() {
+        app.handle_key(key(c));
+    }
+    app.handle_key(ctrl('w'));
+    assert_eq!(app.input, "abc ");
+    app.handle_key(KeyEvent::from(KeyCode::Backspace));
+    assert_eq!(app.input, "abc");
+
+    app.handle_key(ctrl('z'));
+    assert_eq!((app.input.as_str(), app.character_index), ("abc ", 4));
+    app.handle_key(ctrl('z'));
+    assert_eq!((app.input.as_str(), app.character_index), ("abc def", 7));
+    app.handle_key(ctrl('y'));
+    assert_eq!(app.input, "abc ");
+
+    // A new edit drops what was undone.
+    app.handle_key(key('x'));
+    app.handle_key(ctrl('y'));
+    assert_eq!(app.input, "abc x");
+    for _ in 0..10 {
+        app.handle_key(ctrl('z'));
+    }
+    assert_eq!((app.input.as_str(), app.character_index), ("", 0));
+}
+
+#[test]
+fn test_budgeted_search_is_interrupted_once_out_of_steps() {
+    for pattern in ["a", "b+", r"\bcat\b", "a+?", r"\w*", ""] {
+        let regex = CustomRegex::new(pattern).unwrap();
+        for text in ["aab", "concat cat", "bbb a"] {
+            assert_eq!(
+                regex.find_ranges_budgeted(text, None, 1_000),
+                Ok(regex.find_ranges(text)),
+                "{pattern} in {text:?}"
+            );
+        }
+    }
+
+    let text = "ab".repeat(500);
+    let regex = CustomRegex::new("(a|b)*").unwrap();
+    let err = regex.find_iter_budgeted(&text, 100).unwrap_err();
+    assert_eq!(err.to_string(), "Search interrupted after 100 DFA steps");
+    assert!(regex.find_iter_budgeted("abab", 100).is_ok());
+
+    let limits = SearchLimits {
+        max_steps: Some(100),
+        ..SearchLimits::default()
+    };
+    let result = apply_pattern_limited("(a|b)*", &text, &EngineChoice::Custom, &limits);
+    assert_eq!(result, "Search interrupted after 100 DFA steps");
+}
+
+#[test]
+fn test_compiled_searches_honour_the_search_limits() {
+    let regexer = RegexerBuilder::new("b")
+        .engine(EngineChoice::Custom)
+        .build()
+        .unwrap();
+    let limits = SearchLimits {
+        max_matches: Some(1),
+        ..SearchLimits::default()
+    };
+    assert_eq!(
+        regexer.find_ranges_limited("bbbb", &limits),
+        Ok(vec![0..1, 1..2])
+    );
+    let limits = SearchLimits {
+        max_steps: Some(2),
+        ..SearchLimits::default()
+    };
+    assert!(regexer.find_ranges_limited("abababab", &limits).is_err());
+    assert!(repeat_pattern("b", "abab", &EngineChoice::Custom, &limits, 2, true).is_ok());
+    assert!(repeat_pattern("(", "abab", &EngineChoice::Custom, &limits, 2, true).is_err());
+}
+
+#[test]
+fn test_max_dfa_states_is_per_search() {
+    let pattern = "(a|b)*a(a|b)(a|b)";
+    let tight = SearchLimits {
+        max_dfa_states: Some(4),
+        ..SearchLimits::default()
+    };
+    assert!(validate(pattern, &EngineChoice::Custom, &tight)
+        .unwrap_err()
+        .contains("DFA too large (>4 states)"));
+    // A later search without the limit is unaffected.
+    assert!(validate(pattern, &EngineChoice::Custom, &SearchLimits::default()).is_ok());
+    assert!(is_match(pattern, "abab", &EngineChoice::Custom, &tight).is_err());
+}
+
+#[test]
+fn test_config_parse_and_find() {
+    let config = Config::parse("engine = \"custom\"\nignore_case = true\n").unwrap();
+    assert_eq!(config.engine.as_deref(), Some("custom"));
+    assert_eq!(config.color, None);
+    assert_eq!(config.ignore_case, Some(true));
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+    assert!(Config::parse("colour = \"never\"").is_err());
+    assert!(Config::parse("color = \"sometimes\"").is_err());
+
+    let dir = std::env::temp_dir().join("regexer_config_find");
+    let home = dir.join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let _ = std::fs::remove_file(dir.join(CONFIG_FILE));
+    std::fs::write(home.join(CONFIG_FILE), "").unwrap();
+    assert_eq!(find_config(&dir, Some(&home)), Some(home.join(CONFIG_FILE)));
+    std::fs::write(dir.join(CONFIG_FILE), "").unwrap();
+    assert_eq!(find_config(&dir, Some(&home)), Some(dir.join(CONFIG_FILE)));
+    assert_eq!(find_config(&dir.join("missing"), None), None);
+}
+
+#[test]
+fn test_extract_json_maps_named_groups_per_match() {
+    let text = "GET /a 200\nPOST /b\n";
+    let lines = extract_json(
+        r"(?P<method>[A-Z]+) (?P<path>\S+)(?: (?P<status>\d+))?",
+        text,
+        None,
+    )
+    .unwrap();
+    let objects: Vec<serde_json::Value> = lines
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        objects,
+        vec![
+            serde_json::json!({"method": "GET", "path": "/a", "status": "200"}),
+            serde_json::json!({"method": "POST", "path": "/b", "status": null}),
+        ]
+    );
+    assert_eq!(extract_json(r"(?P<n>\d)", "abc", None).unwrap(), "");
+    assert!(extract_json(r"(\d)", "1", None).is_err());
+}
+
+#[test]
+fn test_pattern_history_navigation_stops_at_both_ends() {
+    let mut app = App::new(EngineChoice::Builtin);
+    let key = |c| KeyEvent::from(KeyCode::Char(c));
+    let up = KeyEvent::from(KeyCode::Up);
+    let down = KeyEvent::from(KeyCode::Down);
+    let clear = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL);
+    for pattern in ["a+", "b+", "b+"] {
+        // `p` starts from the current pattern.
+        app.handle_key(key('p'));
+        app.handle_key(clear);
+        for c in pattern.chars() {
+            app.handle_key(key(c));
+        }
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+    }
+    assert_eq!(app.pattern_history, ["a+", "b+"]);
+
+    app.handle_key(key('p'));
+    app.handle_key(clear);
+    app.handle_key(key('c'));
+    // Down from the new pattern has nowhere to go.
+    app.handle_key(down);
+    assert_eq!((app.input.as_str(), app.history_index), ("c", None));
+
+    app.handle_key(up);
+    assert_eq!((app.input.as_str(), app.history_index), ("b+", Some(1)));
+    assert_eq!(app.character_index, 2);
+    app.handle_key(up);
+    app.handle_key(up);
+    assert_eq!((app.input.as_str(), app.history_index), ("a+", Some(0)));
+
+    app.handle_key(down);
+    assert_eq!((app.input.as_str(), app.history_index), ("b+", Some(1)));
+    app.handle_key(down);
+    assert_eq!((app.input.as_str(), app.history_index), ("c", None));
+    assert_eq!(app.character_index, 1);
+
+    // Leaving the editor forgets the position.
+    app.handle_key(up);
+    app.handle_key(KeyEvent::from(KeyCode::Esc));
+    app.handle_key(key('p'));
+    assert_eq!(app.history_index, None);
+    app.handle_key(up);
+    assert_eq!(app.input, "b+");
+}
+
+#[test]
+fn test_optional_nfa_is_smaller_than_union_with_empty() {
+    let a = || Box::new(RegexAST::Literal('a'));
+    let optional = RegexAST::Optional(a());
+    let desugared = RegexAST::Union(a(), Box::new(RegexAST::Empty));
+    assert_eq!(parse_pattern("a?").unwrap(), optional);
+    assert!(
+        nfa_state_count(&optional) < nfa_state_count(&desugared),
+        "{} vs {}",
+        nfa_state_count(&optional),
+        nfa_state_count(&desugared)
+    );
+
+    let dfa = ast_to_dfa(&optional, DEFAULT_MAX_DFA_STATES).unwrap();
+    assert!(dfa.matches(""));
+    assert!(dfa.matches("a"));
+    assert!(!dfa.matches("aa"));
+    assert_eq!(normalize("(?:ab)?c").unwrap(), "(?:ab)?c");
+}
+
+#[test]
+fn test_offset_unit_counts_chars_after_multibyte_text() {
+    let text = "café x";
+    let x = 6..7;
+    assert_eq!(OffsetUnit::Bytes.convert(text, &x), 6..7);
+    assert_eq!(OffsetUnit::Chars.convert(text, &x), 5..6);
+    assert_eq!(OffsetUnit::Chars.convert(text, &(0..0)), 0..0);
+    assert_eq!("chars".parse(), Ok(OffsetUnit::Chars));
+    assert!("runes".parse::<OffsetUnit>().is_err());
+
+    let limits = SearchLimits::default();
+    let builtin = EngineChoice::Builtin;
+    let positions = |unit| apply_pattern_with_positions("x", text, &builtin, &limits, unit);
+    assert_eq!(positions(OffsetUnit::Bytes), "Matches: [\"x\"@[6..7]]");
+    assert_eq!(positions(OffsetUnit::Chars), "Matches: [\"x\"@[5..6]]");
+}
+
+#[test]
+fn test_split_fields_keeps_empty_fields() {
+    let limits = SearchLimits::default();
+    assert_eq!(
+        split_fields(",", "a,b,,c", &limits),
+        Ok(vec!["a", "b", "", "c"])
+    );
+    assert_eq!(split_fields(",", ",a,", &limits), Ok(vec!["", "a", ""]));
+    assert_eq!(
+        split_fields(r"\s+", "a  b\tc", &limits),
+        Ok(vec!["a", "b", "c"])
+    );
+    assert_eq!(split_fields(",", "abc", &limits), Ok(vec!["abc"]));
+
+    let limits = SearchLimits {
+        max_matches: Some(1),
+        ..SearchLimits::default()
+    };
+    assert_eq!(split_fields(",", "a,b,c", &limits), Ok(vec!["a", "b,c"]));
+    assert!(split_fields("(", "a", &limits).is_err_and(|e| e.starts_with("Invalid pattern")));
+}
+
+#[test]
+fn test_match_ending_at_runs_the_reversed_dfa() {
+    let regex = CustomRegex::new("a*").unwrap();
+    let text = "xaaab";
+    let starts: Vec<Option<usize>> = (0..=text.len())
+        .map(|end| regex.match_ending_at(text, end))
+        .collect();
+    // Each end takes in every 'a' just before it; elsewhere only `a*`'s empty match fits.
+    assert_eq!(
+        starts,
+        [Some(0), Some(1), Some(1), Some(1), Some(1), Some(5)]
+    );
+
+    assert_eq!(
+        CustomRegex::new("b+").unwrap().match_ending_at(text, 4),
+        None
+    );
+    assert_eq!(regex.match_ending_at("é", 1), None);
+    assert_eq!(regex.match_ending_at(text, 9), None);
+
+    // `\b` is judged against the whole text, on both sides of the match.
+    let bounded = CustomRegex::new(r"\bab").unwrap();
+    assert_eq!(bounded.match_ending_at("xab ab", 3), None);
+    assert_eq!(bounded.match_ending_at("xab ab", 6), Some(4));
+
+    for pattern in ["a|ab", "(ab)*c", "a+b?", r"\d+x"] {
+        let custom = CustomRegex::new(pattern).unwrap();
+        let whole = regex::Regex::new(&format!("^(?:{})$", pattern)).unwrap();
+        for text in ["abababc", "aab", "12x3x", ""] {
+            for end in 0..=text.len() {
+                let expected = (0..=end).find(|&start| whole.is_match(&text[start..end]));
+                assert_eq!(
+                    custom.match_ending_at(text, end),
+                    expected,
+                    "{pattern} ending at {end} in {text:?}"
+                );
+            }
+        }
+    }
 }
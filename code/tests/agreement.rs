@@ -0,0 +1,131 @@
+//! Runs every implemented engine over a table of patterns and texts and checks
+//! they report the same matches, so an engine drifting from the others is
+//! caught even when its own tests still pass.
+//!
+//! The builtin engine is the reference. Known differences are allowlisted in
+//! [`KNOWN_DIFFERENCES`]: there the engine must report every reference match
+//! but may add more. Engines that are still placeholders are skipped, and join
+//! the comparison automatically once implemented.
+
+use regexer::engines::{is_implemented, ENGINE_NAMES};
+use regexer::{apply_pattern, EngineChoice, MatchOutcome};
+use std::ops::Range;
+
+struct Case {
+    pattern: &'static str,
+    text: &'static str,
+    /// Leftmost-first, non-overlapping matches as (start, end) byte offsets.
+    expected: &'static [(usize, usize)],
+}
+
+const CASES: &[Case] = &[
+    // Literals
+    Case {
+        pattern: "abc",
+        text: "xabcabc",
+        expected: &[(1, 4), (4, 7)],
+    },
+    Case {
+        pattern: "x",
+        text: "yyy",
+        expected: &[],
+    },
+    Case {
+        pattern: "",
+        text: "ab",
+        expected: &[(0, 0), (1, 1), (2, 2)],
+    },
+    // Alternations
+    Case {
+        pattern: "cat|dog",
+        text: "cat dog bird",
+        expected: &[(0, 3), (4, 7)],
+    },
+    Case {
+        pattern: "a|ab",
+        text: "ab",
+        expected: &[(0, 1)],
+    },
+    Case {
+        pattern: "(b|c)d",
+        text: "bd cd ad",
+        expected: &[(0, 2), (3, 5)],
+    },
+    // Quantifiers
+    Case {
+        pattern: "ab*",
+        text: "a ab abbb",
+        expected: &[(0, 1), (2, 4), (5, 9)],
+    },
+    Case {
+        pattern: "(ab)*",
+        text: "ababx",
+        expected: &[(0, 4), (5, 5)],
+    },
+    Case {
+        pattern: "a?b+",
+        text: "abb b",
+        expected: &[(0, 3), (4, 5)],
+    },
+    Case {
+        pattern: "a(b|c)*d",
+        text: "abd acbd ad",
+        expected: &[(0, 3), (4, 8), (9, 11)],
+    },
+];
+
+/// (engine, pattern) pairs allowed to report more than the reference. The
+/// custom engine lists every substring its DFA accepts, so any pattern that can
+/// stop early (`ab*` at "a", "ab", "abb", ...) gives it extra ranges.
+const KNOWN_DIFFERENCES: &[(&str, &str)] = &[
+    ("custom", "a|ab"),
+    ("custom", "ab*"),
+    ("custom", "(ab)*"),
+    ("custom", "a?b+"),
+];
+
+fn ranges(outcome: MatchOutcome) -> Vec<Range<usize>> {
+    match outcome {
+        MatchOutcome::Matches(ranges) => ranges,
+        MatchOutcome::NoMatches => Vec::new(),
+        other => panic!("Expected a search result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_implemented_engines_agree() {
+    let engines: Vec<(&str, EngineChoice)> = ENGINE_NAMES
+        .iter()
+        .map(|&name| (name, name.parse().unwrap()))
+        .filter(|(_, engine)| is_implemented(engine))
+        .collect();
+    assert!(engines.len() > 1, "Expected several engines to compare.");
+
+    for case in CASES {
+        let expected: Vec<Range<usize>> = case
+            .expected
+            .iter()
+            .map(|&(start, end)| start..end)
+            .collect();
+        for (name, engine) in &engines {
+            let found = ranges(apply_pattern(case.pattern, case.text, engine));
+            if KNOWN_DIFFERENCES.contains(&(*name, case.pattern)) {
+                assert!(
+                    expected.iter().all(|range| found.contains(range)),
+                    "{} missed a match of {:?} in {:?}: expected {:?} among {:?}",
+                    name,
+                    case.pattern,
+                    case.text,
+                    expected,
+                    found
+                );
+            } else {
+                assert_eq!(
+                    found, expected,
+                    "{} disagrees on {:?} in {:?}",
+                    name, case.pattern, case.text
+                );
+            }
+        }
+    }
+}
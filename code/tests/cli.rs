@@ -0,0 +1,897 @@
+use std::process::Command;
+
+/// The binary, run from an empty directory that is also its `HOME`, so a
+/// developer's own `.regexerrc` cannot change what the tests see.
+fn regexer() -> Command {
+    let dir = std::env::temp_dir().join("regexer_cli_home");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_regexer"));
+    command.current_dir(&dir).env("HOME", &dir);
+    command
+}
+
+#[test]
+fn test_validate_valid_pattern_exits_zero_silently() {
+    let output = regexer().args(["--validate", "ab*"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(
+        output.stdout.is_empty(),
+        "Expected no output for a valid pattern."
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_validate_invalid_pattern_exits_non_zero() {
+    let output = regexer()
+        .args(["--validate", "--engine", "custom", "(ab"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Invalid pattern:"),
+        "Expected the compile error on stderr, got {stderr:?}"
+    );
+}
+
+#[test]
+fn test_color_always_wraps_matches_and_never_does_not() {
+    let output = regexer()
+        .args(["-n", "--color", "always", "ab", "xaby"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1:x\x1b[1;31mab\x1b[0my"),
+        "Expected the match wrapped in escape codes, got {stdout:?}"
+    );
+
+    let output = regexer()
+        .args(["-n", "--color", "never", "ab", "xaby"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1:xaby"));
+    assert!(
+        !stdout.contains('\x1b'),
+        "Expected no escape codes, got {stdout:?}"
+    );
+}
+
+#[test]
+fn test_testcase_reads_pattern_and_text_from_one_file() {
+    let path = std::env::temp_dir().join("regexer_cli_testcase.txt");
+    std::fs::write(&path, "ab*\nxabb\nab\n").unwrap();
+    let output = regexer()
+        .args(["--testcase", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(r#"Matches: ["abb", "ab"]"#),
+        "Expected both lines searched, got {stdout:?}"
+    );
+}
+
+#[test]
+fn test_max_matches_truncates_output() {
+    let output = regexer()
+        .args(["--max-matches", "2", "a", "aaaa"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(r#"Matches: ["a", "a"] ... (truncated)"#),
+        "Expected two matches and the truncation note, got {stdout:?}"
+    );
+}
+
+#[test]
+fn test_set_rejects_non_builtin_engines() {
+    let path = std::env::temp_dir().join("regexer_cli_set_engine.txt");
+    std::fs::write(&path, "a\nb\n").unwrap();
+    let file = path.to_str().unwrap();
+    let output = regexer()
+        .args(["--set", "--patterns-file", file, "--engine", "custom", "ab"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("--set needs the builtin engine"));
+}
+
+#[test]
+fn test_explain_shows_groups_only_for_parenthesized_patterns() {
+    let explain = |pattern: &str| {
+        let output = regexer()
+            .args(["--explain", "--engine", "custom", pattern])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert_eq!(explain("(ab)"), "Group(Concat(Literal(a), Literal(b)))\n");
+    assert_eq!(explain("ab"), "Concat(Literal(a), Literal(b))\n");
+}
+
+#[test]
+fn test_csv_quotes_matches_with_commas() {
+    let output = regexer()
+        .args(["--csv", r#"a,"?b"#, r#"xa,b a,"b"#])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "index,match,start,end\n0,\"a,b\",1,4\n1,\"a,\"\"b\",5,9\n"
+    );
+}
+
+#[test]
+fn test_csv_reports_an_invalid_pattern_on_stderr() {
+    let output = regexer().args(["--csv", "(", "abc"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Invalid pattern"));
+}
+
+#[test]
+fn test_missing_file_reports_error_and_exits_non_zero() {
+    let output = regexer()
+        .args(["-f", "/nonexistent/regexer-input.txt", "ab"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cannot read file /nonexistent/regexer-input.txt:"),
+        "Expected a clear error on stderr, got {stderr:?}"
+    );
+}
+
+#[test]
+fn test_last_reports_only_the_final_match() {
+    for engine in ["builtin", "custom"] {
+        let output = regexer()
+            .args(["--last", "--engine", engine, "a", "aba"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.ends_with("Last match: \"a\"@[2..3]\n"),
+            "Expected the match at offset 2 from {engine}, got {stdout:?}"
+        );
+    }
+}
+
+#[test]
+fn test_format_template_renders_each_match() {
+    let output = regexer()
+        .args(["--format", "{index}: {match} @{start}-{end}", "a+", "baa a"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "0: aa @1-3\n1: a @4-5\n"
+    );
+
+    let output = regexer()
+        .args(["--format", "{text}", "a", "a"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown placeholder '{text}'"));
+}
+
+#[test]
+fn test_unique_counts_distinct_matches() {
+    let output = regexer()
+        .args(["--unique", "\\w+", "aa bb aa"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with("      2 \"aa\"\n      1 \"bb\"\n"),
+        "Expected aa twice then bb once, got {stdout:?}"
+    );
+
+    let output = regexer()
+        .args(["--unique", "--max-matches", "2", "a", "aaaa"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with("      2 \"a\"\n... (truncated)\n"),
+        "Expected only two matches counted, got {stdout:?}"
+    );
+}
+
+#[test]
+fn test_multiline_anchors_match_every_line() {
+    let haystack = "line one\nline two\nnot a line";
+    let matches = |extra: &[&str]| {
+        let output = regexer()
+            .args(extra)
+            .args(["--csv", "^line", haystack])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).lines().count() - 1
+    };
+    assert_eq!(matches(&[]), 1);
+    assert_eq!(matches(&["-M"]), 2);
+}
+
+#[test]
+fn test_dotall_lets_dot_match_newline() {
+    let matched = |extra: &[&str]| {
+        let output = regexer()
+            .args(extra)
+            .args(["--csv", "a.b", "a\nb"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).lines().count() > 1
+    };
+    assert!(!matched(&[]));
+    assert!(matched(&["-s"]));
+}
+
+#[test]
+fn test_overlapping_reports_every_start() {
+    for engine in ["builtin", "custom"] {
+        let output = regexer()
+            .args(["--overlapping", "--engine", engine, "aa", "aaaa"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.ends_with("Matches: [\"aa\"@[0..2], \"aa\"@[1..3], \"aa\"@[2..4]]\n"),
+            "Unexpected {engine} output {stdout:?}"
+        );
+
+        let output = regexer()
+            .args(["--overlapping", "--engine", engine, "ab*", "abbab"])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.ends_with("Matches: [\"a\"@[0..1], \"a\"@[3..4]]\n"),
+            "Unexpected {engine} output {stdout:?}"
+        );
+    }
+}
+
+#[test]
+fn test_fixed_matches_metacharacters_literally() {
+    for engine in ["builtin", "custom"] {
+        let output = regexer()
+            .args(["-F", "--engine", engine, "a.c", "abc a.c"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.ends_with("Matches: [\"a.c\"@[4..7]]\n"),
+            "Expected only the literal a.c from {engine}, got {stdout:?}"
+        );
+    }
+}
+
+#[test]
+fn test_context_prints_surrounding_lines() {
+    let text = "one\ntwo\nthree\nfour\nfive\nsix\nseven";
+    let output = regexer()
+        .args(["-n", "-C", "1", "fou", text])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("3-three\n4:four\n5-five\n"));
+
+    // Separate windows get a separator; touching ones merge.
+    let output = regexer()
+        .args(["-n", "-B", "1", "on|fiv|six", text])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("1:one\n--\n4-four\n5:five\n6:six\n"));
+}
+
+#[test]
+fn test_generate_lists_accepted_strings() {
+    let output = regexer()
+        .args(["--generate", "10", "--engine", "custom", "a(b|c)"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ab\nac\n");
+}
+
+#[test]
+fn test_repeat_reuse_compiled_reports_compile_errors() {
+    let output = regexer()
+        .args(["--repeat", "3", "--reuse-compiled", "(", "abc"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid pattern"), "Got {stderr:?}");
+
+    let output = regexer()
+        .args(["--repeat", "3", "--reuse-compiled", "--engine", "custom"])
+        .args(["--max-dfa-states", "2", "abcdef", "abcdef"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "--max-dfa-states was ignored");
+}
+
+#[test]
+fn test_repeat_prints_one_result_and_an_average() {
+    for extra in [&[][..], &["--reuse-compiled"][..]] {
+        let output = regexer()
+            .args([
+                "--repeat", "3", "--timing", "--color", "never", "b+", "abbcb",
+            ])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(stdout.matches("Matches:").count(), 1, "Got {stdout:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("per iteration over 3 runs"),
+            "Expected an average timing summary, got {stderr:?}"
+        );
+        assert_eq!(
+            stderr.contains("compiling once"),
+            !extra.is_empty(),
+            "Got {stderr:?}"
+        );
+    }
+}
+
+#[test]
+fn test_several_files_prefix_each_match_line_with_its_name() {
+    let dir = std::env::temp_dir();
+    let first = dir.join("regexer_cli_multi_first.txt");
+    let second = dir.join("regexer_cli_multi_second.txt");
+    let missing = dir.join("regexer_cli_multi_missing.txt");
+    std::fs::write(&first, "cat\ndog\ncatalog\n").unwrap();
+    std::fs::write(&second, "bird\nconcat\n").unwrap();
+    let (first, second, missing) = (
+        first.to_str().unwrap(),
+        second.to_str().unwrap(),
+        missing.to_str().unwrap(),
+    );
+    let output = regexer()
+        .args(["-n", "cat", "-f", first, "-f", missing, "-f", second])
+        .output()
+        .unwrap();
+    std::fs::remove_file(first).unwrap();
+    std::fs::remove_file(second).unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!("{first}:1:cat\n{first}:3:catalog\n{second}:2:concat\n");
+    assert!(
+        stdout.ends_with(&expected),
+        "Expected prefixed lines from both files, got {stdout:?}"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("Cannot read file {missing}")),
+        "Expected a warning for the missing file, got {stderr:?}"
+    );
+}
+
+#[test]
+fn test_several_files_report_search_errors_per_file_and_exit_non_zero() {
+    let dir = std::env::temp_dir();
+    let first = dir.join("regexer_cli_multi_error_first.txt");
+    let second = dir.join("regexer_cli_multi_error_second.txt");
+    std::fs::write(&first, "cat\n").unwrap();
+    std::fs::write(&second, "concat\n").unwrap();
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+    let output = regexer()
+        .args(["-n", "a(", "-f", first, "-f", second])
+        .output()
+        .unwrap();
+    std::fs::remove_file(first).unwrap();
+    std::fs::remove_file(second).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for file in [first, second] {
+        assert!(
+            stderr.contains(&format!("{file}: Invalid pattern")),
+            "Expected a warning naming {file}, got {stderr:?}"
+        );
+    }
+}
+
+#[test]
+fn test_expect_reports_pass_and_fail_per_case() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/expect.tsv");
+    let output = regexer().args(["--expect", fixture]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("PASS line 2: \"ab\" in \"xab ab\"\n"),
+        "Got {stdout:?}"
+    );
+    assert!(
+        stdout.ends_with("3 passed, 0 failed (builtin)\n"),
+        "Got {stdout:?}"
+    );
+
+    // The custom engine reports every accepted substring: six for `b+` in `abbb`.
+    let output = regexer()
+        .args(["--expect", fixture, "--engine", "custom"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("FAIL line 4: \"b+\" in \"abbb\": expected 1 matches, got 6"),
+        "Got {stdout:?}"
+    );
+    assert!(
+        stdout.ends_with("2 passed, 1 failed (custom)\n"),
+        "Got {stdout:?}"
+    );
+}
+
+#[test]
+fn test_first_reports_the_leftmost_match_and_exits_one_without_one() {
+    for engine in ["builtin", "custom", "custommeta"] {
+        let output = regexer()
+            .args(["--first", "--engine", engine, "a", "baba"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.ends_with("First match: \"a\"@[1..2]\n"),
+            "Expected the match at offset 1 from {engine}, got {stdout:?}"
+        );
+    }
+    let output = regexer().args(["--first", "z", "baba"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("No matches found.\n"));
+}
+
+#[test]
+fn test_quiet_prints_nothing_and_reports_through_the_exit_code() {
+    for engine in ["builtin", "custom", "custommeta"] {
+        let output = regexer()
+            .args(["-q", "--engine", engine, "b+", "abbc"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "Expected a match from {engine}");
+        assert!(output.stdout.is_empty() && output.stderr.is_empty());
+
+        let output = regexer()
+            .args(["--quiet", "--engine", engine, "z", "abbc"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        assert!(output.stdout.is_empty() && output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn test_max_dfa_states_rejects_patterns_that_need_more() {
+    let args = ["--engine", "custom", "(a|b)*a(a|b)(a|b)", "abab"];
+    let output = regexer()
+        .args(["--max-dfa-states", "4"])
+        .args(args)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("DFA too large (>4 states)"),
+        "Got {stdout:?}"
+    );
+
+    let output = regexer().args(args).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("DFA too large"), "Got {stdout:?}");
+}
+
+#[test]
+fn test_completions_prints_a_bash_script_with_every_flag() {
+    let output = regexer().args(["--completions", "bash"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--engine"), "Got {stdout:?}");
+    assert!(stdout.contains("custommeta"), "Got {stdout:?}");
+    assert!(!stdout.contains("Running regexer"));
+}
+
+#[test]
+fn test_input_encoding_decodes_latin1_files() {
+    let path = std::env::temp_dir().join("regexer_cli_latin1.txt");
+    // 0xE9 is 'é' in Latin-1 and never valid on its own in UTF-8.
+    std::fs::write(&path, b"caf\xe9 au lait\n").unwrap();
+    let file = path.to_str().unwrap();
+
+    let output = regexer()
+        .args(["--input-encoding", "latin1", "caf.", "-f", file])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Matches: [\"café\"]"), "Got {stdout:?}");
+
+    let output = regexer().args(["caf.", "-f", file]).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid UTF-8 at byte 3"), "Got {stderr:?}");
+}
+
+#[test]
+fn test_match_semantics_choose_longest_or_first_alternative() {
+    let run = |args: &[&str]| {
+        let output = regexer().args(args).output().unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    let (_, stdout) = run(&[
+        "--engine",
+        "custom",
+        "--match-semantics",
+        "longest",
+        "a|ab",
+        "ab",
+    ]);
+    assert!(stdout.ends_with("Matches: [\"ab\"]\n"), "Got {stdout:?}");
+    for engine in ["custom", "builtin"] {
+        let (_, stdout) = run(&[
+            "--engine",
+            engine,
+            "--match-semantics",
+            "first",
+            "a|ab",
+            "ab",
+        ]);
+        assert!(
+            stdout.ends_with("Matches: [\"a\"]\n"),
+            "Got {stdout:?} from {engine}"
+        );
+    }
+    let (code, _) = run(&["--match-semantics", "longest", "a|ab", "ab"]);
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn test_no_newline_leaves_out_the_trailing_newline() {
+    let output = regexer().args(["-z", "b", "abc"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("Matches: [\"b\"]"), "Got {stdout:?}");
+
+    let output = regexer()
+        .args(["--no-newline", "--format", "{match}", "a", "aba"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\na");
+}
+
+#[test]
+fn test_max_steps_interrupts_a_long_custom_search() {
+    let text = "ab".repeat(200);
+    let output = regexer()
+        .args(["--engine", "custom", "--max-steps", "50", "(a|b)*", &text])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with("Search interrupted after 50 DFA steps\n"),
+        "Got {stdout:?}"
+    );
+
+    let output = regexer()
+        .args(["--max-steps", "50", "a", "abc"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_regexerrc_sets_defaults_that_flags_override() {
+    let dir = std::env::temp_dir().join("regexer_cli_regexerrc");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join(".regexerrc"),
+        "engine = \"custom\"\nignore_case = true\n",
+    )
+    .unwrap();
+
+    let output = regexer()
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .args(["ab", "xAB"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Engine: custom"), "Got {stdout:?}");
+    assert!(stdout.contains("Matches: [\"AB\"]"), "Got {stdout:?}");
+
+    let output = regexer()
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .args(["--engine", "builtin", "--ignore-case=false", "ab", "xAB"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Engine: builtin"), "Got {stdout:?}");
+    assert!(!stdout.contains("Matches: ["), "Got {stdout:?}");
+
+    std::fs::write(dir.join(".regexerrc"), "colour = \"never\"\n").unwrap();
+    let output = regexer()
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .args(["a", "a"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(".regexerrc"));
+}
+
+#[test]
+fn test_extract_prints_named_groups_as_json_lines() {
+    let line = "2024-05-01T12:00:00 level=error msg=\"disk full\"";
+    let output = regexer()
+        .args([
+            "--extract",
+            r#"level=(?P<level>\w+) msg="(?P<msg>[^"]*)"(?: code=(?P<code>\d+))?"#,
+            line,
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let object: serde_json::Value = serde_json::from_str(stdout.trim_end()).unwrap();
+    assert_eq!(
+        object,
+        serde_json::json!({"level": "error", "msg": "disk full", "code": null})
+    );
+
+    let output = regexer()
+        .args(["--extract", "--engine", "custom", "(?P<a>a)", "a"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_extract_reports_an_invalid_pattern_on_stderr() {
+    let output = regexer().args(["--extract", "(", "abc"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Invalid pattern"));
+
+    let output = regexer()
+        .args(["--extract", "(a)", "abc"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("--extract needs"));
+}
+
+#[test]
+fn test_offset_unit_reports_char_positions() {
+    let output = regexer()
+        .args(["--show-positions", "--offset-unit", "chars", "x", "café x"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Matches: [\"x\"@[5..6]]"), "Got {stdout:?}");
+
+    let output = regexer().args(["--csv", "x", "café x"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("0,x,6,7"), "Got {stdout:?}");
+}
+
+#[test]
+fn test_placeholder_engine_warns_and_fails() {
+    let output = regexer()
+        .args(["--engine", "meta", "a", "abc"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("engine 'meta' is not yet implemented; results are placeholders"),
+        "Got {stderr:?}"
+    );
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("placeholder"));
+}
+
+#[test]
+fn test_pattern_and_text_from_environment() {
+    let output = regexer()
+        .env("REGEXER_TEST_PATTERN", r#""[^"]*""#)
+        .args(["--pattern-env", "REGEXER_TEST_PATTERN", r#"say "hi" twice"#])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(r#"Matches: ["\"hi\""]"#), "Got {stdout:?}");
+
+    let output = regexer()
+        .env("REGEXER_TEST_PATTERN", "b+")
+        .env("REGEXER_TEST_TEXT", "abbc")
+        .args([
+            "--pattern-env",
+            "REGEXER_TEST_PATTERN",
+            "--text-env",
+            "REGEXER_TEST_TEXT",
+        ])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(r#"Matches: ["bb"]"#), "Got {stdout:?}");
+
+    let output = regexer()
+        .env_remove("REGEXER_TEST_UNSET")
+        .args(["--pattern-env", "REGEXER_TEST_UNSET", "abc"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--pattern-env REGEXER_TEST_UNSET: environment variable not found"),
+        "Got {stderr:?}"
+    );
+}
+
+#[test]
+fn test_split_prints_one_field_per_line() {
+    let output = regexer().args(["--split", ",", "a,b,,c"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\nb\n\nc\n");
+
+    let output = regexer()
+        .args(["--split", "--engine", "custom", ",", "a,b"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let output = regexer().args(["--split", "(", "a(b"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Invalid pattern"));
+}
+
+#[test]
+fn test_large_streamed_file_compiles_the_pattern_once() {
+    let path = std::env::temp_dir().join("regexer_cli_large_stream.txt");
+    let text: String = (0..20_000)
+        .map(|i| {
+            if i % 7 == 0 {
+                "xxabcxx\n"
+            } else {
+                "lorem ipsum dolor sit amet\n"
+            }
+        })
+        .collect();
+    std::fs::write(&path, text).unwrap();
+
+    let started = std::time::Instant::now();
+    let output = regexer()
+        .args([
+            "-c",
+            "--engine",
+            "custom",
+            "abc",
+            "-f",
+            path.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    let elapsed = started.elapsed();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("2858\n"), "Got {stdout:?}");
+    // Recompiling per line took over a minute; one compile takes about a second.
+    assert!(
+        elapsed < std::time::Duration::from_secs(20),
+        "Took {elapsed:?}"
+    );
+}
+
+#[test]
+fn test_engine_info_groups_column_matches_what_groups_accepts() {
+    let output = regexer().arg("--engine-info").output().unwrap();
+    let table = String::from_utf8_lossy(&output.stdout).into_owned();
+    for row in table.lines().skip(1) {
+        let columns: Vec<&str> = row.split_whitespace().collect();
+        let (engine, groups) = (columns[0], columns[2]);
+        let output = regexer()
+            .args(["--groups", "--engine", engine, "(a)", "a"])
+            .output()
+            .unwrap();
+        assert_eq!(
+            output.status.success(),
+            groups == "yes",
+            "--groups with {engine} disagrees with --engine-info: {row:?}"
+        );
+    }
+}
+
+#[test]
+fn test_inverted_line_match_reports_an_invalid_pattern() {
+    let path = std::env::temp_dir().join("regexer_cli_invert_invalid.txt");
+    std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+    let file = path.to_str().unwrap();
+    for args in [
+        vec!["-v", "a(", "-f", file],
+        vec!["-c", "-v", "a(", "-f", file],
+        vec!["-v", "a(", "-f", file, "--context", "1"],
+    ] {
+        let output = regexer().args(&args).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            !stdout.contains("two") && !stdout.ends_with("3\n"),
+            "{args:?}: {stdout:?}"
+        );
+        assert!(
+            String::from_utf8_lossy(&output.stderr).starts_with("Invalid pattern"),
+            "{args:?}"
+        );
+    }
+}
+
+#[test]
+fn test_or_reports_the_half_that_does_not_parse() {
+    for args in [
+        vec!["--engine", "custom", "--or", "c", "a)(b", "ac"],
+        vec!["--or", "c", "a)|(?:b", "ac"],
+        vec!["--or", "a)(b", "c", "ac"],
+    ] {
+        let output = regexer().args(&args).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        assert!(
+            !String::from_utf8_lossy(&output.stdout).contains("Matches"),
+            "{args:?}"
+        );
+        assert!(
+            String::from_utf8_lossy(&output.stderr).starts_with("Invalid"),
+            "{args:?}"
+        );
+    }
+}
+
+#[test]
+fn test_conflicting_flags_are_rejected_by_the_parser() {
+    for args in [
+        &["--csv", "-n", "a", "a"][..],
+        &["--extract", "--first", "a", "a"],
+        &["--split", "--match-semantics", "first", "a", "a"],
+        &["--reuse-compiled", "a", "a"],
+        &["--repeat", "0", "a", "a"],
+        &["--json", "a", "a"],
+        &["-C", "1", "a", "a"],
+        &["--set", "a", "a"],
+    ] {
+        let output = regexer().args(args).output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{args:?} was accepted");
+        assert!(output.stdout.is_empty());
+    }
+
+    let output = regexer()
+        .args(["--engine", "all", "--csv", "a", "a"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be combined with --csv"));
+}
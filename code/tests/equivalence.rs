@@ -0,0 +1,131 @@
+//! Cross-checks the custom engine against the regex crate on random patterns
+//! over the grammar both understand: literals, `|`, `*` and `()`.
+//!
+//! The custom engine reports every accepted substring while the regex crate
+//! reports non-overlapping leftmost-first matches, so the two lists differ by
+//! design. What must agree is the language: a text matches the whole pattern
+//! in one engine exactly when it does in the other, and every match the regex
+//! crate reports is one of the custom engine's ranges. Asked for leftmost-first
+//! matches instead, the custom engine must report exactly the regex crate's.
+//!
+//! Cases come from proptest, which shrinks a divergence to a minimal pattern
+//! and text and saves its seed under `proptest-regressions/` so it is retried
+//! first next time. Run with `cargo test --test equivalence`; set
+//! `PROPTEST_CASES` for more or fewer cases and `PROPTEST_RNG_SEED` to fix
+//! the seed.
+
+use proptest::prelude::*;
+use regex::Regex;
+use regexer::{CustomRegex, MatchSemantics};
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Clone, Debug)]
+enum Pattern {
+    Literal(char),
+    Concat(Box<Pattern>, Box<Pattern>),
+    Union(Box<Pattern>, Box<Pattern>),
+    Kleene(Box<Pattern>),
+    Group(Box<Pattern>),
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Literal(c) => write!(f, "{}", c),
+            Pattern::Concat(a, b) => write!(f, "{}{}", a, b),
+            // Parenthesised so the surrounding concatenation cannot split it.
+            Pattern::Union(a, b) => write!(f, "({}|{})", a, b),
+            Pattern::Kleene(a) => match **a {
+                Pattern::Literal(_) | Pattern::Group(_) => write!(f, "{}*", a),
+                _ => write!(f, "({})*", a),
+            },
+            Pattern::Group(a) => write!(f, "({})", a),
+        }
+    }
+}
+
+/// Patterns over `a`, `b` and `c`, nested up to four levels deep.
+fn pattern() -> impl Strategy<Value = Pattern> {
+    let literal = prop::sample::select(vec!['a', 'b', 'c']).prop_map(Pattern::Literal);
+    literal.prop_recursive(4, 32, 2, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone())
+                .prop_map(|(a, b)| Pattern::Concat(Box::new(a), Box::new(b))),
+            (inner.clone(), inner.clone())
+                .prop_map(|(a, b)| Pattern::Union(Box::new(a), Box::new(b))),
+            inner.clone().prop_map(|a| Pattern::Kleene(Box::new(a))),
+            inner.prop_map(|a| Pattern::Group(Box::new(a))),
+        ]
+    })
+}
+
+/// Describes how the engines disagree on `pattern` over `text`, if they do.
+fn divergence(pattern: &Pattern, text: &str) -> Option<String> {
+    let source = pattern.to_string();
+    let custom = match CustomRegex::new(&source) {
+        Ok(custom) => custom,
+        Err(e) => return Some(format!("custom engine rejected the pattern: {}", e)),
+    };
+    let whole = Regex::new(&format!("^(?:{})$", source)).unwrap();
+    let custom_ranges = custom.find_ranges(text);
+    let custom_whole = custom_ranges.contains(&(0..text.len()));
+    if custom_whole != whole.is_match(text) {
+        return Some(format!(
+            "whole-text match: custom {}, builtin {}",
+            custom_whole,
+            whole.is_match(text)
+        ));
+    }
+    let builtin_ranges: Vec<Range<usize>> = Regex::new(&source)
+        .unwrap()
+        .find_iter(text)
+        .map(|m| m.range())
+        .collect();
+    let missing: Vec<&Range<usize>> = builtin_ranges
+        .iter()
+        .filter(|range| !range.is_empty() && !custom_ranges.contains(range))
+        .collect();
+    if !missing.is_empty() {
+        return Some(format!(
+            "builtin matches missing from custom: {:?}",
+            missing
+        ));
+    }
+    let first = custom.find_iter_with(text, MatchSemantics::LeftmostFirst);
+    if first != builtin_ranges {
+        return Some(format!(
+            "leftmost-first matches: custom {:?}, builtin {:?}",
+            first, builtin_ranges
+        ));
+    }
+    None
+}
+
+/// 1000 cases, unless `PROPTEST_CASES` asks for another number, which
+/// `ProptestConfig::default` reads.
+fn config() -> ProptestConfig {
+    if std::env::var_os("PROPTEST_CASES").is_some() {
+        ProptestConfig::default()
+    } else {
+        ProptestConfig::with_cases(1000)
+    }
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn test_custom_engine_agrees_with_builtin_on_random_patterns(
+        pattern in pattern(),
+        text in "[abc]{0,7}",
+    ) {
+        prop_assert_eq!(
+            divergence(&pattern, &text),
+            None,
+            "Engines diverge on pattern {:?} over text {:?}",
+            pattern.to_string(),
+            text
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use regexer::{apply_pattern, match_all, CustomRegex, EngineChoice, MatchOutcome, RegexerBuilder};
+
+#[test]
+fn test_match_all_builtin_reports_ranges() {
+    let outcome = match_all("ab", "xab ab", EngineChoice::Builtin);
+    assert_eq!(outcome, MatchOutcome::Matches(vec![1..3, 4..6]));
+}
+
+#[test]
+fn test_match_all_reports_invalid_and_placeholder_engines() {
+    assert!(
+        matches!(
+            match_all("(", "abc", EngineChoice::Builtin),
+            MatchOutcome::InvalidPattern(_)
+        ),
+        "Expected an unmatched group to be reported as invalid."
+    );
+    assert_eq!(
+        match_all("a", "abc", EngineChoice::Meta),
+        MatchOutcome::NotImplemented
+    );
+    assert_eq!(
+        match_all("z", "abc", EngineChoice::Custom),
+        MatchOutcome::NoMatches
+    );
+}
+
+#[test]
+fn test_public_custom_regex_and_apply_pattern() {
+    let regex = CustomRegex::new("ab").unwrap();
+    assert_eq!(regex.find_iter("abcab"), vec!["ab", "ab"]);
+    let outcome = apply_pattern("ab", "abcab", &EngineChoice::Custom);
+    assert_eq!(outcome, MatchOutcome::Matches(vec![0..2, 3..5]));
+    assert_eq!(outcome.describe("abcab"), "Matches: [\"ab\", \"ab\"]");
+}
+
+#[test]
+fn test_apply_pattern_reports_outcome_variants() {
+    for engine in [
+        EngineChoice::Builtin,
+        EngineChoice::Custom,
+        EngineChoice::Custommeta,
+    ] {
+        assert_eq!(
+            apply_pattern("b", "abbc", &engine),
+            MatchOutcome::Matches(vec![1..2, 2..3]),
+            "{engine:?}"
+        );
+        assert_eq!(
+            apply_pattern("z", "abbc", &engine),
+            MatchOutcome::NoMatches,
+            "{engine:?}"
+        );
+        let invalid = apply_pattern("(b", "abbc", &engine);
+        assert!(
+            matches!(invalid, MatchOutcome::InvalidPattern(_)),
+            "Expected {engine:?} to reject an unclosed group, got {invalid:?}"
+        );
+        assert!(invalid.describe("abbc").starts_with("Invalid pattern: "));
+    }
+    assert_eq!(
+        apply_pattern("b", "abc", &EngineChoice::Pikevm),
+        MatchOutcome::NotImplemented
+    );
+}
+
+#[test]
+fn test_regexer_builder_reuses_a_configured_matcher() {
+    let regexer = RegexerBuilder::new("ab")
+        .engine(EngineChoice::Custom)
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert_eq!(regexer.find_iter("xAB ab"), vec!["AB", "ab"]);
+    assert_eq!(
+        regexer.match_all("aB ab"),
+        MatchOutcome::Matches(vec![0..2, 3..5])
+    );
+    assert_eq!(regexer.match_all("xyz"), MatchOutcome::NoMatches);
+
+    let builtin = RegexerBuilder::new("a+b").build().unwrap();
+    assert_eq!(builtin.find_iter("aab AB"), vec!["aab"]);
+
+    assert!(RegexerBuilder::new("(").build().is_err());
+    assert!(RegexerBuilder::new("a")
+        .engine(EngineChoice::Meta)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_binary_types_come_from_the_library() {
+    // main.rs has no inline copies of these; it imports the ones below.
+    let app = regexer::app::App::new(EngineChoice::Custom);
+    assert!(app.status_line().starts_with("Engine: custom |"));
+    assert_eq!(
+        regexer::engines::apply_pattern("a", "ba", &EngineChoice::Custom),
+        apply_pattern("a", "ba", &EngineChoice::Custom)
+    );
+}
+
+#[test]
+fn test_regexer_is_match_agrees_with_find_ranges() {
+    for engine in [
+        EngineChoice::Builtin,
+        EngineChoice::Custom,
+        EngineChoice::Custommeta,
+    ] {
+        let regexer = RegexerBuilder::new("ab+")
+            .engine(engine)
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        for text in ["xABb", "ba", ""] {
+            assert_eq!(
+                regexer.is_match(text),
+                !regexer.find_ranges(text).is_empty(),
+                "{engine:?} on {text:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_regexer_builder_takes_a_dfa_state_limit() {
+    let pattern = "(a|b)*a(a|b)(a|b)";
+    let build = |limit| {
+        RegexerBuilder::new(pattern)
+            .engine(EngineChoice::Custom)
+            .max_dfa_states(limit)
+            .build()
+    };
+    assert!(build(4).is_err_and(|e| e.contains("DFA too large (>4 states)")));
+    assert!(build(100).unwrap().is_match("babb"));
+}